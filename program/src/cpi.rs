@@ -0,0 +1,180 @@
+// Calls the program from another program. Every wrapper builds the instruction with the
+// `instruction` module and invokes it with the given account infos, the `_signed` ones along
+// with the seeds of the PDAs of the calling program signing it, such as a PDA player.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, msg,
+    program::invoke_signed, program_error::ProgramError, pubkey::Pubkey,
+};
+
+use crate::{
+    instruction,
+    state::{
+        AllowlistProof, Attestation, DraftWinner, PlayerLimits, PoolStorageData, PurchaseIntent,
+        RoundSchedule, SubscriptionTerms, TicketAccountData, Voucher,
+    },
+};
+
+/// Invokes the instruction once every account it lists is among the account infos, so a missing
+/// account is reported by its address instead of failing the whole call.
+fn invoke_checked(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    for account_meta in &instruction.accounts {
+        if !account_infos
+            .iter()
+            .any(|account_info| *account_info.key == account_meta.pubkey)
+        {
+            msg!("Missing account {}", account_meta.pubkey);
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+    }
+
+    invoke_signed(instruction, account_infos, signers_seeds)
+}
+
+// Declares the plain and the signed wrapper of each builder of the `instruction` module
+macro_rules! cpi_wrappers {
+    ($($name:ident, $signed_name:ident ($($arg:ident: $arg_type:ty),*);)*) => {
+        $(
+            #[doc = concat!("Invokes [`instruction::", stringify!($name), "`].")]
+            pub fn $name(
+                $($arg: $arg_type,)*
+                account_infos: &[AccountInfo],
+            ) -> ProgramResult {
+                invoke_checked(&instruction::$name($($arg),*), account_infos, &[])
+            }
+
+            #[doc = concat!(
+                "Invokes [`instruction::",
+                stringify!($name),
+                "`], signed by the PDAs of the calling program."
+            )]
+            pub fn $signed_name(
+                $($arg: $arg_type,)*
+                account_infos: &[AccountInfo],
+                signers_seeds: &[&[&[u8]]],
+            ) -> ProgramResult {
+                invoke_checked(&instruction::$name($($arg),*), account_infos, signers_seeds)
+            }
+        )*
+    };
+}
+
+cpi_wrappers! {
+    initialize_pool, initialize_pool_signed(pool_authority: &Pubkey, pool_storage: PoolStorageData);
+    deposit, deposit_signed(payer: &Pubkey, pool_authority: &Pubkey, amount: u64);
+    withdraw, withdraw_signed(player: &Pubkey, amount: u64);
+    purchase_ticket, purchase_ticket_signed(
+        pool_authority: &Pubkey,
+        payer: &Pubkey,
+        player: &Pubkey,
+        round_id: u64,
+        ticket_data: TicketAccountData
+    );
+    referred_purchase_ticket, referred_purchase_ticket_signed(
+        pool_authority: &Pubkey,
+        player: &Pubkey,
+        referrer: &Pubkey,
+        round_id: u64,
+        ticket_data: TicketAccountData
+    );
+    select_winners, select_winners_signed(
+        pool_authority: &Pubkey,
+        round_id: u64,
+        draft_winners: Vec<DraftWinner>
+    );
+    close_player_account, close_player_account_signed(player: &Pubkey, pool_authority: &Pubkey);
+    deposit_principal, deposit_principal_signed(pool_authority: &Pubkey, player: &Pubkey, amount: u64);
+    withdraw_principal, withdraw_principal_signed(pool_authority: &Pubkey, player: &Pubkey, amount: u64);
+    harvest_yield, harvest_yield_signed(
+        pool_authority: &Pubkey,
+        yield_source: &Pubkey,
+        yield_program: &Pubkey
+    );
+    commit_savings_draw, commit_savings_draw_signed(pool_authority: &Pubkey);
+    draw_savings_prize, draw_savings_prize_signed(
+        cranker: &Pubkey,
+        pool_authority: &Pubkey,
+        participants: &[Pubkey]
+    );
+    open_round, open_round_signed(pool_authority: &Pubkey, round_id: u64, schedule: RoundSchedule);
+    settle_round, settle_round_signed(cranker: &Pubkey, pool_authority: &Pubkey, round_id: u64);
+    claim_prize, claim_prize_signed(player: &Pubkey, pool_authority: &Pubkey, round_id: u64);
+    refund_tickets, refund_tickets_signed(player: &Pubkey, pool_authority: &Pubkey, round_id: u64);
+    cancel_round, cancel_round_signed(caller: &Pubkey, pool_authority: &Pubkey, round_id: u64);
+    set_player_limits, set_player_limits_signed(player: &Pubkey, limits: PlayerLimits);
+    sweep_expired_prizes, sweep_expired_prizes_signed(pool_authority: &Pubkey, round_ids: &[u64]);
+    close_pool, close_pool_signed(
+        pool_authority: &Pubkey,
+        current_round_id: u64,
+        player_token_accounts: &[Pubkey]
+    );
+    register_ticket_holder, register_ticket_holder_signed(
+        pool_authority: &Pubkey,
+        player: &Pubkey,
+        round_id: u64
+    );
+    select_transfer_round, select_transfer_round_signed(
+        pool_authority: &Pubkey,
+        player: &Pubkey,
+        round_id: u64
+    );
+    sponsored_purchase, sponsored_purchase_signed(
+        pool_authority: &Pubkey,
+        relayer: &Pubkey,
+        intent: PurchaseIntent
+    );
+    redeem_voucher, redeem_voucher_signed(
+        pool_authority: &Pubkey,
+        player: &Pubkey,
+        round_id: u64,
+        voucher: Voucher
+    );
+    withdraw_referral_rewards, withdraw_referral_rewards_signed(
+        referrer: &Pubkey,
+        pool_authority: &Pubkey,
+        round_ids: &[u64]
+    );
+    subscribe, subscribe_signed(player: &Pubkey, pool_authority: &Pubkey, terms: SubscriptionTerms);
+    crank_subscription, crank_subscription_signed(
+        cranker: &Pubkey,
+        pool_authority: &Pubkey,
+        player: &Pubkey,
+        round_id: u64,
+        tickets_per_round: u64
+    );
+    cancel_subscription, cancel_subscription_signed(player: &Pubkey, pool_authority: &Pubkey);
+    set_allowlist_root, set_allowlist_root_signed(
+        pool_authority: &Pubkey,
+        current_round_id: u64,
+        allowlist_root: [u8; 32]
+    );
+    purchase_allowlisted_ticket, purchase_allowlisted_ticket_signed(
+        pool_authority: &Pubkey,
+        player: &Pubkey,
+        round_id: u64,
+        ticket_data: TicketAccountData,
+        allowlist_proof: AllowlistProof
+    );
+    register_attestation, register_attestation_signed(
+        player: &Pubkey,
+        pool_authority: &Pubkey,
+        attestation: Attestation
+    );
+    get_pool_info, get_pool_info_signed(pool_authority: &Pubkey, current_round_id: u64);
+    get_player_info, get_player_info_signed(player: &Pubkey, pool_authority: &Pubkey, round_id: u64);
+    quote_ticket_price, quote_ticket_price_signed(pool_authority: &Pubkey, round_id: u64, count: u64);
+    get_round_result, get_round_result_signed(pool_authority: &Pubkey, round_id: u64);
+    migrate_account, migrate_account_signed(payer: &Pubkey, account: &Pubkey);
+    begin_draw, begin_draw_signed(pool_authority: &Pubkey, round_id: u64);
+    pay_draw_winners, pay_draw_winners_signed(
+        pool_authority: &Pubkey,
+        round_id: u64,
+        draft_winners: Vec<DraftWinner>
+    );
+    finalize_draw, finalize_draw_signed(pool_authority: &Pubkey, round_id: u64);
+    abort_draw, abort_draw_signed(pool_authority: &Pubkey, round_id: u64);
+}
@@ -27,6 +27,130 @@ pub enum LotteryError {
     InvalidOwner,
     #[error("Invalid program account")]
     InvalidProramAccount,
+    #[error("The instruction is not supported by the pool mode")]
+    InvalidPoolMode,
+    #[error("Invalid yield source")]
+    InvalidYieldSource,
+    #[error("There is no yield to draw")]
+    EmptyPrizeReserve,
+    #[error("There are no participants to draw")]
+    NoParticipants,
+    #[error("The participants do not match the pool weight")]
+    ParticipantsMismatch,
+    #[error("The ticket sales are closed")]
+    SalesClosed,
+    #[error("The round can't be drawn yet")]
+    DrawTooEarly,
+    #[error("Invalid round account")]
+    InvalidRound,
+    #[error("Invalid sales window")]
+    InvalidSalesWindow,
+    #[error("The round randomness is not available yet")]
+    RandomnessNotAvailable,
+    #[error("The round is not open")]
+    RoundNotOpen,
+    #[error("The round is not settled")]
+    RoundNotSettled,
+    #[error("The tickets don't include the winning ticket")]
+    NotAWinner,
+    #[error("The prize was already claimed")]
+    PrizeAlreadyClaimed,
+    #[error("Too many purchases in the round")]
+    TooManyPurchases,
+    #[error("The round can't be cancelled yet")]
+    CancelTooEarly,
+    #[error("The round is not cancelled")]
+    RoundNotCancelled,
+    #[error("The tickets were already refunded")]
+    AlreadyRefunded,
+    #[error("The round didn't reach its minimum participation")]
+    MinimumParticipationNotMet,
+    #[error("The player reached its ticket cap for the round")]
+    PlayerTicketCapExceeded,
+    #[error("The round reached its ticket cap")]
+    RoundTicketCapExceeded,
+    #[error("Too many tickets purchased in the transaction")]
+    TransactionTicketCapExceeded,
+    #[error("The player limits are invalid")]
+    InvalidPlayerLimits,
+    #[error("The player is self-excluded")]
+    PlayerSelfExcluded,
+    #[error("The player reached its spending limit")]
+    SpendingLimitExceeded,
+    #[error("The claim deadline of the prize has passed")]
+    ClaimExpired,
+    #[error("The prize can't be swept")]
+    PrizeNotSweepable,
+    #[error("The current round of the pool is still open")]
+    RoundStillOpen,
+    #[error("The pool still owes prizes, refunds or principal")]
+    OutstandingLiabilities,
+    #[error("Receipts are still outstanding")]
+    ReceiptsOutstanding,
+    #[error("The receipt is not being transferred")]
+    NotTransferring,
+    #[error("Not enough tickets in the current round")]
+    NotEnoughTickets,
+    #[error("The purchase intent has expired")]
+    IntentExpired,
+    #[error("The purchase intent is not signed by the player")]
+    InvalidIntentSignature,
+    #[error("The purchase intent nonce was already used")]
+    InvalidIntentNonce,
+    #[error("The purchase intent doesn't match the purchase")]
+    IntentMismatch,
+    #[error("The voucher is invalid")]
+    InvalidVoucher,
+    #[error("The voucher has expired")]
+    VoucherExpired,
+    #[error("The voucher was already redeemed")]
+    VoucherAlreadyRedeemed,
+    #[error("Players can't refer themselves")]
+    SelfReferral,
+    #[error("The crank tip and referral share exceed the house fee")]
+    InvalidReferralShare,
+    #[error("The subscription terms are invalid")]
+    InvalidSubscription,
+    #[error("The subscription already played the current round")]
+    SubscriptionAlreadyPlayed,
+    #[error("The subscription escrow can't pay for the round")]
+    SubscriptionEscrowExhausted,
+    #[error("The player is not on the pool allowlist")]
+    NotAllowlisted,
+    #[error("The attestation is invalid")]
+    InvalidAttestation,
+    #[error("The player has no valid attestation")]
+    AttestationRequired,
+    #[error("The account is not of the expected type")]
+    InvalidAccountType,
+    #[error("The account layout must be migrated first")]
+    AccountNotMigrated,
+    #[error("The account must be writable")]
+    AccountNotWritable,
+    #[error("The winner was already paid by the draw")]
+    WinnerAlreadyPaid,
+    #[error("The payouts exceed the prize pool of the draw")]
+    DrawBudgetExceeded,
+    #[error("The draw pays too many winners")]
+    TooManyDrawWinners,
+    #[error("The payouts don't add up to the prize pool of the draw")]
+    DrawTotalsMismatch,
+    #[error("The house fee and the crank tip can't exceed the basis points")]
+    InvalidFeeRate,
+    #[error("The referrer has too many rounds of rewards pending, withdraw them first")]
+    TooManyPendingReferrals,
+    #[error("The player account can't be closed while its limits are active")]
+    PlayerLimitsActive,
+    #[error("The player account can't be closed once it has used purchase intents")]
+    IntentNonceInUse,
+    #[error("The savings draw is already committed")]
+    SavingsDrawCommitted,
+    #[error("The savings draw must be committed first")]
+    SavingsDrawNotCommitted,
+    #[error("The principal can't move while the savings draw is pending")]
+    SavingsDrawPending,
+    #[error("The pool caps the tickets per transaction and can't sell them through CPI")]
+    CpiPurchaseNotAllowed,
 }
 
 impl From<LotteryError> for ProgramError {
@@ -0,0 +1,216 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+use crate::state::{PlayerLimits, PoolMode, SweepDestination};
+
+// The first field of the data logged with each event, telling the events of the program apart
+// from the data logged by the programs it invokes.
+pub const EVENT_TAG: &[u8] = b"luckysol:event";
+
+// The state transitions of the program, logged with `sol_log_data` so indexers follow the pools
+// without diffing their accounts. The amounts are in lamports.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum LotteryEvent {
+    PoolInitialized {
+        pool_vault: Pubkey,
+        authority: Pubkey,
+        mode: PoolMode,
+        ticket_price: u64,
+    },
+    Deposit {
+        pool_vault: Pubkey,
+        payer: Pubkey,
+        amount: u64,
+    },
+    Withdraw {
+        player: Pubkey,
+        amount: u64,
+    },
+    TicketsPurchased {
+        pool_vault: Pubkey,
+        round_id: u64,
+        player: Pubkey,
+        payer: Pubkey,
+        first_ticket: u64,
+        tickets: u64,
+        price: u64,
+    },
+    // A winner airdropped by the authority
+    WinnerPaid {
+        pool_vault: Pubkey,
+        winner: Pubkey,
+        amount: u64,
+    },
+    AccountClosed {
+        player: Pubkey,
+    },
+    PrincipalDeposited {
+        pool_vault: Pubkey,
+        player: Pubkey,
+        amount: u64,
+    },
+    PrincipalWithdrawn {
+        pool_vault: Pubkey,
+        player: Pubkey,
+        amount: u64,
+    },
+    YieldHarvested {
+        pool_vault: Pubkey,
+        amount: u64,
+    },
+    SavingsPrizeDrawn {
+        pool_vault: Pubkey,
+        winner: Pubkey,
+        prize: u64,
+    },
+    RoundOpened {
+        pool_vault: Pubkey,
+        round_id: u64,
+        sales_open_slot: u64,
+        sales_close_slot: u64,
+    },
+    RoundSettled {
+        pool_vault: Pubkey,
+        round_id: u64,
+        tickets_sold: u64,
+        winning_ticket: u64,
+        prize: u64,
+        house_fee: u64,
+        crank_tip: u64,
+    },
+    RoundCancelled {
+        pool_vault: Pubkey,
+        round_id: u64,
+        refunds: u64,
+    },
+    PrizeClaimed {
+        pool_vault: Pubkey,
+        round_id: u64,
+        winner: Pubkey,
+        prize: u64,
+    },
+    TicketsRefunded {
+        pool_vault: Pubkey,
+        round_id: u64,
+        player: Pubkey,
+        amount: u64,
+    },
+    PlayerLimitsSet {
+        player: Pubkey,
+        limits: PlayerLimits,
+        pending: PlayerLimits,
+        pending_effective_at: i64,
+    },
+    PrizeSwept {
+        pool_vault: Pubkey,
+        round_id: u64,
+        amount: u64,
+        destination: SweepDestination,
+    },
+    PoolClosed {
+        pool_vault: Pubkey,
+        authority: Pubkey,
+    },
+    TicketHolderRegistered {
+        pool_vault: Pubkey,
+        round_id: u64,
+        holder: Pubkey,
+    },
+    TicketsTransferred {
+        pool_vault: Pubkey,
+        round_id: u64,
+        from: Pubkey,
+        to: Pubkey,
+        tickets: u64,
+    },
+    VoucherRedeemed {
+        pool_vault: Pubkey,
+        voucher_id: u64,
+        player: Pubkey,
+        tickets: u64,
+    },
+    ReferralRecorded {
+        pool_vault: Pubkey,
+        referrer: Pubkey,
+        player: Pubkey,
+        reward: u64,
+    },
+    ReferralRewardsWithdrawn {
+        pool_vault: Pubkey,
+        referrer: Pubkey,
+        amount: u64,
+    },
+    Subscribed {
+        pool_vault: Pubkey,
+        owner: Pubkey,
+        tickets_per_round: u64,
+        deposit: u64,
+    },
+    SubscriptionCranked {
+        pool_vault: Pubkey,
+        owner: Pubkey,
+        round_id: u64,
+        cost: u64,
+    },
+    SubscriptionCancelled {
+        pool_vault: Pubkey,
+        owner: Pubkey,
+        refund: u64,
+    },
+    AllowlistRootSet {
+        pool_vault: Pubkey,
+        allowlist_root: [u8; 32],
+    },
+    AttestationRegistered {
+        player: Pubkey,
+        attestor: Pubkey,
+        expires_at: i64,
+        level: u8,
+    },
+    AccountMigrated {
+        account: Pubkey,
+        discriminator: [u8; 8],
+        version: u8,
+    },
+    DrawStarted {
+        pool_vault: Pubkey,
+        round_id: u64,
+        prize_pool: u64,
+    },
+    DrawFinalized {
+        pool_vault: Pubkey,
+        round_id: u64,
+        winners: u64,
+        prize_pool: u64,
+    },
+    DrawAborted {
+        pool_vault: Pubkey,
+        round_id: u64,
+        winners: u64,
+        returned: u64,
+    },
+    SavingsDrawCommitted {
+        pool_vault: Pubkey,
+        draw_slot: u64,
+    },
+    TransferRoundSelected {
+        pool_vault: Pubkey,
+        owner: Pubkey,
+        round_id: u64,
+    },
+}
+
+impl LotteryEvent {
+    pub fn emit(&self) {
+        sol_log_data(&[EVENT_TAG, &borsh::to_vec(self).unwrap_or_default()]);
+    }
+
+    /// Decodes the fields of a `Program data:` log, already base64 decoded.
+    /// Returns `None` when the data wasn't logged by `emit`.
+    pub fn decode(fields: &[&[u8]]) -> Option<LotteryEvent> {
+        match fields {
+            [tag, event] if *tag == EVENT_TAG => LotteryEvent::try_from_slice(event).ok(),
+            _ => None,
+        }
+    }
+}
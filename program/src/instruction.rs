@@ -0,0 +1,829 @@
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use spl_transfer_hook_interface::get_extra_account_metas_address;
+
+use crate::{
+    processor::{
+        find_draw_session_pda, find_player_pda_account, find_player_token_pda_account,
+        find_referrer_pda, find_round_pda, find_round_tickets_pda, find_savings_pda_account,
+        find_stake_pool_mint_pda, find_stake_pool_vault_pda, find_subscription_pda,
+        find_transfer_round_pda, find_voucher_nullifier_pda,
+    },
+    state::{
+        AllowlistProof, Attestation, DraftWinner, Instruction as LotteryInstruction, PlayerLimits,
+        PoolStorageData, PurchaseIntent, RoundSchedule, SubscriptionTerms, TicketAccountData,
+        Voucher,
+    },
+    ID,
+};
+
+/// Creates the vault and the receipt mint of the pool of `pool_authority`, along with the
+/// transfer hook validation account when its tickets are transferable.
+pub fn initialize_pool(pool_authority: &Pubkey, pool_storage: PoolStorageData) -> Instruction {
+    let (pool_mint_account, _) = find_stake_pool_mint_pda(&ID, pool_authority);
+
+    let mut accounts = vec![
+        AccountMeta::new(*pool_authority, true),
+        AccountMeta::new(find_stake_pool_vault_pda(&ID, pool_authority).0, false),
+        AccountMeta::new(pool_mint_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    if pool_storage.transferable_tickets {
+        accounts.push(AccountMeta::new(
+            get_extra_account_metas_address(&pool_mint_account, &ID),
+            false,
+        ));
+    }
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::InitializePool(pool_storage),
+        accounts,
+    )
+}
+
+/// Moves `amount` lamports from the payer to the vault of the pool.
+pub fn deposit(payer: &Pubkey, pool_authority: &Pubkey, amount: u64) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(find_stake_pool_vault_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::Deposit(amount), accounts)
+}
+
+/// Withdraws `amount` lamports of the winnings held by the player PDA.
+pub fn withdraw(player: &Pubkey, amount: u64) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new(find_player_pda_account(&ID, player).0, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::PlayerWithdraw(amount), accounts)
+}
+
+// The accounts of the instructions selling tickets of the round to the player, paid by the payer
+fn purchase_accounts(
+    pool_authority: &Pubkey,
+    payer: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+) -> Vec<AccountMeta> {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+    let (round_account, _) = find_round_pda(&ID, &pool_vault_account, round_id);
+
+    let mut accounts = vec![
+        AccountMeta::new(*pool_authority, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(find_player_pda_account(&ID, player).0, false),
+        AccountMeta::new(find_player_token_pda_account(&ID, player).0, false),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(round_account, false),
+        AccountMeta::new(find_round_tickets_pda(&ID, &round_account, player).0, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+    ];
+
+    // The payer buys for itself unless another player follows
+    if payer != player {
+        accounts.push(AccountMeta::new_readonly(*player, false));
+    }
+
+    accounts
+}
+
+/// Buys tickets of the round for the player, paid by the payer, which is usually the player itself.
+pub fn purchase_ticket(
+    pool_authority: &Pubkey,
+    payer: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+    ticket_data: TicketAccountData,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::PurchaseTicket(ticket_data),
+        purchase_accounts(pool_authority, payer, player, round_id),
+    )
+}
+
+/// Buys tickets of the round for the player, crediting the referral reward of the purchase to the referrer.
+pub fn referred_purchase_ticket(
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    referrer: &Pubkey,
+    round_id: u64,
+    ticket_data: TicketAccountData,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = purchase_accounts(pool_authority, player, player, round_id);
+    accounts.extend([
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(*referrer, false),
+        AccountMeta::new(
+            find_referrer_pda(&ID, &pool_vault_account, referrer).0,
+            false,
+        ),
+    ]);
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::PurchaseTicket(ticket_data),
+        accounts,
+    )
+}
+
+/// Draws the current round of the pool, paying out the winners along with their accounts.
+pub fn select_winners(
+    pool_authority: &Pubkey,
+    round_id: u64,
+    draft_winners: Vec<DraftWinner>,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![
+        AccountMeta::new(*pool_authority, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+    ];
+
+    for winner in &draft_winners {
+        accounts.push(AccountMeta::new(winner.address, false));
+        accounts.push(AccountMeta::new(winner.token_account, false));
+    }
+
+    accounts.push(AccountMeta::new_readonly(spl_token_2022::id(), false));
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::SelectWinnersAndAirdrop(draft_winners),
+        accounts,
+    )
+}
+
+/// Closes the player PDA and the receipt token account of the player, refunding their rent.
+pub fn close_player_account(player: &Pubkey, pool_authority: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new(find_player_pda_account(&ID, player).0, false),
+        AccountMeta::new(find_player_token_pda_account(&ID, player).0, false),
+        AccountMeta::new(*pool_authority, false),
+        AccountMeta::new_readonly(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::ClosePlayerAccount, accounts)
+}
+
+/// Deposits `amount` lamports of principal of the player into a savings pool.
+pub fn deposit_principal(pool_authority: &Pubkey, player: &Pubkey, amount: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*pool_authority, false),
+        AccountMeta::new(*player, true),
+        AccountMeta::new(
+            find_savings_pda_account(&ID, &pool_vault_account, player).0,
+            false,
+        ),
+        AccountMeta::new(find_player_token_pda_account(&ID, player).0, false),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::DepositPrincipal(amount), accounts)
+}
+
+/// Withdraws `amount` lamports of principal of the player from a savings pool.
+pub fn withdraw_principal(pool_authority: &Pubkey, player: &Pubkey, amount: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*pool_authority, false),
+        AccountMeta::new(*player, true),
+        AccountMeta::new(
+            find_savings_pda_account(&ID, &pool_vault_account, player).0,
+            false,
+        ),
+        AccountMeta::new(find_player_token_pda_account(&ID, player).0, false),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::WithdrawPrincipal(amount), accounts)
+}
+
+/// Collects the yield of a savings pool from its yield source.
+pub fn harvest_yield(
+    pool_authority: &Pubkey,
+    yield_source: &Pubkey,
+    yield_program: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_stake_pool_vault_pda(&ID, pool_authority).0, false),
+        AccountMeta::new(*yield_source, false),
+        AccountMeta::new_readonly(*yield_program, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::HarvestYield, accounts)
+}
+
+/// Commits the slot whose hash draws the prize of a savings pool.
+pub fn commit_savings_draw(pool_authority: &Pubkey) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*pool_authority, true),
+        AccountMeta::new(pool_vault_account, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::CommitSavingsDraw, accounts)
+}
+
+/// Draws the prize of a savings pool among all of its participants, the draw must be committed first.
+pub fn draw_savings_prize(
+    cranker: &Pubkey,
+    pool_authority: &Pubkey,
+    participants: &[Pubkey],
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![
+        AccountMeta::new(*cranker, true),
+        AccountMeta::new_readonly(*pool_authority, false),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ];
+
+    // The program expects the participants ordered by savings PDA
+    let mut participants: Vec<(Pubkey, &Pubkey)> = participants
+        .iter()
+        .map(|participant| {
+            (
+                find_savings_pda_account(&ID, &pool_vault_account, participant).0,
+                participant,
+            )
+        })
+        .collect();
+    participants.sort();
+
+    for (savings_pda_account, participant) in participants {
+        accounts.push(AccountMeta::new(savings_pda_account, false));
+        accounts.push(AccountMeta::new(
+            find_player_token_pda_account(&ID, participant).0,
+            false,
+        ));
+    }
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::DrawSavingsPrize, accounts)
+}
+
+/// Opens the round `round_id` of the pool, which must be its draft count.
+pub fn open_round(pool_authority: &Pubkey, round_id: u64, schedule: RoundSchedule) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*pool_authority, true),
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::OpenRound(schedule), accounts)
+}
+
+/// Settles the round of the pool, the cranker earns the settlement tip.
+pub fn settle_round(cranker: &Pubkey, pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*cranker, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+        AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::SettleRound, accounts)
+}
+
+// The accounts of the instructions paying a player out of its round tickets
+fn round_tickets_payout_accounts(
+    player: &Pubkey,
+    pool_authority: &Pubkey,
+    round_id: u64,
+) -> Vec<AccountMeta> {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+    let (round_account, _) = find_round_pda(&ID, &pool_vault_account, round_id);
+
+    vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new(find_round_tickets_pda(&ID, &round_account, player).0, false),
+        AccountMeta::new(round_account, false),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new_readonly(*pool_authority, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new(find_player_token_pda_account(&ID, player).0, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ]
+}
+
+/// Claims the prize of the round for the player holding its winning ticket.
+pub fn claim_prize(player: &Pubkey, pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let mut accounts = round_tickets_payout_accounts(player, pool_authority, round_id);
+    // The player PDA holds the attestation of the player
+    accounts.push(AccountMeta::new_readonly(
+        find_player_pda_account(&ID, player).0,
+        false,
+    ));
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::ClaimPrize, accounts)
+}
+
+/// Refunds the tickets of the player in a cancelled round.
+pub fn refund_tickets(player: &Pubkey, pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::RefundTickets,
+        round_tickets_payout_accounts(player, pool_authority, round_id),
+    )
+}
+
+/// Cancels the round of the pool, by its authority or by anyone once the round is stale.
+pub fn cancel_round(caller: &Pubkey, pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*caller, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::CancelRound, accounts)
+}
+
+/// Sets the spending limits of the player.
+pub fn set_player_limits(player: &Pubkey, limits: PlayerLimits) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new(find_player_pda_account(&ID, player).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::SetPlayerLimits(limits), accounts)
+}
+
+/// Sweeps the unclaimed prizes of the expired rounds of the pool.
+pub fn sweep_expired_prizes(pool_authority: &Pubkey, round_ids: &[u64]) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![AccountMeta::new(pool_vault_account, false)];
+    accounts.extend(round_ids.iter().map(|round_id| {
+        AccountMeta::new(find_round_pda(&ID, &pool_vault_account, *round_id).0, false)
+    }));
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::SweepExpiredPrizes, accounts)
+}
+
+/// Closes the pool, burning the receipts left in the given player token accounts.
+pub fn close_pool(
+    pool_authority: &Pubkey,
+    current_round_id: u64,
+    player_token_accounts: &[Pubkey],
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![
+        AccountMeta::new(*pool_authority, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(
+            find_round_pda(&ID, &pool_vault_account, current_round_id).0,
+            false,
+        ),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+    ];
+    accounts.extend(
+        player_token_accounts
+            .iter()
+            .map(|account| AccountMeta::new(*account, false)),
+    );
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::ClosePool, accounts)
+}
+
+/// Creates the accounts the player needs to receive transferred receipts in the round.
+pub fn register_ticket_holder(
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+    let (round_account, _) = find_round_pda(&ID, &pool_vault_account, round_id);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*pool_authority, false),
+        AccountMeta::new(*player, true),
+        AccountMeta::new(find_player_token_pda_account(&ID, player).0, false),
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(round_account, false),
+        AccountMeta::new(find_round_tickets_pda(&ID, &round_account, player).0, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::RegisterTicketHolder, accounts)
+}
+
+/// Selects the round whose tickets move along with the receipts the player transfers next.
+pub fn select_transfer_round(
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new_readonly(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+        AccountMeta::new(
+            find_transfer_round_pda(&ID, &pool_vault_account, player).0,
+            false,
+        ),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::SelectTransferRound { round_id },
+        accounts,
+    )
+}
+
+/// Submits a purchase intent paid by the relayer, the ed25519 instruction verifying the
+/// signature of the intent must precede it.
+pub fn sponsored_purchase(
+    pool_authority: &Pubkey,
+    relayer: &Pubkey,
+    intent: PurchaseIntent,
+) -> Instruction {
+    let mut accounts = purchase_accounts(pool_authority, relayer, &intent.player, intent.round_id);
+    // The intent player always follows, even when it relays its own intent
+    if *relayer == intent.player {
+        accounts.push(AccountMeta::new_readonly(intent.player, false));
+    }
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::SponsoredPurchase(intent), accounts)
+}
+
+/// Redeems a voucher of the pool for the player, the ed25519 instruction verifying the
+/// signature of the voucher must precede it.
+pub fn redeem_voucher(
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+    voucher: Voucher,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![AccountMeta::new(
+        find_voucher_nullifier_pda(&ID, &pool_vault_account, voucher.voucher_id).0,
+        false,
+    )];
+    accounts.extend(purchase_accounts(pool_authority, player, player, round_id));
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::RedeemVoucher(voucher), accounts)
+}
+
+/// Withdraws the referral rewards the referrer earned in the pool, resolving the rewards pending on
+/// the given rounds.
+pub fn withdraw_referral_rewards(
+    referrer: &Pubkey,
+    pool_authority: &Pubkey,
+    round_ids: &[u64],
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![
+        AccountMeta::new(*referrer, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(
+            find_referrer_pda(&ID, &pool_vault_account, referrer).0,
+            false,
+        ),
+    ];
+
+    for round_id in round_ids {
+        accounts.push(AccountMeta::new_readonly(
+            find_round_pda(&ID, &pool_vault_account, *round_id).0,
+            false,
+        ));
+    }
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::WithdrawReferralRewards, accounts)
+}
+
+/// Subscribes the player to the rounds of the pool, funding the escrow of the subscription.
+pub fn subscribe(
+    player: &Pubkey,
+    pool_authority: &Pubkey,
+    terms: SubscriptionTerms,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new(
+            find_subscription_pda(&ID, &pool_vault_account, player).0,
+            false,
+        ),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::Subscribe(terms), accounts)
+}
+
+/// Buys the subscription tickets of the player in the round, the cranker paying the rent upfront.
+/// `tickets_per_round` must match the terms of the subscription.
+pub fn crank_subscription(
+    cranker: &Pubkey,
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+    tickets_per_round: u64,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![AccountMeta::new(
+        find_subscription_pda(&ID, &pool_vault_account, player).0,
+        false,
+    )];
+    accounts.extend(purchase_accounts(pool_authority, cranker, player, round_id));
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::CrankSubscription { tickets_per_round },
+        accounts,
+    )
+}
+
+/// Cancels the subscription of the player, returning what is left of its escrow.
+pub fn cancel_subscription(player: &Pubkey, pool_authority: &Pubkey) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new(
+            find_subscription_pda(&ID, &pool_vault_account, player).0,
+            false,
+        ),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::CancelSubscription, accounts)
+}
+
+/// Rotates the allowlist root of the pool, outside of an open round.
+pub fn set_allowlist_root(
+    pool_authority: &Pubkey,
+    current_round_id: u64,
+    allowlist_root: [u8; 32],
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new(*pool_authority, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new_readonly(
+            find_round_pda(&ID, &pool_vault_account, current_round_id).0,
+            false,
+        ),
+    ];
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::SetAllowlistRoot(allowlist_root),
+        accounts,
+    )
+}
+
+/// Buys tickets of an allowlisted pool for the player, along with the proof its wallet is listed.
+pub fn purchase_allowlisted_ticket(
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+    ticket_data: TicketAccountData,
+    allowlist_proof: AllowlistProof,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::PurchaseAllowlistedTicket(ticket_data, allowlist_proof),
+        purchase_accounts(pool_authority, player, player, round_id),
+    )
+}
+
+/// Registers an attestation of the player, the ed25519 instruction verifying the signature
+/// of the attestor must precede it.
+pub fn register_attestation(
+    player: &Pubkey,
+    pool_authority: &Pubkey,
+    attestation: Attestation,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(find_stake_pool_vault_pda(&ID, pool_authority).0, false),
+        AccountMeta::new(find_player_pda_account(&ID, player).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+    ];
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::RegisterAttestation(attestation),
+        accounts,
+    )
+}
+
+/// Returns the `PoolInfo` of the pool, as of its current round.
+pub fn get_pool_info(pool_authority: &Pubkey, current_round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new_readonly(
+            find_round_pda(&ID, &pool_vault_account, current_round_id).0,
+            false,
+        ),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::GetPoolInfo, accounts)
+}
+
+/// Returns the `PlayerInfo` of the player in the round.
+pub fn get_player_info(player: &Pubkey, pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+    let (round_account, _) = find_round_pda(&ID, &pool_vault_account, round_id);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*player, false),
+        AccountMeta::new_readonly(find_player_pda_account(&ID, player).0, false),
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new_readonly(round_account, false),
+        AccountMeta::new_readonly(find_round_tickets_pda(&ID, &round_account, player).0, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::GetPlayerInfo, accounts)
+}
+
+/// Returns the `TicketQuote` of `count` tickets of the round.
+pub fn quote_ticket_price(pool_authority: &Pubkey, round_id: u64, count: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new_readonly(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+    ];
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::QuoteTicketPrice { count },
+        accounts,
+    )
+}
+
+/// Returns the `RoundResult` of the round.
+pub fn get_round_result(pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new_readonly(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::GetRoundResult, accounts)
+}
+
+/// Upgrades a program account to the current layout.
+pub fn migrate_account(payer: &Pubkey, account: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction::new_with_borsh(ID, &LotteryInstruction::MigrateAccount, accounts)
+}
+
+/// Settles the current round of the pool and opens its draw session, its prize pool paid out to the
+/// winners across `pay_draw_winners` calls.
+pub fn begin_draw(pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::BeginDraw,
+        vec![
+            AccountMeta::new(*pool_authority, true),
+            AccountMeta::new(pool_vault_account, false),
+            AccountMeta::new(find_round_pda(&ID, &pool_vault_account, round_id).0, false),
+            AccountMeta::new(
+                find_draw_session_pda(&ID, &pool_vault_account, round_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Pays a batch of the winners of the draw session of the round, along with their accounts.
+pub fn pay_draw_winners(
+    pool_authority: &Pubkey,
+    round_id: u64,
+    draft_winners: Vec<DraftWinner>,
+) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*pool_authority, true),
+        AccountMeta::new(pool_vault_account, false),
+        AccountMeta::new(find_stake_pool_mint_pda(&ID, pool_authority).0, false),
+        AccountMeta::new(
+            find_draw_session_pda(&ID, &pool_vault_account, round_id).0,
+            false,
+        ),
+    ];
+
+    for winner in &draft_winners {
+        accounts.push(AccountMeta::new(winner.address, false));
+        accounts.push(AccountMeta::new(winner.token_account, false));
+    }
+
+    accounts.push(AccountMeta::new_readonly(spl_token_2022::id(), false));
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::PayDrawWinners(draft_winners),
+        accounts,
+    )
+}
+
+/// Closes the draw session of the round once the whole prize pool was paid, refunding its rent
+/// to the pool authority.
+pub fn finalize_draw(pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::FinalizeDraw,
+        vec![
+            AccountMeta::new(*pool_authority, true),
+            AccountMeta::new_readonly(pool_vault_account, false),
+            AccountMeta::new(
+                find_draw_session_pda(&ID, &pool_vault_account, round_id).0,
+                false,
+            ),
+        ],
+    )
+}
+
+/// Closes the draw session of the round before its whole prize pool was paid, returning the unpaid
+/// budget to the pool jackpot.
+pub fn abort_draw(pool_authority: &Pubkey, round_id: u64) -> Instruction {
+    let (pool_vault_account, _) = find_stake_pool_vault_pda(&ID, pool_authority);
+
+    Instruction::new_with_borsh(
+        ID,
+        &LotteryInstruction::AbortDraw,
+        vec![
+            AccountMeta::new(*pool_authority, true),
+            AccountMeta::new(pool_vault_account, false),
+            AccountMeta::new(
+                find_draw_session_pda(&ID, &pool_vault_account, round_id).0,
+                false,
+            ),
+        ],
+    )
+}
@@ -1,5 +1,10 @@
+#[cfg(feature = "cpi")]
+pub mod cpi;
+#[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
 pub mod error;
+pub mod events;
+pub mod instruction;
 pub mod processor;
 pub mod state;
 
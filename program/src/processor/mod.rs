@@ -1,12 +1,46 @@
 use borsh::BorshDeserialize;
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
+mod pda;
+mod process_cancel_round;
+mod process_cancel_subscription;
+mod process_claim_prize;
 mod process_close_player_account;
+mod process_close_pool;
+mod process_commit_savings_draw;
+mod process_crank_subscription;
 mod process_deposit;
+mod process_deposit_principal;
 mod process_draft;
+mod process_draw_session;
+mod process_get_player_info;
+mod process_get_pool_info;
+mod process_get_round_result;
+mod process_harvest_yield;
+mod process_migrate_account;
+mod process_open_round;
 mod process_player_withdraw;
 mod process_pool_initialization;
 mod process_purchase_ticket;
+mod process_quote_ticket_price;
+mod process_redeem_voucher;
+mod process_refund_tickets;
+mod process_register_attestation;
+mod process_register_ticket_holder;
+mod process_savings_draw;
+mod process_select_transfer_round;
+mod process_set_allowlist_root;
+mod process_set_player_limits;
+mod process_settle_round;
+mod process_sponsored_purchase;
+mod process_subscribe;
+mod process_sweep_expired_prizes;
+mod process_transfer_hook;
+mod process_withdraw_principal;
+mod process_withdraw_referral_rewards;
+mod randomness;
+mod signature;
+mod validation;
 
 pub use process_pool_initialization::find_player_pda_account;
 pub use process_pool_initialization::find_stake_pool_mint_pda;
@@ -14,13 +48,60 @@ pub use process_pool_initialization::find_stake_pool_vault_pda;
 pub use process_pool_initialization::update_player_account;
 
 pub use process_draft::process_draft;
+pub use process_draw_session::find_draw_session_pda;
+pub use process_draw_session::process_abort_draw;
+pub use process_draw_session::process_begin_draw;
+pub use process_draw_session::process_finalize_draw;
+pub use process_draw_session::process_pay_draw_winners;
 pub use process_pool_initialization::process_pool_initialization;
 
 pub use process_deposit::process_deposit;
 
+pub use process_get_player_info::process_get_player_info;
+pub use process_get_pool_info::process_get_pool_info;
+pub use process_get_round_result::process_get_round_result;
+pub use process_quote_ticket_price::process_quote_ticket_price;
+
+pub use process_deposit_principal::find_savings_pda_account;
+pub use process_deposit_principal::process_deposit_principal;
+pub use process_harvest_yield::process_harvest_yield;
+pub use process_migrate_account::process_migrate_account;
+pub use process_open_round::find_round_pda;
+pub use process_open_round::process_open_round;
+pub use process_savings_draw::process_savings_draw;
+pub use process_select_transfer_round::find_transfer_round_pda;
+pub use process_select_transfer_round::process_select_transfer_round;
+pub use process_set_allowlist_root::process_set_allowlist_root;
+pub use process_set_player_limits::process_set_player_limits;
+pub use process_settle_round::process_settle_round;
+pub use process_sponsored_purchase::process_sponsored_purchase;
+pub use process_subscribe::find_subscription_pda;
+pub use process_subscribe::process_subscribe;
+pub use process_sweep_expired_prizes::process_sweep_expired_prizes;
+
+pub use process_cancel_round::process_cancel_round;
+pub use process_cancel_subscription::process_cancel_subscription;
+pub use process_claim_prize::process_claim_prize;
+pub use process_close_pool::process_close_pool;
+pub use process_commit_savings_draw::process_commit_savings_draw;
+pub use process_crank_subscription::process_crank_subscription;
+pub use process_redeem_voucher::find_voucher_nullifier_pda;
+pub use process_redeem_voucher::process_redeem_voucher;
+pub use process_refund_tickets::process_refund_tickets;
+pub use process_register_attestation::process_register_attestation;
+pub use process_register_ticket_holder::process_register_ticket_holder;
+pub use process_transfer_hook::process_transfer_hook;
+pub use process_withdraw_principal::process_withdraw_principal;
+pub use process_withdraw_referral_rewards::find_referrer_pda;
+pub use process_withdraw_referral_rewards::process_withdraw_referral_rewards;
+
 pub use process_purchase_ticket::find_player_token_pda_account;
+pub use process_purchase_ticket::find_round_tickets_pda;
+pub use process_purchase_ticket::process_allowlisted_ticket_purchase;
 pub use process_purchase_ticket::process_ticket_purchase;
 
+use spl_transfer_hook_interface::instruction::TransferHookInstruction;
+
 use crate::state::Instruction;
 
 pub fn processor(
@@ -28,6 +109,13 @@ pub fn processor(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
+    // Token-2022 invokes the program as the transfer hook of transferable receipts
+    if let Ok(TransferHookInstruction::Execute { amount }) =
+        TransferHookInstruction::unpack(instruction_data)
+    {
+        return process_transfer_hook(program_id, accounts, instruction_data, amount);
+    }
+
     let instr = Instruction::try_from_slice(instruction_data)?;
     match instr {
         Instruction::InitializePool(pool_storage_account) => {
@@ -46,5 +134,64 @@ pub fn processor(
         Instruction::SelectWinnersAndAirdrop(draft_winners) => {
             process_draft(program_id, &accounts.to_vec(), draft_winners)
         }
+        Instruction::DepositPrincipal(amount) => {
+            process_deposit_principal(program_id, accounts, amount)
+        }
+        Instruction::WithdrawPrincipal(amount) => {
+            process_withdraw_principal(program_id, accounts, amount)
+        }
+        Instruction::HarvestYield => process_harvest_yield(program_id, accounts),
+        Instruction::DrawSavingsPrize => process_savings_draw(program_id, accounts),
+        Instruction::OpenRound(schedule) => process_open_round(program_id, accounts, schedule),
+        Instruction::SettleRound => process_settle_round(program_id, accounts),
+        Instruction::ClaimPrize => process_claim_prize(program_id, accounts),
+        Instruction::CancelRound => process_cancel_round(program_id, accounts),
+        Instruction::RefundTickets => process_refund_tickets(program_id, accounts),
+        Instruction::SetPlayerLimits(limits) => {
+            process_set_player_limits(program_id, accounts, limits)
+        }
+        Instruction::SweepExpiredPrizes => process_sweep_expired_prizes(program_id, accounts),
+        Instruction::ClosePool => process_close_pool(program_id, accounts),
+        Instruction::RegisterTicketHolder => process_register_ticket_holder(program_id, accounts),
+        Instruction::SponsoredPurchase(intent) => {
+            process_sponsored_purchase(program_id, accounts, intent)
+        }
+        Instruction::RedeemVoucher(voucher) => {
+            process_redeem_voucher(program_id, accounts, voucher)
+        }
+        Instruction::WithdrawReferralRewards => {
+            process_withdraw_referral_rewards(program_id, accounts)
+        }
+        Instruction::Subscribe(terms) => process_subscribe(program_id, accounts, terms),
+        Instruction::CrankSubscription { tickets_per_round } => {
+            process_crank_subscription(program_id, accounts, tickets_per_round)
+        }
+        Instruction::CancelSubscription => process_cancel_subscription(program_id, accounts),
+        Instruction::SetAllowlistRoot(allowlist_root) => {
+            process_set_allowlist_root(program_id, accounts, allowlist_root)
+        }
+        Instruction::PurchaseAllowlistedTicket(account_data, allowlist_proof) => {
+            process_allowlisted_ticket_purchase(program_id, accounts, account_data, allowlist_proof)
+        }
+        Instruction::RegisterAttestation(attestation) => {
+            process_register_attestation(program_id, accounts, attestation)
+        }
+        Instruction::GetPoolInfo => process_get_pool_info(program_id, accounts),
+        Instruction::GetPlayerInfo => process_get_player_info(program_id, accounts),
+        Instruction::QuoteTicketPrice { count } => {
+            process_quote_ticket_price(program_id, accounts, count)
+        }
+        Instruction::GetRoundResult => process_get_round_result(program_id, accounts),
+        Instruction::MigrateAccount => process_migrate_account(program_id, accounts),
+        Instruction::BeginDraw => process_begin_draw(program_id, accounts),
+        Instruction::PayDrawWinners(draft_winners) => {
+            process_pay_draw_winners(program_id, accounts, draft_winners)
+        }
+        Instruction::FinalizeDraw => process_finalize_draw(program_id, accounts),
+        Instruction::AbortDraw => process_abort_draw(program_id, accounts),
+        Instruction::CommitSavingsDraw => process_commit_savings_draw(program_id, accounts),
+        Instruction::SelectTransferRound { round_id } => {
+            process_select_transfer_round(program_id, accounts, round_id)
+        }
     }
 }
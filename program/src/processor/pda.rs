@@ -0,0 +1,61 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
+    pubkey::Pubkey,
+    system_instruction,
+};
+
+/// Creates the PDA `account` owned by `owner`, funded with `lamports` by the payer.
+/// `create_account` fails once anyone sent lamports to the address, so a pre-funded PDA is topped up
+/// to `lamports`, allocated and assigned under its seeds instead.
+pub(crate) fn create_pda_account<'a>(
+    payer_account: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+    system_program_account: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let accounts = [
+        payer_account.clone(),
+        account.clone(),
+        system_program_account.clone(),
+    ];
+
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                account.key,
+                lamports,
+                space,
+                owner,
+            ),
+            &accounts,
+            &[signer_seeds],
+        );
+    }
+
+    let missing_lamports = lamports.saturating_sub(account.lamports());
+
+    if missing_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_account.key, account.key, missing_lamports),
+            &accounts,
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(account.key, space),
+        &accounts,
+        &[signer_seeds],
+    )?;
+
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &accounts,
+        &[signer_seeds],
+    )
+}
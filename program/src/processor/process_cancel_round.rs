@@ -0,0 +1,72 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{PoolMode, ProgramAccount, RoundStatus},
+};
+
+use super::{
+    find_stake_pool_vault_pda, process_open_round::load_current_round, validation::PoolVault,
+};
+
+/// Cancels the current round of a lottery pool, its sales become refunds owed to the players.
+/// The authority can cancel the round at any time, anyone else only once the cancel timeout
+/// has elapsed after the draw slot without the round being settled.
+pub fn process_cancel_round(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The pool authority, or anyone once the round timed out
+    let caller_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The current round of the pool
+    let round_account = next_account_info(&mut accounts)?;
+
+    if !caller_account.is_signer {
+        return Err(LotteryError::InvalidSigner.into());
+    }
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let mut round_data =
+        load_current_round(program_id, pool_vault_account, round_account, &pool_storage)?;
+
+    if round_data.status != RoundStatus::Open {
+        return Err(LotteryError::RoundNotOpen.into());
+    }
+
+    let is_authority =
+        find_stake_pool_vault_pda(program_id, caller_account.key).0 == *pool_vault_account.key;
+
+    let slot = Clock::get()?.slot;
+
+    if !is_authority && slot < round_data.cancel_slot(&pool_storage) {
+        return Err(LotteryError::CancelTooEarly.into());
+    }
+
+    pool_storage.cancel_round(&mut round_data, slot)?;
+
+    round_data.store(&mut round_account.data.borrow_mut())?;
+    pool_vault.store(&pool_storage)?;
+
+    LotteryEvent::RoundCancelled {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        refunds: round_data.cash_raised(),
+    }
+    .emit();
+
+    Ok(())
+}
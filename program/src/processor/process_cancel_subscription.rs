@@ -0,0 +1,49 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::{error::LotteryError, events::LotteryEvent};
+
+use super::process_subscribe::find_subscription_pda;
+
+/// Cancels the subscription of the player, closing it and returning the unspent escrow along with its rent.
+pub fn process_cancel_subscription(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The subscribed player, receives the escrow
+    let player_account = next_account_info(&mut accounts)?;
+    // Stake pool vault, not loaded so the subscription can still be cancelled once the pool is closed
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The subscription of the player to the pool
+    let subscription_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    if subscription_account.owner != program_id
+        || find_subscription_pda(program_id, pool_vault_account.key, player_account.key).0
+            != *subscription_account.key
+    {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let refund = subscription_account.lamports();
+
+    **player_account.try_borrow_mut_lamports()? += refund;
+    **subscription_account.try_borrow_mut_lamports()? = 0;
+
+    subscription_account.realloc(0, false)?;
+    subscription_account.assign(&system_program::id());
+
+    LotteryEvent::SubscriptionCancelled {
+        pool_vault: *pool_vault_account.key,
+        owner: *player_account.key,
+        refund,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{ProgramAccount, RoundData, RoundStatus, RoundTicketsData},
+};
+
+use super::{
+    find_round_pda, find_round_tickets_pda,
+    process_register_attestation::enforce_attestation,
+    validation::{PlayerTicketAccount, PlayerTokenAccount, PoolVault, ReceiptMint},
+};
+
+/// Pays the prize of a settled round to the player holding the winning ticket,
+/// the receipt of the winning ticket is burned.
+pub fn process_claim_prize(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The winner
+    let player_account = next_account_info(&mut accounts)?;
+    // The tickets of the player in the round
+    let round_tickets_account = next_account_info(&mut accounts)?;
+    // The settled round
+    let round_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Pool authority
+    let pool_authority_account = next_account_info(&mut accounts)?;
+    // Stake pool mint account
+    let pool_mint_account = next_account_info(&mut accounts)?;
+    // The player token account
+    let player_token_pda_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+    // Account PDA for the player, holding its attestation when the pool requires one
+    let player_pda_account = next_account_info(&mut accounts).ok();
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, pool_authority_account.key)?.writable()?;
+
+    // The receipt burned is that of the winner
+    PlayerTokenAccount::new(program_id, player_token_pda_account, player_account.key)?
+        .writable()?;
+
+    if round_tickets_account.owner != program_id
+        || find_round_tickets_pda(program_id, round_account.key, player_account.key).0
+            != *round_tickets_account.key
+    {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    let round_tickets = RoundTicketsData::load(&round_tickets_account.data.borrow())?;
+
+    if round_account.owner != program_id
+        || find_round_pda(program_id, pool_vault_account.key, round_tickets.round_id).0
+            != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    let mut round_data = RoundData::load(&round_account.data.borrow())?;
+
+    if round_data.status != RoundStatus::Settled {
+        return Err(LotteryError::RoundNotSettled.into());
+    }
+
+    if round_data.prize_claimed || round_data.prize_swept {
+        return Err(LotteryError::PrizeAlreadyClaimed.into());
+    }
+
+    if round_data.claim_expired(Clock::get()?.slot) {
+        return Err(LotteryError::ClaimExpired.into());
+    }
+
+    if round_data.tickets_sold == 0 || !round_tickets.holds_ticket(round_data.winning_ticket) {
+        return Err(LotteryError::NotAWinner.into());
+    }
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.requires_attestation() {
+        let player_pda = PlayerTicketAccount::new(
+            program_id,
+            player_pda_account.ok_or(LotteryError::AttestationRequired)?,
+            player_account.key,
+        )?;
+
+        enforce_attestation(program_id, &pool_storage, player_pda.account)?;
+    }
+
+    if round_data.prize > pool_vault_account.lamports() {
+        return Err(LotteryError::InsufficientFunds.into());
+    }
+
+    round_data.prize_claimed = true;
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_sub(round_data.prize)
+        .ok_or(LotteryError::InsufficientFunds)?;
+
+    round_data.store(&mut round_account.data.borrow_mut())?;
+    pool_vault.store(&pool_storage)?;
+
+    **pool_vault_account.try_borrow_mut_lamports()? -= round_data.prize;
+    **player_account.try_borrow_mut_lamports()? += round_data.prize;
+
+    let burn_instr = spl_token_2022::instruction::burn(
+        &spl_token_2022::id(),
+        player_token_pda_account.key,
+        pool_mint_account.key,
+        pool_mint_account.key,
+        &[],
+        1,
+    )?;
+
+    invoke_signed(
+        &burn_instr,
+        &[
+            player_token_pda_account.clone(),
+            pool_mint_account.clone(),
+            spl_2022_account.clone(),
+        ],
+        &[&receipt_mint.signer_seeds()],
+    )?;
+
+    LotteryEvent::PrizeClaimed {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        winner: *player_account.key,
+        prize: round_data.prize,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -1,15 +1,17 @@
 use solana_program::{
-    account_info::next_account_info, account_info::AccountInfo, entrypoint::ProgramResult,
-    program::invoke_signed, pubkey::Pubkey,
+    account_info::next_account_info, account_info::AccountInfo, clock::Clock,
+    entrypoint::ProgramResult, program::invoke_signed, pubkey::Pubkey, sysvar::Sysvar,
 };
 
-use solana_program::program_pack::Pack;
+use spl_token_2022::{extension::StateWithExtensions, state::Account};
 
-use spl_token_2022::state::Account;
+use crate::{error::LotteryError, events::LotteryEvent};
 
-use crate::{error::LotteryError, state::PoolStorageSeed};
-
-use super::find_stake_pool_mint_pda;
+use super::{
+    process_set_player_limits::load_player_limits,
+    process_sponsored_purchase::load_intent_nonce,
+    validation::{PlayerTicketAccount, PlayerTokenAccount, ReceiptMint},
+};
 
 pub fn process_close_player_account(
     program_id: &Pubkey,
@@ -26,28 +28,47 @@ pub fn process_close_player_account(
         return Err(LotteryError::InvalidSigner.into());
     }
 
-    if player_pda_account.data_is_empty() {
+    let player_pda =
+        PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?.writable()?;
+    let player_token_pda =
+        PlayerTokenAccount::new(program_id, player_token_pda_account, player_account.key)?
+            .writable()?;
+
+    if !player_pda.is_initialized() || !player_token_pda.is_initialized() {
         return Err(LotteryError::InvalidAccount.into());
     }
 
-    if player_token_pda_account.data_is_empty() {
-        return Err(LotteryError::InvalidAccount.into());
+    // Closing the player PDA would drop its limits along with it
+    let now = Clock::get()?.unix_timestamp;
+
+    if load_player_limits(program_id, player_pda_account)?
+        .is_some_and(|limits_data| limits_data.are_active(now))
+    {
+        return Err(LotteryError::PlayerLimitsActive.into());
+    }
+
+    // A recreated player PDA would start over at nonce 0, letting the intents already used be replayed
+    if load_intent_nonce(player_pda_account)? != 0 {
+        return Err(LotteryError::IntentNonceInUse.into());
     }
 
-    let token_account_unpacked = Account::unpack(&player_token_pda_account.try_borrow_data()?)?;
+    let receipt_mint = ReceiptMint::new(program_id, mint_account, pool_authority.key)?;
+
+    let token_account =
+        StateWithExtensions::<Account>::unpack(&player_token_pda_account.try_borrow_data()?)?.base;
+    let token_amount = token_account.amount;
 
     // Close the token_account if its amount is 0
-    if token_account_unpacked.amount == 0 {
+    if token_amount == 0 {
         let ix = spl_token_2022::instruction::close_account(
             &spl_token_2022::ID,
             player_token_pda_account.key,
             player_account.key,
-            &mint_account.key,
+            // Transferable receipts are held by the player
+            &token_account.owner,
             &[],
         )?;
 
-        let auth_bump = find_stake_pool_mint_pda(program_id, pool_authority.key).1;
-
         invoke_signed(
             &ix,
             &[
@@ -55,11 +76,7 @@ pub fn process_close_player_account(
                 player_token_pda_account.clone(),
                 player_account.clone(),
             ],
-            &[&[
-                PoolStorageSeed::ReceiptMint.as_bytes(),
-                pool_authority.key.as_ref(),
-                &[auth_bump],
-            ]],
+            &[&receipt_mint.signer_seeds()],
         )?;
     }
 
@@ -68,7 +85,13 @@ pub fn process_close_player_account(
     **player_account_lamports += player_pda_account.lamports();
     **player_pda_account.try_borrow_mut_lamports()? = 0;
 
-    player_pda_account.assign(&program_id);
+    player_pda_account.assign(program_id);
     player_pda_account.realloc(0, false)?;
+
+    LotteryEvent::AccountClosed {
+        player: *player_account.key,
+    }
+    .emit();
+
     Ok(())
 }
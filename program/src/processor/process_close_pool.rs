@@ -0,0 +1,160 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    pubkey::Pubkey,
+    system_program,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{
+    extension::{ExtensionType, StateWithExtensions},
+    state::{Account, Mint},
+};
+
+use crate::{error::LotteryError, events::LotteryEvent};
+
+use super::{
+    find_round_pda,
+    validation::{PoolVault, ReceiptMint},
+};
+
+/// Winds down a pool, closing its receipt mint and its vault and returning their lamports to the authority,
+/// the receipt mint of a pool of the first release can't be closed and is left empty.
+/// The current round must not be opened and the pool must not owe anything. When receipts are still
+/// outstanding past the grace period, the player token accounts holding them are passed to burn them.
+pub fn process_close_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Pool authority, receives the lamports
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Stake pool mint account
+    let pool_mint_account = next_account_info(&mut accounts)?;
+    // The current round of the pool, must not be opened
+    let round_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, authority_account.key)?.writable()?;
+
+    let pool_storage = pool_vault.load()?;
+
+    if find_round_pda(program_id, pool_vault_account.key, pool_storage.draft_count).0
+        != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    if !round_account.data_is_empty() {
+        return Err(LotteryError::RoundStillOpen.into());
+    }
+
+    if pool_storage.liabilities > 0
+        || pool_storage.total_principal > 0
+        || pool_storage.prize_reserve > 0
+    {
+        return Err(LotteryError::OutstandingLiabilities.into());
+    }
+
+    let mint_seeds = receipt_mint.signer_seeds();
+
+    if receipt_supply(pool_mint_account)? > 0 {
+        let grace_end = pool_storage
+            .last_round_slot
+            .saturating_add(pool_storage.close_grace_slots);
+
+        if Clock::get()?.slot < grace_end {
+            return Err(LotteryError::ReceiptsOutstanding.into());
+        }
+
+        // The token accounts are owned by the mint, so the program burns the leftover receipts.
+        for player_token_account in accounts {
+            let amount = {
+                let data = player_token_account.data.borrow();
+                let token_account = StateWithExtensions::<Account>::unpack(&data)?;
+
+                if token_account.base.mint != *pool_mint_account.key {
+                    return Err(LotteryError::InvalidAccount.into());
+                }
+
+                token_account.base.amount
+            };
+
+            if amount == 0 {
+                continue;
+            }
+
+            invoke_signed(
+                &spl_token_2022::instruction::burn(
+                    &spl_token_2022::id(),
+                    player_token_account.key,
+                    pool_mint_account.key,
+                    pool_mint_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    player_token_account.clone(),
+                    pool_mint_account.clone(),
+                    spl_2022_account.clone(),
+                ],
+                &[&mint_seeds],
+            )?;
+        }
+
+        if receipt_supply(pool_mint_account)? > 0 {
+            return Err(LotteryError::ReceiptsOutstanding.into());
+        }
+    }
+
+    // The mints of the first release have no close authority, they are left behind with no supply
+    if receipt_mint.has_extension(ExtensionType::MintCloseAuthority)? {
+        invoke_signed(
+            &spl_token_2022::instruction::close_account(
+                &spl_token_2022::id(),
+                pool_mint_account.key,
+                authority_account.key,
+                pool_mint_account.key,
+                &[],
+            )?,
+            &[
+                pool_mint_account.clone(),
+                authority_account.clone(),
+                spl_2022_account.clone(),
+            ],
+            &[&mint_seeds],
+        )?;
+    }
+
+    **authority_account.try_borrow_mut_lamports()? += pool_vault_account.lamports();
+    **pool_vault_account.try_borrow_mut_lamports()? = 0;
+
+    pool_vault_account.realloc(0, false)?;
+    pool_vault_account.assign(&system_program::id());
+
+    LotteryEvent::PoolClosed {
+        pool_vault: *pool_vault_account.key,
+        authority: *authority_account.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn receipt_supply(
+    pool_mint_account: &AccountInfo,
+) -> Result<u64, solana_program::program_error::ProgramError> {
+    let data = pool_mint_account.data.borrow();
+
+    Ok(StateWithExtensions::<Mint>::unpack(&data)?.base.supply)
+}
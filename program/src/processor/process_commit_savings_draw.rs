@@ -0,0 +1,60 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{error::LotteryError, events::LotteryEvent, state::PoolMode};
+
+use super::validation::PoolVault;
+
+/// Commits the slot whose hash draws the prize of a savings pool, the draw buffer after the current slot.
+/// The hash isn't known to anyone when the draw is committed, and the principal is frozen from the draw
+/// slot until the draw. A committed draw can only be replaced once its hash can't be read anymore.
+pub fn process_commit_savings_draw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Pool authority
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Savings {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let slot = Clock::get()?.slot;
+
+    if pool_storage.savings_draw_slot != 0
+        && (slot < pool_storage.savings_draw_slot || pool_storage.savings_draw_pending(slot))
+    {
+        return Err(LotteryError::SavingsDrawCommitted.into());
+    }
+
+    let draw_slot = slot
+        .checked_add(pool_storage.draw_buffer_slots.max(1))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    pool_storage.savings_draw_slot = draw_slot;
+    pool_vault.store(&pool_storage)?;
+
+    LotteryEvent::SavingsDrawCommitted {
+        pool_vault: *pool_vault_account.key,
+        draw_slot,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{ProgramAccount, SubscriptionData},
+};
+
+use super::{
+    process_purchase_ticket::{purchase_tickets, TicketFunding},
+    process_subscribe::find_subscription_pda,
+    validation::PoolVault,
+};
+
+/// Buys the tickets of a subscription in the current round, anyone can crank it once per round.
+/// The subscription comes first, followed by the accounts of a ticket purchase paid by the cranker,
+/// with the subscribed player following the instructions sysvar. The escrow pays the tickets and
+/// pays the cranker back the rent of the player accounts it created. The tickets of the subscription
+/// are declared by the instruction, so they are counted against the tickets per transaction.
+pub fn process_crank_subscription(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    tickets_per_round: u64,
+) -> ProgramResult {
+    let mut accounts_iter = accounts.iter();
+    // The subscription of the player to the pool
+    let subscription_account = next_account_info(&mut accounts_iter)?;
+
+    let purchase_accounts = accounts_iter.as_slice();
+
+    let pool_authority_account = next_account_info(&mut accounts_iter)?;
+    // Whoever cranks the subscription, pays the rent of the player accounts upfront
+    let cranker_account = next_account_info(&mut accounts_iter)?;
+
+    let _player_pda_account = next_account_info(&mut accounts_iter)?;
+
+    let _player_token_pda_account = next_account_info(&mut accounts_iter)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts_iter)?;
+
+    let _pool_mint_account = next_account_info(&mut accounts_iter)?;
+
+    let _rent_account = next_account_info(&mut accounts_iter)?;
+
+    let _spl_2022_account = next_account_info(&mut accounts_iter)?;
+
+    let _system_account = next_account_info(&mut accounts_iter)?;
+
+    let _round_account = next_account_info(&mut accounts_iter)?;
+
+    let _round_tickets_account = next_account_info(&mut accounts_iter)?;
+
+    let _instructions_sysvar_account = next_account_info(&mut accounts_iter)?;
+    // The subscribed player
+    let player_account = next_account_info(&mut accounts_iter)?;
+
+    PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?;
+
+    if subscription_account.owner != program_id
+        || find_subscription_pda(program_id, pool_vault_account.key, player_account.key).0
+            != *subscription_account.key
+    {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let mut subscription = SubscriptionData::load(&subscription_account.data.borrow())?;
+
+    if subscription.tickets_per_round != tickets_per_round {
+        return Err(LotteryError::InvalidSubscription.into());
+    }
+
+    let cranker_lamports = cranker_account.lamports();
+
+    let round_data = purchase_tickets(
+        program_id,
+        purchase_accounts,
+        subscription.tickets_per_round,
+        None,
+        TicketFunding::Escrow,
+        None,
+    )?;
+
+    if subscription.last_round_id == Some(round_data.round_id) {
+        return Err(LotteryError::SubscriptionAlreadyPlayed.into());
+    }
+
+    let ticket_cost = round_data
+        .ticket_price
+        .checked_mul(subscription.tickets_per_round)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let rent_paid = cranker_lamports.saturating_sub(cranker_account.lamports());
+
+    subscription.escrow = ticket_cost
+        .checked_add(rent_paid)
+        .and_then(|cost| subscription.escrow.checked_sub(cost))
+        .ok_or(LotteryError::SubscriptionEscrowExhausted)?;
+    subscription.last_round_id = Some(round_data.round_id);
+    subscription.rounds_played += 1;
+
+    subscription.store(&mut subscription_account.data.borrow_mut())?;
+
+    **subscription_account.try_borrow_mut_lamports()? -= ticket_cost + rent_paid;
+    **pool_vault_account.try_borrow_mut_lamports()? += ticket_cost;
+    **cranker_account.try_borrow_mut_lamports()? += rent_paid;
+
+    LotteryEvent::SubscriptionCranked {
+        pool_vault: *pool_vault_account.key,
+        owner: *player_account.key,
+        round_id: round_data.round_id,
+        cost: ticket_cost + rent_paid,
+    }
+    .emit();
+
+    Ok(())
+}
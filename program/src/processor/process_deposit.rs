@@ -2,27 +2,41 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     program::invoke,
+    program_error::ProgramError,
     pubkey::Pubkey,
     system_instruction,
 };
 
+use crate::events::LotteryEvent;
+
+use super::validation::PoolVault;
+
 pub fn process_deposit(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     amount: u64,
 ) -> ProgramResult {
-    let mut accounts = accounts.into_iter();
+    let mut accounts = accounts.iter();
     let payer = next_account_info(&mut accounts)?;
     let pool_vault = next_account_info(&mut accounts)?;
     let _system_program_account = next_account_info(&mut accounts)?;
 
-    if pool_vault.owner != program_id {
-        return Err(solana_program::program_error::ProgramError::IllegalOwner);
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
     }
 
+    PoolVault::new(program_id, pool_vault)?.writable()?;
+
     let instr = system_instruction::transfer(payer.key, pool_vault.key, amount);
 
     invoke(&instr, &[payer.clone(), pool_vault.clone()])?;
 
+    LotteryEvent::Deposit {
+        pool_vault: *pool_vault.key,
+        payer: *payer.key,
+        amount,
+    }
+    .emit();
+
     Ok(())
 }
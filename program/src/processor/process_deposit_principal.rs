@@ -0,0 +1,217 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        PoolMode, PoolStorageSeed, ProgramAccount, SavingsAccountData, SAVINGS_ACCOUNT_DATA_SIZE,
+    },
+};
+
+use super::{
+    pda::create_pda_account,
+    process_purchase_ticket::initialize_player_token_account,
+    validation::{PoolVault, ReceiptMint},
+};
+
+/// Process a deposit into a savings pool.
+/// The deposited lamports are kept in the pool vault as the player principal and
+/// the player receives one receipt share per lamport deposited.
+pub fn process_deposit_principal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Pool authority
+    let pool_authority_account = next_account_info(&mut accounts)?;
+    // The depositor
+    let player_account = next_account_info(&mut accounts)?;
+    // The savings PDA of the player for this pool
+    let savings_pda_account = next_account_info(&mut accounts)?;
+    // The player token account receiving the shares
+    let player_token_pda_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Stake pool mint account
+    let pool_mint_account = next_account_info(&mut accounts)?;
+
+    let rent_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, pool_authority_account.key)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Savings {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    if savings_pda_account.data_is_empty() {
+        initialize_savings_account(
+            program_id,
+            player_account,
+            savings_pda_account,
+            pool_vault_account,
+            system_account,
+        )?;
+    }
+
+    if player_token_pda_account.data_is_empty() {
+        initialize_player_token_account(
+            program_id,
+            pool_authority_account,
+            player_account,
+            player_account,
+            player_token_pda_account,
+            pool_mint_account,
+            rent_account,
+        )?;
+    }
+
+    let mut savings_data = SavingsAccountData::load(&savings_pda_account.data.borrow())?;
+
+    if savings_pda_account.owner != program_id || savings_data.owner != *player_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let slot = Clock::get()?.slot;
+
+    if pool_storage.savings_draw_pending(slot) {
+        return Err(LotteryError::SavingsDrawPending.into());
+    }
+
+    pool_storage.accrue_weight(slot)?;
+    savings_data.accrue_weight(&pool_storage, slot)?;
+
+    invoke(
+        &system_instruction::transfer(player_account.key, pool_vault_account.key, amount),
+        &[
+            player_account.clone(),
+            pool_vault_account.clone(),
+            system_account.clone(),
+        ],
+    )?;
+
+    savings_data.principal = savings_data
+        .principal
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool_storage.total_principal = pool_storage
+        .total_principal
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    savings_data.store(&mut savings_pda_account.data.borrow_mut())?;
+    pool_vault.store(&pool_storage)?;
+
+    let mint_shares_instr = spl_token_2022::instruction::mint_to(
+        &spl_token_2022::id(),
+        pool_mint_account.key,
+        player_token_pda_account.key,
+        pool_mint_account.key,
+        &[],
+        amount,
+    )?;
+
+    invoke_signed(
+        &mint_shares_instr,
+        &[
+            pool_mint_account.clone(),
+            player_token_pda_account.clone(),
+            spl_2022_account.clone(),
+        ],
+        &[&receipt_mint.signer_seeds()],
+    )?;
+
+    LotteryEvent::PrincipalDeposited {
+        pool_vault: *pool_vault_account.key,
+        player: *player_account.key,
+        amount,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn find_savings_pda_account(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    player_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::SavingsAccount.as_bytes(),
+            pool_vault_account.as_ref(),
+            player_account.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+// Creates the savings PDA of the player, the weight starts accruing from the first deposit.
+fn initialize_savings_account<'a>(
+    program_id: &Pubkey,
+    player_account: &AccountInfo<'a>,
+    savings_pda_account: &AccountInfo<'a>,
+    pool_vault_account: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+) -> ProgramResult {
+    let (savings_address, bump) =
+        find_savings_pda_account(program_id, pool_vault_account.key, player_account.key);
+
+    if savings_address != *savings_pda_account.key {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    let rent = Rent::get()?;
+
+    create_pda_account(
+        player_account,
+        savings_pda_account,
+        rent.minimum_balance(SAVINGS_ACCOUNT_DATA_SIZE as usize),
+        SAVINGS_ACCOUNT_DATA_SIZE as u64,
+        program_id,
+        system_program_account,
+        &[
+            PoolStorageSeed::SavingsAccount.as_bytes(),
+            pool_vault_account.key.as_ref(),
+            player_account.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+
+    let savings_data = SavingsAccountData {
+        owner: *player_account.key,
+        principal: 0,
+        weight: 0,
+        updated_slot: 0,
+        period: 0,
+    };
+
+    savings_data.store(&mut savings_pda_account.data.borrow_mut())?;
+
+    Ok(())
+}
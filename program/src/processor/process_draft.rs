@@ -1,25 +1,33 @@
-use borsh::{BorshDeserialize, BorshSerialize};
 use rs_merkle::{algorithms::Sha256, MerkleProof};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
+    msg,
     program::invoke_signed,
+    program_error::ProgramError,
     pubkey::Pubkey,
+    sysvar::Sysvar,
 };
 
 use crate::{
     error::LotteryError,
-    processor::find_stake_pool_mint_pda,
-    state::{DraftWinner, PoolStorageData, PoolStorageSeed, TicketAccountData},
+    events::LotteryEvent,
+    state::{
+        DraftWinner, DrawSessionData, PoolMode, PoolStorageData, ProgramAccount, RoundData,
+        RoundStatus, TicketAccountData,
+    },
 };
 
-use super::find_stake_pool_vault_pda;
+use super::{
+    process_open_round::load_current_round,
+    validation::{PoolVault, ReceiptMint},
+};
 
+#[allow(clippy::too_many_arguments)]
 fn process_winner<'a>(
-    program_id: &Pubkey,
-    pool_authority: &AccountInfo<'a>,
     player_token_account: &AccountInfo<'a>,
-    mint_account: &AccountInfo<'a>,
+    receipt_mint: &ReceiptMint<'_, 'a>,
     pool_vault_account: &AccountInfo<'a>,
     account: &AccountInfo<'a>,
     amount: u64,
@@ -27,8 +35,8 @@ fn process_winner<'a>(
     proof: Vec<u8>,
     ticket_indices: Vec<usize>,
 ) -> ProgramResult {
-    let account_data = TicketAccountData::try_from_slice(&account.data.borrow())?;
-    let proof = MerkleProof::<Sha256>::try_from(proof).expect("Provided invalid proof");
+    let account_data = TicketAccountData::load(&account.data.borrow())?;
+    let proof = MerkleProof::<Sha256>::try_from(proof).map_err(|_| LotteryError::InvalidTicket)?;
 
     // Verify inclusion of the ticket in the merkle tree
     if proof.verify(
@@ -45,28 +53,28 @@ fn process_winner<'a>(
         **pool_vault_account.try_borrow_mut_lamports()? -= amount;
         **account.try_borrow_mut_lamports()? += amount;
 
-        // @todo:
-        // - Burn the receipt token
+        LotteryEvent::WinnerPaid {
+            pool_vault: *pool_vault_account.key,
+            winner: *account.key,
+            amount,
+        }
+        .emit();
+
+        // The receipt of the winner is burned
         let burn_instr = spl_token_2022::instruction::burn_checked(
             &spl_token_2022::ID,
-            &player_token_account.key,
-            &mint_account.key,
-            &mint_account.key,
+            player_token_account.key,
+            receipt_mint.account.key,
+            receipt_mint.account.key,
             &[],
             1,
             0,
         )?;
 
-        let auth_bump = find_stake_pool_mint_pda(program_id, pool_authority.key).1;
-
         invoke_signed(
             &burn_instr,
-            &[player_token_account.clone(), mint_account.clone()],
-            &[&[
-                PoolStorageSeed::ReceiptMint.as_bytes(),
-                pool_authority.key.as_ref(),
-                &[auth_bump],
-            ]],
+            &[player_token_account.clone(), receipt_mint.account.clone()],
+            &[&receipt_mint.signer_seeds()],
         )?;
     } else {
         return Err(LotteryError::InvalidTicket.into());
@@ -75,6 +83,110 @@ fn process_winner<'a>(
     Ok(())
 }
 
+/// Settles the current round of the pool for its winners to be drawn, once its draw slot has passed.
+/// The referral rewards of the round are owed from then on.
+pub(crate) fn settle_drawn_round(
+    program_id: &Pubkey,
+    pool_vault_account: &AccountInfo,
+    round_account: &AccountInfo,
+    pool_storage: &mut PoolStorageData,
+) -> Result<RoundData, ProgramError> {
+    // The principal of a savings pool can't be airdropped, only the harvested yield is drawn.
+    if pool_storage.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let mut round_data =
+        load_current_round(program_id, pool_vault_account, round_account, pool_storage)?;
+
+    let slot = Clock::get()?.slot;
+
+    if slot < round_data.draw_slot(pool_storage) {
+        return Err(LotteryError::DrawTooEarly.into());
+    }
+
+    // Small rounds are refunded through the settlement instead of being drawn
+    if !round_data.meets_minimum_participation() {
+        return Err(LotteryError::MinimumParticipationNotMet.into());
+    }
+
+    round_data.status = RoundStatus::Settled;
+    round_data.store(&mut round_account.data.borrow_mut())?;
+
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_add(round_data.referral_fees)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool_storage.complete_round(slot);
+
+    Ok(round_data)
+}
+
+/// Books the house fee of the drawn round and hands the pool jackpot to its prize pool, which is
+/// returned once the vault is checked to cover it on top of what it already owes and the house balance.
+pub(crate) fn fund_prize_pool(
+    pool_vault_account: &AccountInfo,
+    round_data: &RoundData,
+    pool_storage: &mut PoolStorageData,
+) -> Result<u64, ProgramError> {
+    let prize_pool = round_data.settled_prize(pool_storage)?;
+
+    // The house keeps its fee, out of which the referral rewards of the round are paid
+    let house_share = pool_storage
+        .house_fee(round_data.lamports_raised)
+        .checked_sub(round_data.referral_fees)
+        .ok_or(LotteryError::InsufficientFunds)?;
+
+    pool_storage.house_balance = pool_storage
+        .house_balance
+        .checked_add(house_share)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool_storage.jackpot = 0;
+
+    let available = pool_vault_account
+        .lamports()
+        .saturating_sub(pool_storage.liabilities)
+        .saturating_sub(pool_storage.house_balance);
+
+    if prize_pool > available {
+        return Err(LotteryError::InsufficientFunds.into());
+    }
+
+    Ok(prize_pool)
+}
+
+/// Pays a drafted winner, finding its player PDA and token account among the winner accounts.
+pub(crate) fn pay_draft_winner<'a>(
+    winner_accounts: &[AccountInfo<'a>],
+    receipt_mint: &ReceiptMint<'_, 'a>,
+    pool_vault_account: &AccountInfo<'a>,
+    winner: &DraftWinner,
+) -> ProgramResult {
+    let find_account = |address: &Pubkey| {
+        winner_accounts
+            .iter()
+            .find(|account| account.key == address)
+            .ok_or_else(|| {
+                msg!("Missing account {}", address);
+                ProgramError::NotEnoughAccountKeys
+            })
+    };
+
+    let account_info = find_account(&winner.address)?;
+    let player_token_account = find_account(&winner.token_account)?;
+
+    process_winner(
+        player_token_account,
+        receipt_mint,
+        pool_vault_account,
+        account_info,
+        winner.amount,
+        winner.tickets.clone(),
+        winner.proof.clone(),
+        winner.ticket_indices.clone(),
+    )
+}
+
 pub fn process_draft(
     program_id: &Pubkey,
     accounts: &Vec<AccountInfo>,
@@ -88,54 +200,63 @@ pub fn process_draft(
 
     let mint_account = next_account_info(&mut accounts)?;
 
-    let mut pool_vault_data = pool_vault_account.try_borrow_mut_data()?;
-
-    let mut pool_storage = PoolStorageData::deserialize(&mut &**pool_vault_data)?;
-    pool_storage.draft_count += 1;
+    let round_account = next_account_info(&mut accounts)?;
 
-    pool_storage.serialize(&mut &mut **pool_vault_data)?;
     // Verify that the authority is the signer of the transaction
     if !authority_account.is_signer {
         return Err(LotteryError::AuthorityMustSign.into());
     }
 
-    // Verify that the stake pool account is owned by the program
-    if pool_vault_account.owner != program_id {
-        return Err(LotteryError::IncorrectOwner.into());
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, mint_account, authority_account.key)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    let round_data = settle_drawn_round(
+        program_id,
+        pool_vault_account,
+        round_account,
+        &mut pool_storage,
+    )?;
+
+    let prize_pool = fund_prize_pool(pool_vault_account, &round_data, &mut pool_storage)?;
+
+    // The winners are paid within the prize pool of the round like a draw session paid at once,
+    // the unpaid remainder goes back to the jackpot
+    let mut draw = DrawSessionData {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        prize_pool,
+        remaining_budget: prize_pool,
+        paid_winners: Vec::new(),
+    };
+
+    for winner in &draft_winners {
+        draw.record_payment(winner.address, winner.amount)?;
     }
 
-    let (stake_pool_pda, ..) = find_stake_pool_vault_pda(program_id, authority_account.key);
+    pool_storage.jackpot = draw.remaining_budget;
+    pool_vault.store(&pool_storage)?;
 
-    // Verify that the stake pool account is the correct one
-    if stake_pool_pda != *pool_vault_account.key {
-        return Err(LotteryError::InvalidStakePoolVault.into());
+    // The winners are drafted off-chain, there is no winning ticket nor crank tip
+    LotteryEvent::RoundSettled {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        tickets_sold: round_data.tickets_sold,
+        winning_ticket: 0,
+        prize: prize_pool - draw.remaining_budget,
+        house_fee: pool_storage.house_fee(round_data.lamports_raised),
+        crank_tip: 0,
     }
-    // @todo:
-    // - Verify if all of the prizes combined is larger than the stake pool amount
-    draft_winners
-        .iter()
-        .map(|winner| -> ProgramResult {
-            let account_info = accounts
-                .find(|account| account.key == &winner.address)
-                .expect(
-                    format!("Unable to find the account {} in the list", winner.address).as_str(),
-                );
-            let player_token_account = accounts
-                .find(|account| account.key == &winner.token_account)
-                .expect("Unable to find the token account in the list");
-
-            process_winner(
-                program_id,
-                authority_account,
-                player_token_account,
-                mint_account,
-                pool_vault_account,
-                account_info,
-                winner.amount,
-                winner.tickets.clone(),
-                winner.proof.clone(),
-                winner.ticket_indices.clone(),
-            )
-        })
-        .collect()
+    .emit();
+
+    let winner_accounts = accounts.as_slice();
+
+    draft_winners.iter().try_for_each(|winner| {
+        pay_draft_winner(winner_accounts, &receipt_mint, pool_vault_account, winner)
+    })
 }
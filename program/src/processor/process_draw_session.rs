@@ -0,0 +1,289 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        DraftWinner, DrawSessionData, PoolStorageSeed, ProgramAccount, DRAW_SESSION_DATA_SIZE,
+    },
+};
+
+use super::{
+    pda::create_pda_account,
+    process_draft::{fund_prize_pool, pay_draft_winner, settle_drawn_round},
+    validation::{PoolVault, ReceiptMint},
+};
+
+pub fn find_draw_session_pda(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    round_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::DrawSession.as_bytes(),
+            pool_vault_account.as_ref(),
+            &round_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Loads the draw session of the pool, verifying the account is the PDA of its round.
+fn load_draw_session(
+    program_id: &Pubkey,
+    pool_vault_account: &AccountInfo,
+    draw_session_account: &AccountInfo,
+) -> Result<DrawSessionData, ProgramError> {
+    if draw_session_account.owner != program_id {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let draw_session = DrawSessionData::load(&draw_session_account.data.borrow())?;
+
+    if draw_session.pool_vault != *pool_vault_account.key
+        || find_draw_session_pda(program_id, pool_vault_account.key, draw_session.round_id).0
+            != *draw_session_account.key
+    {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    Ok(draw_session)
+}
+
+/// Settles the current round like `SelectWinnersAndAirdrop` and opens its draw session. The prize pool
+/// of the draw is the prize of the round, its sales minus the house fee along with the pool jackpot,
+/// paid out to the winners by `PayDrawWinners`.
+pub fn process_begin_draw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The pool authority, pays for the draw session
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The current round of the pool
+    let round_account = next_account_info(&mut accounts)?;
+    // The draw session of the round
+    let draw_session_account = next_account_info(&mut accounts)?;
+    let system_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    let round_data = settle_drawn_round(
+        program_id,
+        pool_vault_account,
+        round_account,
+        &mut pool_storage,
+    )?;
+
+    let prize_pool = fund_prize_pool(pool_vault_account, &round_data, &mut pool_storage)?;
+
+    // The prize pool is owed until it is paid out or the draw is aborted
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_add(prize_pool)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    pool_vault.store(&pool_storage)?;
+
+    let (draw_session_address, bump) =
+        find_draw_session_pda(program_id, pool_vault_account.key, round_data.round_id);
+
+    if draw_session_address != *draw_session_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    create_pda_account(
+        authority_account,
+        draw_session_account,
+        Rent::get()?.minimum_balance(DRAW_SESSION_DATA_SIZE as usize),
+        DRAW_SESSION_DATA_SIZE as u64,
+        program_id,
+        system_account,
+        &[
+            PoolStorageSeed::DrawSession.as_bytes(),
+            pool_vault_account.key.as_ref(),
+            &round_data.round_id.to_le_bytes(),
+            &[bump],
+        ],
+    )?;
+
+    let draw_session = DrawSessionData {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        prize_pool,
+        remaining_budget: prize_pool,
+        paid_winners: Vec::new(),
+    };
+
+    draw_session.store(&mut draw_session_account.data.borrow_mut())?;
+
+    LotteryEvent::DrawStarted {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        prize_pool,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Pays a batch of the winners of an open draw session, each winner once and within the remaining
+/// prize pool of the draw.
+pub fn process_pay_draw_winners(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    draft_winners: Vec<DraftWinner>,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The pool authority
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault, pays the winners
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The receipt mint, a receipt of each winner is burned
+    let mint_account = next_account_info(&mut accounts)?;
+    // The draw session of the round
+    let draw_session_account = next_account_info(&mut accounts)?;
+    // The player PDA and the token account of each winner
+    let winner_accounts = accounts.as_slice();
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, mint_account, authority_account.key)?.writable()?;
+
+    let mut draw_session = load_draw_session(program_id, pool_vault_account, draw_session_account)?;
+    let mut pool_storage = pool_vault.load()?;
+
+    for winner in &draft_winners {
+        draw_session.record_payment(winner.address, winner.amount)?;
+        pool_storage.liabilities = pool_storage
+            .liabilities
+            .checked_sub(winner.amount)
+            .ok_or(LotteryError::InsufficientFunds)?;
+
+        pay_draft_winner(winner_accounts, &receipt_mint, pool_vault_account, winner)?;
+    }
+
+    pool_vault.store(&pool_storage)?;
+    draw_session.store(&mut draw_session_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Closes the draw session, refunding its rent to the pool authority.
+fn close_draw_session(
+    authority_account: &AccountInfo,
+    draw_session_account: &AccountInfo,
+) -> ProgramResult {
+    **authority_account.try_borrow_mut_lamports()? += draw_session_account.lamports();
+    **draw_session_account.try_borrow_mut_lamports()? = 0;
+
+    draw_session_account.realloc(0, false)?;
+    draw_session_account.assign(&system_program::id());
+
+    Ok(())
+}
+
+/// Closes the draw session once its winners were paid the whole prize pool of the draw, refunding its
+/// rent to the pool authority.
+pub fn process_finalize_draw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The pool authority, receives the rent of the draw session
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The draw session of the round
+    let draw_session_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?;
+
+    let draw_session = load_draw_session(program_id, pool_vault_account, draw_session_account)?;
+
+    if draw_session.remaining_budget != 0 {
+        return Err(LotteryError::DrawTotalsMismatch.into());
+    }
+
+    close_draw_session(authority_account, draw_session_account)?;
+
+    LotteryEvent::DrawFinalized {
+        pool_vault: *pool_vault_account.key,
+        round_id: draw_session.round_id,
+        winners: draw_session.paid_winners.len() as u64,
+        prize_pool: draw_session.prize_pool,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Closes the draw session before its whole prize pool was paid, the unpaid budget is returned to the
+/// pool jackpot and the rent of the session to the pool authority.
+pub fn process_abort_draw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The pool authority, receives the rent of the draw session
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The draw session of the round
+    let draw_session_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let draw_session = load_draw_session(program_id, pool_vault_account, draw_session_account)?;
+    let mut pool_storage = pool_vault.load()?;
+
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_sub(draw_session.remaining_budget)
+        .ok_or(LotteryError::InsufficientFunds)?;
+    pool_storage.jackpot = pool_storage
+        .jackpot
+        .checked_add(draw_session.remaining_budget)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    pool_vault.store(&pool_storage)?;
+
+    close_draw_session(authority_account, draw_session_account)?;
+
+    LotteryEvent::DrawAborted {
+        pool_vault: *pool_vault_account.key,
+        round_id: draw_session.round_id,
+        winners: draw_session.paid_winners.len() as u64,
+        returned: draw_session.remaining_budget,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use borsh::to_vec;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::set_return_data,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    state::{
+        PlayerInfo, ProgramAccount, RoundData, RoundStatus, RoundTicketsData, TicketAccountData,
+    },
+};
+
+use super::{
+    find_round_tickets_pda, process_get_round_result::load_round, validation::PlayerTicketAccount,
+};
+
+/// Returns the summary of the player and of its tickets in a round of the pool, along with the
+/// prize or the refund it can claim from the round. The player PDA and the round tickets are
+/// empty when the player never played, or didn't play the round.
+pub fn process_get_player_info(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The player queried, doesn't need to sign
+    let player_account = next_account_info(&mut accounts)?;
+    // Account PDA for the player
+    let player_pda_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The round queried
+    let round_account = next_account_info(&mut accounts)?;
+    // The tickets of the player in the round
+    let round_tickets_account = next_account_info(&mut accounts)?;
+
+    let player_pda = PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?;
+
+    if find_round_tickets_pda(program_id, round_account.key, player_account.key).0
+        != *round_tickets_account.key
+    {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    let round_data = load_round(program_id, pool_vault_account, round_account)?;
+
+    let ticket_data = if player_pda.is_initialized() {
+        // The player limits may follow the ticket data
+        TicketAccountData::load(&player_pda_account.data.borrow())?
+    } else {
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 0,
+        }
+    };
+
+    let round_tickets = if round_tickets_account.owner == program_id {
+        Some(RoundTicketsData::load(
+            &round_tickets_account.data.borrow(),
+        )?)
+    } else {
+        None
+    };
+
+    let player_info = PlayerInfo {
+        player: *player_account.key,
+        merkle_root: ticket_data.merkle_root,
+        total_tickets: ticket_data.total_tickets,
+        round_id: round_data.round_id,
+        round_tickets: round_tickets
+            .as_ref()
+            .map_or(0, |round_tickets| round_tickets.tickets),
+        claimable: match round_tickets {
+            Some(round_tickets) => claimable(&round_data, &round_tickets, Clock::get()?.slot)?,
+            None => 0,
+        },
+    };
+
+    set_return_data(&to_vec(&player_info)?);
+
+    Ok(())
+}
+
+// What the player can claim from the round, mirroring the prize claim and the ticket refund.
+fn claimable(
+    round_data: &RoundData,
+    round_tickets: &RoundTicketsData,
+    slot: u64,
+) -> Result<u64, ProgramError> {
+    Ok(match round_data.status {
+        RoundStatus::Settled
+            if round_data.tickets_sold > 0
+                && !round_data.prize_claimed
+                && !round_data.prize_swept
+                && !round_data.claim_expired(slot)
+                && round_tickets.holds_ticket(round_data.winning_ticket) =>
+        {
+            round_data.prize
+        }
+        RoundStatus::Cancelled if !round_tickets.refunded => {
+            round_tickets.refund(round_data.ticket_price)?
+        }
+        _ => 0,
+    })
+}
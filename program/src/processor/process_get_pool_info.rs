@@ -0,0 +1,66 @@
+use borsh::to_vec;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::set_return_data,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::LotteryError,
+    state::{PoolInfo, ProgramAccount, RoundData},
+};
+
+use super::{find_round_pda, validation::PoolVault};
+
+/// Returns the summary of the pool and of its current round, the round account
+/// is empty until the round is opened.
+pub fn process_get_pool_info(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The current round of the pool
+    let round_account = next_account_info(&mut accounts)?;
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?;
+
+    let pool_storage = pool_vault.load()?;
+
+    if find_round_pda(program_id, pool_vault_account.key, pool_storage.draft_count).0
+        != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    let round_data = if round_account.data_is_empty() {
+        None
+    } else if round_account.owner != program_id {
+        return Err(LotteryError::InvalidRound.into());
+    } else {
+        Some(RoundData::load(&round_account.data.borrow())?)
+    };
+
+    let pool_info = PoolInfo {
+        mode: pool_storage.mode,
+        ticket_price: pool_storage.ticket_price,
+        current_round_id: pool_storage.draft_count,
+        round_status: round_data.as_ref().map(|round_data| round_data.status),
+        tickets_sold: round_data
+            .as_ref()
+            .map_or(0, |round_data| round_data.tickets_sold),
+        prize_pool: round_data
+            .as_ref()
+            .map_or(Ok(pool_storage.jackpot), |round_data| {
+                round_data.prize_pool(&pool_storage)
+            })?,
+        jackpot: pool_storage.jackpot,
+        house_balance: pool_storage.house_balance,
+        liabilities: pool_storage.liabilities,
+        total_principal: pool_storage.total_principal,
+        prize_reserve: pool_storage.prize_reserve,
+    };
+
+    set_return_data(&to_vec(&pool_info)?);
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use borsh::to_vec;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::set_return_data,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::LotteryError,
+    state::{ProgramAccount, RoundData, RoundResult, RoundStatus},
+};
+
+use super::{find_round_pda, validation::PoolVault};
+
+/// Returns the result of a round of the pool, the round can be any past or current round.
+pub fn process_get_round_result(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The round queried
+    let round_account = next_account_info(&mut accounts)?;
+
+    let round_data = load_round(program_id, pool_vault_account, round_account)?;
+
+    let round_result = RoundResult {
+        round_id: round_data.round_id,
+        status: round_data.status,
+        tickets_sold: round_data.tickets_sold,
+        lamports_raised: round_data.lamports_raised,
+        winning_ticket: (round_data.status == RoundStatus::Settled && round_data.tickets_sold > 0)
+            .then_some(round_data.winning_ticket),
+        prize: round_data.prize,
+        prize_claimed: round_data.prize_claimed,
+        prize_swept: round_data.prize_swept,
+        claim_deadline_slot: round_data.claim_deadline_slot,
+    };
+
+    set_return_data(&to_vec(&round_result)?);
+
+    Ok(())
+}
+
+/// Loads a round of the pool, verifying the account is the round PDA of its round id.
+pub(crate) fn load_round(
+    program_id: &Pubkey,
+    pool_vault_account: &AccountInfo,
+    round_account: &AccountInfo,
+) -> Result<RoundData, ProgramError> {
+    PoolVault::new(program_id, pool_vault_account)?;
+
+    if round_account.owner != program_id {
+        return Err(LotteryError::IncorrectOwner.into());
+    }
+
+    let round_data = RoundData::load(&round_account.data.borrow())?;
+
+    if find_round_pda(program_id, pool_vault_account.key, round_data.round_id).0
+        != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    Ok(round_data)
+}
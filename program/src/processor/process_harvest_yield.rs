@@ -0,0 +1,74 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{error::LotteryError, events::LotteryEvent, state::PoolMode};
+
+use super::validation::PoolVault;
+
+/// Harvests the yield of a savings pool into its prize reserve.
+/// The yield program is invoked with the accounts `[yield_source, pool_vault]` and no data,
+/// whatever lands in the vault during the call is added to the prize reserve.
+/// Anyone can harvest, the instruction can only add lamports to the vault.
+pub fn process_harvest_yield(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The account the yield is harvested from
+    let yield_source_account = next_account_info(&mut accounts)?;
+    // The program paying the yield
+    let yield_program_account = next_account_info(&mut accounts)?;
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Savings {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    if pool_storage.yield_source != *yield_source_account.key
+        || pool_storage.yield_program != *yield_program_account.key
+    {
+        return Err(LotteryError::InvalidYieldSource.into());
+    }
+
+    let balance_before = pool_vault_account.lamports();
+
+    invoke(
+        &Instruction::new_with_bytes(
+            *yield_program_account.key,
+            &[],
+            vec![
+                AccountMeta::new(*yield_source_account.key, false),
+                AccountMeta::new(*pool_vault_account.key, false),
+            ],
+        ),
+        &[
+            yield_source_account.clone(),
+            pool_vault_account.clone(),
+            yield_program_account.clone(),
+        ],
+    )?;
+
+    let harvested = pool_vault_account.lamports().saturating_sub(balance_before);
+
+    pool_storage.prize_reserve = pool_storage
+        .prize_reserve
+        .checked_add(harvested)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool_vault.store(&pool_storage)?;
+
+    LotteryEvent::YieldHarvested {
+        pool_vault: *pool_vault_account.key,
+        amount: harvested,
+    }
+    .emit();
+
+    Ok(())
+}
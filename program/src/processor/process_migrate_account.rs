@@ -0,0 +1,114 @@
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        AccountHeader, DrawSessionData, FixedSize, LegacyPoolStorageDataV0,
+        LegacyTicketAccountDataV0, PoolStorageData, ProgramAccount, ReferrerData, RoundData,
+        RoundTicketsData, SavingsAccountData, SubscriptionData, TicketAccountData,
+        TransferRoundData,
+    },
+};
+
+/// Upgrades a program-owned account to the current layout in place, the payer funding the rent of the
+/// added space. The accounts of the first release, created before the account header, are told apart by
+/// their length and rewritten in the current layout. Migrating an account already up to date does nothing.
+pub fn process_migrate_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Anyone can migrate an account, paying the rent of the added space
+    let payer_account = next_account_info(&mut accounts)?;
+    // The program-owned account to migrate
+    let account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+
+    if !payer_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if account.owner != program_id {
+        return Err(LotteryError::IncorrectOwner.into());
+    }
+
+    if let Ok(header) = AccountHeader::deserialize(&mut &**account.data.borrow()) {
+        if current_headers().contains(&header) {
+            return Ok(());
+        }
+    }
+
+    let legacy_size = account.data_len();
+    let (header, data) = migrate_legacy_data(&account.data.borrow())?;
+    let size = data.len();
+
+    let rent = Rent::get()?;
+    let missing_rent = rent
+        .minimum_balance(size)
+        .saturating_sub(rent.minimum_balance(legacy_size));
+
+    invoke(
+        &system_instruction::transfer(payer_account.key, account.key, missing_rent),
+        &[
+            payer_account.clone(),
+            account.clone(),
+            system_account.clone(),
+        ],
+    )?;
+
+    account.realloc(size, false)?;
+    account.data.borrow_mut().copy_from_slice(&data);
+
+    LotteryEvent::AccountMigrated {
+        account: *account.key,
+        discriminator: header.discriminator,
+        version: header.version,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn current_headers() -> [AccountHeader; 9] {
+    [
+        PoolStorageData::header(),
+        TicketAccountData::header(),
+        SavingsAccountData::header(),
+        RoundData::header(),
+        RoundTicketsData::header(),
+        ReferrerData::header(),
+        SubscriptionData::header(),
+        DrawSessionData::header(),
+        TransferRoundData::header(),
+    ]
+}
+
+// The data of a headerless account of the first release in the current layout, along with its header.
+fn migrate_legacy_data(legacy_data: &[u8]) -> Result<(AccountHeader, Vec<u8>), ProgramError> {
+    match legacy_data.len() {
+        LegacyPoolStorageDataV0::PACKED_SIZE => account_data(&PoolStorageData::from(
+            LegacyPoolStorageDataV0::try_from_slice(legacy_data)?,
+        )),
+        LegacyTicketAccountDataV0::PACKED_SIZE => account_data(&TicketAccountData::from(
+            LegacyTicketAccountDataV0::try_from_slice(legacy_data)?,
+        )),
+        _ => Err(LotteryError::InvalidAccountType.into()),
+    }
+}
+
+fn account_data<T: ProgramAccount>(account: &T) -> Result<(AccountHeader, Vec<u8>), ProgramError> {
+    let mut data = vec![0; T::SIZE];
+
+    account.store(&mut data)?;
+
+    Ok((T::header(), data))
+}
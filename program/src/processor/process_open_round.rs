@@ -0,0 +1,167 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        PoolMode, PoolStorageData, PoolStorageSeed, ProgramAccount, RoundData, RoundSchedule,
+        RoundStatus, ROUND_DATA_SIZE, ROUND_METADATA_KEY,
+    },
+};
+use spl_token_2022::extension::ExtensionType;
+use spl_token_metadata_interface::state::Field;
+
+use super::pda::create_pda_account;
+use super::validation::{PoolVault, ReceiptMint};
+
+/// Opens the current round of a lottery pool, the round id is the pool draft count
+/// so a new round can only be opened once the previous one has been drawn.
+pub fn process_open_round(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    schedule: RoundSchedule,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+
+    let authority_account = next_account_info(&mut accounts)?;
+
+    let pool_vault_account = next_account_info(&mut accounts)?;
+
+    let round_account = next_account_info(&mut accounts)?;
+
+    let system_program_account = next_account_info(&mut accounts)?;
+    // The receipt mint, its metadata follows the current round when it has any
+    let pool_mint_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?;
+
+    let pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, authority_account.key)?.writable()?;
+
+    if schedule.sales_open_slot >= schedule.sales_close_slot {
+        return Err(LotteryError::InvalidSalesWindow.into());
+    }
+
+    let round_id = pool_storage.draft_count;
+    let (round_address, bump) = find_round_pda(program_id, pool_vault_account.key, round_id);
+
+    if round_address != *round_account.key || !round_account.data_is_empty() {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    let rent = Rent::get()?;
+
+    create_pda_account(
+        authority_account,
+        round_account,
+        rent.minimum_balance(ROUND_DATA_SIZE as usize),
+        ROUND_DATA_SIZE as u64,
+        program_id,
+        system_program_account,
+        &[
+            PoolStorageSeed::Round.as_bytes(),
+            pool_vault_account.key.as_ref(),
+            &round_id.to_le_bytes(),
+            &[bump],
+        ],
+    )?;
+
+    let round_data = RoundData {
+        round_id,
+        sales_open_slot: schedule.sales_open_slot,
+        sales_close_slot: schedule.sales_close_slot,
+        status: RoundStatus::Open,
+        ticket_price: pool_storage.ticket_price,
+        tickets_sold: 0,
+        lamports_raised: 0,
+        winning_ticket: 0,
+        prize: 0,
+        prize_claimed: false,
+        min_tickets_sold: schedule.min_tickets_sold,
+        min_lamports_raised: schedule.min_lamports_raised,
+        claim_deadline_slot: 0,
+        prize_swept: false,
+        referral_fees: 0,
+        voucher_lamports: 0,
+    };
+
+    round_data.store(&mut round_account.data.borrow_mut())?;
+
+    // The mints of the first release carry no metadata
+    if receipt_mint.has_extension(ExtensionType::TokenMetadata)? {
+        invoke_signed(
+            &spl_token_metadata_interface::instruction::update_field(
+                &spl_token_2022::ID,
+                pool_mint_account.key,
+                pool_mint_account.key,
+                Field::Key(ROUND_METADATA_KEY.to_string()),
+                round_id.to_string(),
+            ),
+            &[pool_mint_account.clone(), spl_2022_account.clone()],
+            &[&receipt_mint.signer_seeds()],
+        )?;
+    }
+
+    LotteryEvent::RoundOpened {
+        pool_vault: *pool_vault_account.key,
+        round_id,
+        sales_open_slot: schedule.sales_open_slot,
+        sales_close_slot: schedule.sales_close_slot,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn find_round_pda(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    round_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::Round.as_bytes(),
+            pool_vault_account.as_ref(),
+            &round_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Loads the current round of the pool, verifying the account is the round PDA of its draft count.
+pub(crate) fn load_current_round(
+    program_id: &Pubkey,
+    pool_vault_account: &AccountInfo,
+    round_account: &AccountInfo,
+    pool_storage: &PoolStorageData,
+) -> Result<RoundData, ProgramError> {
+    let (round_address, _) =
+        find_round_pda(program_id, pool_vault_account.key, pool_storage.draft_count);
+
+    if round_account.owner != program_id || round_address != *round_account.key {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    RoundData::load(&round_account.data.borrow())
+}
@@ -4,8 +4,12 @@ use solana_program::{
     pubkey::Pubkey,
 };
 
+use crate::events::LotteryEvent;
+
+use super::validation::PlayerTicketAccount;
+
 pub fn process_player_withdraw(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     amount: u64,
 ) -> ProgramResult {
@@ -19,6 +23,8 @@ pub fn process_player_withdraw(
         return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
     }
 
+    PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?.writable()?;
+
     if amount > **player_pda_account.try_borrow_lamports()? {
         return Err(solana_program::program_error::ProgramError::InsufficientFunds);
     }
@@ -27,5 +33,11 @@ pub fn process_player_withdraw(
 
     **player_account.try_borrow_mut_lamports()? += amount;
 
+    LotteryEvent::Withdraw {
+        player: *player_account.key,
+        amount,
+    }
+    .emit();
+
     Ok(())
 }
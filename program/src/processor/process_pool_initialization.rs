@@ -1,26 +1,36 @@
 use crate::{
     error::LotteryError,
-    state::{PoolStorageData, PoolStorageSeed, TicketAccountData, POOL_STORAGE_SIZE},
+    events::LotteryEvent,
+    state::{
+        PoolMode, PoolStorageData, PoolStorageSeed, ProgramAccount, TicketAccountData,
+        BASIS_POINTS, POOL_STORAGE_SIZE, ROUND_METADATA_KEY,
+    },
 };
-use borsh::{to_vec, BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
     program::invoke_signed,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction,
     sysvar::Sysvar,
 };
 
-use solana_program::program_pack::Pack;
+use spl_token_2022::{
+    extension::{metadata_pointer, transfer_hook, ExtensionType},
+    state::Mint,
+};
+use spl_token_metadata_interface::state::{Field, TokenMetadata};
+
+use super::pda::create_pda_account;
+use super::process_transfer_hook::initialize_extra_account_metas;
 
 pub fn process_pool_initialization(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     pool_storage_data: &PoolStorageData,
 ) -> ProgramResult {
-    let mut accounts = accounts.into_iter();
+    let mut accounts = accounts.iter();
 
     // The stake pool authority, is the authority which can verify tickets if they are valid and proceeding to airdrop prises.
     let pool_authority_account = next_account_info(&mut accounts)?;
@@ -39,6 +49,22 @@ pub fn process_pool_initialization(
     // The system account
     let system_program_account = next_account_info(&mut accounts)?;
 
+    if pool_storage_data.transferable_tickets && pool_storage_data.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    if pool_storage_data.house_fee_bps as u64 > BASIS_POINTS
+        || pool_storage_data.crank_tip_bps as u64 > BASIS_POINTS
+    {
+        return Err(LotteryError::InvalidFeeRate.into());
+    }
+
+    if pool_storage_data.crank_tip_bps as u64 + pool_storage_data.referral_share_bps as u64
+        > BASIS_POINTS
+    {
+        return Err(LotteryError::InvalidReferralShare.into());
+    }
+
     initialize_pool_vault(
         program_id,
         pool_authority_account,
@@ -54,7 +80,31 @@ pub fn process_pool_initialization(
         rent_account,
         system_program_account,
         spl_token_2022_account,
+        pool_storage_data,
     )?;
+
+    if pool_storage_data.transferable_tickets {
+        // The transfer hook validation account of the receipt mint
+        let extra_account_metas_account = next_account_info(&mut accounts)?;
+
+        initialize_extra_account_metas(
+            program_id,
+            pool_authority_account,
+            pool_vault_account,
+            mint_account,
+            extra_account_metas_account,
+            system_program_account,
+        )?;
+    }
+
+    LotteryEvent::PoolInitialized {
+        pool_vault: *pool_vault_account.key,
+        authority: *pool_authority_account.key,
+        mode: pool_storage_data.mode,
+        ticket_price: pool_storage_data.ticket_price,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -66,7 +116,7 @@ fn initialize_pool_vault<'a>(
     pool_storage_data: &PoolStorageData,
 ) -> ProgramResult {
     let (pool_vault_address, bump) =
-        find_stake_pool_vault_pda(program_id, &pool_authority_account.key);
+        find_stake_pool_vault_pda(program_id, pool_authority_account.key);
 
     if pool_vault_account.key != &pool_vault_address {
         return Err(LotteryError::InvalidStakePoolVault.into());
@@ -80,31 +130,37 @@ fn initialize_pool_vault<'a>(
         return Err(LotteryError::InsufficientFunds.into());
     }
 
-    let pool_vault_account_instr = system_instruction::create_account(
-        &pool_authority_account.key,
-        &pool_vault_address,
+    create_pda_account(
+        pool_authority_account,
+        pool_vault_account,
         exempt_balance + pool_storage_data.initial_amount,
         POOL_STORAGE_SIZE as u64,
-        &program_id,
-    );
-
-    invoke_signed(
-        &pool_vault_account_instr,
+        program_id,
+        system_program_account,
         &[
-            pool_authority_account.clone(),
-            pool_vault_account.clone(),
-            system_program_account.clone(),
-        ],
-        &[&[
             PoolStorageSeed::StakePool.as_bytes(),
-            &pool_authority_account.key.as_ref(),
+            pool_authority_account.key.as_ref(),
             &[bump],
-        ]],
+        ],
     )?;
 
-    pool_vault_account
-        .try_borrow_mut_data()?
-        .copy_from_slice(&to_vec(pool_storage_data)?);
+    // The accounting fields always start from scratch, whatever the payload contains.
+    let slot = Clock::get()?.slot;
+    let pool_storage_data = PoolStorageData {
+        total_principal: 0,
+        prize_reserve: 0,
+        total_weight: 0,
+        weight_updated_slot: slot,
+        period_start_slot: slot,
+        liabilities: 0,
+        jackpot: 0,
+        house_balance: 0,
+        last_round_slot: slot,
+        savings_draw_slot: 0,
+        ..pool_storage_data.clone()
+    };
+
+    pool_storage_data.store(&mut pool_vault_account.try_borrow_mut_data()?)?;
 
     Ok(())
 }
@@ -116,9 +172,10 @@ fn initialize_pool_mint<'a>(
     rent_account: &AccountInfo<'a>,
     system_program_account: &AccountInfo<'a>,
     spl_token_2022_account: &AccountInfo<'a>,
+    pool_storage_data: &PoolStorageData,
 ) -> ProgramResult {
     let (pool_mint_address, bump) =
-        find_stake_pool_mint_pda(program_id, &pool_authority_account.key);
+        find_stake_pool_mint_pda(program_id, pool_authority_account.key);
 
     if &pool_mint_address != mint_account.key {
         return Err(LotteryError::InvalidStakePoolVault.into());
@@ -126,39 +183,112 @@ fn initialize_pool_mint<'a>(
 
     let rent = Rent::get()?;
 
-    let exempt_balance = rent.minimum_balance(spl_token_2022::state::Mint::LEN);
+    // The receipts either can't be transferred, or are transferred through the program transfer hook
+    // and burned by the mint as their permanent delegate. The mint can be closed by itself once the
+    // pool is wound down and it holds its own metadata.
+    let transfer_extensions = if pool_storage_data.transferable_tickets {
+        vec![
+            ExtensionType::TransferHook,
+            ExtensionType::PermanentDelegate,
+        ]
+    } else {
+        vec![ExtensionType::NonTransferable]
+    };
+
+    let mint_size = ExtensionType::try_calculate_account_len::<Mint>(
+        &[
+            transfer_extensions.as_slice(),
+            &[
+                ExtensionType::MintCloseAuthority,
+                ExtensionType::MetadataPointer,
+            ],
+        ]
+        .concat(),
+    )?;
+
+    // The metadata is reallocated by the token program, the rent is funded upfront for
+    // the widest round number so updating it never requires more lamports.
+    let metadata = TokenMetadata {
+        name: pool_storage_data.receipt_name(),
+        symbol: pool_storage_data.receipt_symbol(),
+        additional_metadata: vec![(ROUND_METADATA_KEY.to_string(), u64::MAX.to_string())],
+        ..Default::default()
+    };
+
+    let exempt_balance = rent.minimum_balance(mint_size + metadata.tlv_size_of()?);
 
     if pool_authority_account.lamports() < exempt_balance {
         return Err(LotteryError::InsufficientFunds.into());
     }
 
-    let mint_account_instr: solana_program::instruction::Instruction =
-        system_instruction::create_account(
-            &pool_authority_account.key,
-            &mint_account.key,
-            exempt_balance,
-            spl_token_2022::state::Mint::LEN as u64,
-            &spl_token_2022::ID,
-        );
+    let mint_seeds: &[&[u8]] = &[
+        PoolStorageSeed::ReceiptMint.as_bytes(),
+        pool_authority_account.key.as_ref(),
+        &[bump],
+    ];
 
-    invoke_signed(
-        &mint_account_instr,
-        &[
-            pool_authority_account.clone(),
-            mint_account.clone(),
-            system_program_account.clone(),
-        ],
-        &[&[
-            PoolStorageSeed::ReceiptMint.as_bytes(),
-            &pool_authority_account.key.as_ref(),
-            &[bump],
-        ]],
+    create_pda_account(
+        pool_authority_account,
+        mint_account,
+        exempt_balance,
+        mint_size as u64,
+        &spl_token_2022::ID,
+        system_program_account,
+        mint_seeds,
     )?;
 
+    let transfer_instructions = if pool_storage_data.transferable_tickets {
+        vec![
+            transfer_hook::instruction::initialize(
+                &spl_token_2022::ID,
+                mint_account.key,
+                Some(*mint_account.key),
+                Some(*program_id),
+            )?,
+            spl_token_2022::instruction::initialize_permanent_delegate(
+                &spl_token_2022::ID,
+                mint_account.key,
+                mint_account.key,
+            )?,
+        ]
+    } else {
+        vec![
+            spl_token_2022::instruction::initialize_non_transferable_mint(
+                &spl_token_2022::ID,
+                mint_account.key,
+            )?,
+        ]
+    };
+
+    let extension_instructions = [
+        spl_token_2022::instruction::initialize_mint_close_authority(
+            &spl_token_2022::ID,
+            mint_account.key,
+            Some(mint_account.key),
+        )?,
+        metadata_pointer::instruction::initialize(
+            &spl_token_2022::ID,
+            mint_account.key,
+            Some(*mint_account.key),
+            Some(*mint_account.key),
+        )?,
+    ];
+
+    for instruction in transfer_instructions
+        .iter()
+        .chain(extension_instructions.iter())
+    {
+        invoke_signed(
+            instruction,
+            &[mint_account.clone(), spl_token_2022_account.clone()],
+            &[mint_seeds],
+        )?;
+    }
+
     let token_init_instruction = spl_token_2022::instruction::initialize_mint(
         &spl_token_2022::ID,
-        &mint_account.key,
-        &mint_account.key,
+        mint_account.key,
+        mint_account.key,
         None,
         0,
     )?;
@@ -171,13 +301,38 @@ fn initialize_pool_mint<'a>(
             spl_token_2022_account.clone(),
             rent_account.clone(),
         ],
-        &[&[
-            PoolStorageSeed::ReceiptMint.as_bytes(),
-            &pool_authority_account.key.as_ref(),
-            &[bump],
-        ]],
+        &[mint_seeds],
     )?;
 
+    // The mint is its own mint and metadata update authority
+    let metadata_instructions = [
+        spl_token_metadata_interface::instruction::initialize(
+            &spl_token_2022::ID,
+            mint_account.key,
+            mint_account.key,
+            mint_account.key,
+            mint_account.key,
+            metadata.name,
+            metadata.symbol,
+            metadata.uri,
+        ),
+        spl_token_metadata_interface::instruction::update_field(
+            &spl_token_2022::ID,
+            mint_account.key,
+            mint_account.key,
+            Field::Key(ROUND_METADATA_KEY.to_string()),
+            pool_storage_data.draft_count.to_string(),
+        ),
+    ];
+
+    for instruction in metadata_instructions.iter() {
+        invoke_signed(
+            instruction,
+            &[mint_account.clone(), spl_token_2022_account.clone()],
+            &[mint_seeds],
+        )?;
+    }
+
     Ok(())
 }
 
@@ -200,12 +355,13 @@ pub fn update_player_account<'a>(
 ) -> ProgramResult {
     let mut player_account_data = player_pda_account.try_borrow_mut_data()?;
 
-    let mut ticket_data = TicketAccountData::try_from_slice(&player_account_data)?;
+    // The player limits may follow the ticket data
+    let mut ticket_data = TicketAccountData::load(&player_account_data)?;
 
     ticket_data.merkle_root = data.merkle_root;
     ticket_data.total_tickets = data.total_tickets;
 
-    ticket_data.serialize(&mut &mut player_account_data[..])?;
+    ticket_data.store(&mut player_account_data)?;
 
     Ok(())
 }
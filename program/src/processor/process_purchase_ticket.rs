@@ -1,40 +1,117 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshDeserialize;
+use rs_merkle::{algorithms::Sha256, Hasher, MerkleProof};
 
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
+    instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
     program::invoke,
     program::invoke_signed,
+    program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
     system_instruction,
-    sysvar::Sysvar,
+    sysvar::{instructions::load_instruction_at_checked, Sysvar},
 };
 
-use solana_program::program_pack::Pack;
+use spl_token_2022::{
+    extension::{
+        transfer_hook::TransferHook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    state::{Account, Mint},
+};
 
 use crate::{
     error::LotteryError,
-    state::{PoolStorageData, PoolStorageSeed, TicketAccountData, TICKET_ACCOUNT_DATA_SIZE},
+    events::LotteryEvent,
+    state::{
+        AllowlistProof, Instruction, PoolMode, PoolStorageSeed, ProgramAccount, RoundData,
+        RoundTicketsData, TicketAccountData, TicketRange, ROUND_TICKETS_DATA_SIZE,
+        TICKET_ACCOUNT_DATA_SIZE,
+    },
 };
 
-use super::{find_player_pda_account, find_stake_pool_mint_pda, update_player_account};
+use super::{
+    find_player_pda_account,
+    pda::create_pda_account,
+    process_open_round::load_current_round,
+    process_register_attestation::enforce_attestation,
+    process_set_player_limits::enforce_player_limits,
+    process_withdraw_referral_rewards::record_referral,
+    update_player_account,
+    validation::{PlayerTicketAccount, PlayerTokenAccount, PoolVault, ReceiptMint},
+};
 
 /// Process the player initialization
 /// This function will create a new account for the player and transfer the ticket price to the stake pool vault.
 /// The player account will be initialized with the ticket data.
 /// The player account will be owned by the program.
+/// The tickets go to the beneficiary when one follows the instructions sysvar, the payer only funds the purchase.
 pub fn process_ticket_purchase(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     account_data: TicketAccountData,
 ) -> ProgramResult {
-    let mut accounts = accounts.into_iter();
+    purchase_tickets(
+        program_id,
+        accounts,
+        1,
+        Some(account_data),
+        TicketFunding::Payer,
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// Process the purchase of a ticket from an allowlisted pool, the player proves its wallet is on the allowlist.
+pub fn process_allowlisted_ticket_purchase(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account_data: TicketAccountData,
+    allowlist_proof: AllowlistProof,
+) -> ProgramResult {
+    purchase_tickets(
+        program_id,
+        accounts,
+        1,
+        Some(account_data),
+        TicketFunding::Payer,
+        Some(&allowlist_proof),
+    )?;
+
+    Ok(())
+}
+
+// Who pays for the tickets sold by `purchase_tickets`
+#[derive(PartialEq)]
+pub(crate) enum TicketFunding {
+    // The payer transfers the ticket price to the vault
+    Payer,
+    // The ticket price is taken from the house balance already held by the vault
+    HouseBalance,
+    // The caller moves the ticket price to the vault out of the escrow it holds for the player
+    Escrow,
+}
+
+/// Sells `ticket_count` tickets of the current round to the player, returning the round they were sold from.
+/// The ticket data of the player is left untouched when none is given.
+/// The tickets of an allowlisted pool are only sold along with the allowlist proof of the player.
+pub(crate) fn purchase_tickets(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    ticket_count: u64,
+    account_data: Option<TicketAccountData>,
+    funding: TicketFunding,
+    allowlist_proof: Option<&AllowlistProof>,
+) -> Result<RoundData, ProgramError> {
+    let mut accounts = accounts.iter();
     // Pool authority
     let pool_authority_account = next_account_info(&mut accounts)?;
     // Account payer
-    let player_account = next_account_info(&mut accounts)?;
-    // Account PDA for payer
+    let payer_account = next_account_info(&mut accounts)?;
+    // Account PDA for the player
     let player_pda_account = next_account_info(&mut accounts)?;
     // The player token account
     let player_token_pda_account = next_account_info(&mut accounts)?;
@@ -50,33 +127,171 @@ pub fn process_ticket_purchase(
 
     let system_account = next_account_info(&mut accounts)?;
 
-    if !player_account.is_signer {
+    // The current round of the pool
+    let round_account = next_account_info(&mut accounts)?;
+
+    // The tickets of the player in the current round
+    let round_tickets_account = next_account_info(&mut accounts)?;
+
+    // The instructions sysvar, used to enforce the per transaction ticket cap
+    let instructions_sysvar_account = next_account_info(&mut accounts)?;
+
+    // The player receiving the tickets, the payer itself when omitted
+    let player_account = next_account_info(&mut accounts).unwrap_or(payer_account);
+
+    // The referrer of the purchase and its PDA, when referred
+    let referrer_accounts = next_account_info(&mut accounts)
+        .ok()
+        .zip(next_account_info(&mut accounts).ok());
+
+    if !payer_account.is_signer {
         return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
     }
 
-    let pool_storage =
-        PoolStorageData::deserialize(&mut &**pool_vault_account.try_borrow_mut_data()?)?;
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?
+            .writable()?;
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, pool_authority_account.key)?.writable()?;
+    let player_pda =
+        PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?.writable()?;
+    let player_token_pda =
+        PlayerTokenAccount::new(program_id, player_token_pda_account, player_account.key)?
+            .writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
 
-    let PoolStorageData { ticket_price, .. } = pool_storage;
+    if pool_storage.is_allowlisted()
+        && !allowlist_proof.is_some_and(|proof| {
+            is_allowlisted(&pool_storage.allowlist_root, player_account.key, proof)
+        })
+    {
+        return Err(LotteryError::NotAllowlisted.into());
+    }
+
+    let mut round_data =
+        load_current_round(program_id, pool_vault_account, round_account, &pool_storage)?;
+
+    if !round_data.is_selling(Clock::get()?.slot) {
+        return Err(LotteryError::SalesClosed.into());
+    }
+
+    if pool_storage.max_tickets_per_round > 0
+        && round_data
+            .tickets_sold
+            .checked_add(ticket_count)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            > pool_storage.max_tickets_per_round
+    {
+        return Err(LotteryError::RoundTicketCapExceeded.into());
+    }
+
+    // The purchases made through CPI don't show in the instructions sysvar and can't be counted against
+    // the cap of the transaction, so the pools enforcing one only sell tickets to top level instructions
+    let transaction_tickets = if get_stack_height() > TRANSACTION_LEVEL_STACK_HEIGHT {
+        if pool_storage.max_tickets_per_transaction > 0 {
+            return Err(LotteryError::CpiPurchaseNotAllowed.into());
+        }
+
+        ticket_count
+    } else {
+        purchases_in_transaction(program_id, instructions_sysvar_account)?
+    };
 
-    if player_pda_account.data_is_empty() {
+    if pool_storage.max_tickets_per_transaction > 0
+        && transaction_tickets > pool_storage.max_tickets_per_transaction
+    {
+        return Err(LotteryError::TransactionTicketCapExceeded.into());
+    }
+
+    let ticket_price = round_data.ticket_price;
+    let purchase_price = ticket_price
+        .checked_mul(ticket_count)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let ticket_range = round_data.sell_tickets(ticket_count)?;
+    let first_ticket = ticket_range.first_ticket;
+
+    enforce_attestation(program_id, &pool_storage, player_pda_account)?;
+
+    // Tickets paid by the house don't count against the spending limits of the player
+    let player_spending = match funding {
+        TicketFunding::Payer | TicketFunding::Escrow => purchase_price,
+        TicketFunding::HouseBalance => 0,
+    };
+
+    enforce_player_limits(program_id, player_pda_account, player_spending)?;
+
+    record_round_tickets(
+        program_id,
+        payer_account,
+        player_account,
+        round_account,
+        round_tickets_account,
+        system_account,
+        &round_data,
+        ticket_range,
+        funding == TicketFunding::HouseBalance,
+        pool_storage.max_tickets_per_player,
+    )?;
+
+    // The house keeps track of the tickets it paid to get them back if the round is cancelled
+    if funding == TicketFunding::HouseBalance {
+        round_data.voucher_lamports = round_data
+            .voucher_lamports
+            .checked_add(purchase_price)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+
+    // Only the tickets paid by the payer earn referral rewards
+    if let (Some((referrer_account, referrer_pda_account)), TicketFunding::Payer) =
+        (referrer_accounts, &funding)
+    {
+        record_referral(
+            program_id,
+            payer_account,
+            player_account,
+            referrer_account,
+            referrer_pda_account,
+            pool_vault_account,
+            system_account,
+            &pool_storage,
+            &mut round_data,
+            ticket_count,
+            purchase_price,
+        )?;
+    }
+
+    round_data.store(&mut round_account.data.borrow_mut())?;
+
+    if !player_pda.is_initialized() {
         initialize_player_account(
             program_id,
-            ticket_price,
+            if funding == TicketFunding::Payer {
+                ticket_price
+            } else {
+                0
+            },
+            payer_account,
             player_account,
             player_pda_account,
             pool_mint_account,
             system_account,
-            Some(account_data),
+            account_data,
         )?;
-    } else {
+    } else if let Some(account_data) = account_data.filter(|_| player_account.is_signer) {
+        // The ticket data of an existing player is only replaced by the player itself
         update_player_account(player_pda_account, account_data)?;
     }
 
-    if player_token_pda_account.data_is_empty() {
+    if !player_token_pda.is_initialized() {
         initialize_player_token_account(
             program_id,
             pool_authority_account,
+            payer_account,
             player_account,
             player_token_pda_account,
             pool_mint_account,
@@ -84,29 +299,43 @@ pub fn process_ticket_purchase(
         )?;
     }
 
-    let ticket_purchase_instr =
-        system_instruction::transfer(player_account.key, pool_vault_account.key, ticket_price);
+    match funding {
+        TicketFunding::Payer => {
+            let ticket_purchase_instr = system_instruction::transfer(
+                payer_account.key,
+                pool_vault_account.key,
+                purchase_price,
+            );
+
+            invoke(
+                &ticket_purchase_instr,
+                &[
+                    payer_account.clone(),
+                    pool_vault_account.clone(),
+                    system_account.clone(),
+                ],
+            )?;
+        }
+        TicketFunding::HouseBalance => {
+            pool_storage.house_balance = pool_storage
+                .house_balance
+                .checked_sub(purchase_price)
+                .ok_or(LotteryError::InsufficientFunds)?;
+        }
+        TicketFunding::Escrow => {}
+    }
 
-    invoke(
-        &ticket_purchase_instr,
-        &[
-            player_account.clone(),
-            pool_vault_account.clone(),
-            system_account.clone(),
-        ],
-    )?;
+    pool_vault.store(&pool_storage)?;
 
     let ticket_purchase_receipt = spl_token_2022::instruction::mint_to(
         &spl_token_2022::id(),
         pool_mint_account.key,
         player_token_pda_account.key,
-        &pool_mint_account.key,
-        &[&pool_mint_account.key],
-        1,
+        pool_mint_account.key,
+        &[pool_mint_account.key],
+        ticket_count,
     )?;
 
-    let (.., bump) = find_stake_pool_mint_pda(program_id, &pool_authority_account.key);
-
     invoke_signed(
         &ticket_purchase_receipt,
         &[
@@ -116,14 +345,21 @@ pub fn process_ticket_purchase(
             pool_authority_account.clone(),
             spl_2022_account.clone(),
         ],
-        &[&[
-            PoolStorageSeed::ReceiptMint.as_bytes(),
-            &pool_authority_account.key.as_ref(),
-            &[bump],
-        ]],
+        &[&receipt_mint.signer_seeds()],
     )?;
 
-    Ok(())
+    LotteryEvent::TicketsPurchased {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        player: *player_account.key,
+        payer: *payer_account.key,
+        first_ticket,
+        tickets: ticket_count,
+        price: purchase_price,
+    }
+    .emit();
+
+    Ok(round_data)
 }
 
 pub fn find_player_token_pda_account(
@@ -149,11 +385,176 @@ pub fn find_player_token_pda_account(
     (key, bump, player_account_seeds)
 }
 
+pub fn find_round_tickets_pda(
+    program_id: &Pubkey,
+    round_account: &Pubkey,
+    player_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::RoundTickets.as_bytes(),
+            round_account.as_ref(),
+            player_account.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+// Adds the purchased ticket range to the round tickets of the player, counting the tickets paid by the house
+// apart, the account is created on the first purchase of the round.
+#[allow(clippy::too_many_arguments)]
+fn record_round_tickets<'a>(
+    program_id: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    player_account: &AccountInfo<'a>,
+    round_account: &AccountInfo<'a>,
+    round_tickets_account: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+    round_data: &RoundData,
+    ticket_range: TicketRange,
+    house_funded: bool,
+    max_tickets_per_player: u64,
+) -> ProgramResult {
+    initialize_round_tickets_account(
+        program_id,
+        payer_account,
+        player_account,
+        round_account,
+        round_tickets_account,
+        system_program_account,
+        round_data.round_id,
+    )?;
+
+    let mut round_tickets = RoundTicketsData::load(&round_tickets_account.data.borrow())?;
+
+    if max_tickets_per_player > 0
+        && round_tickets
+            .tickets
+            .checked_add(ticket_range.count)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            > max_tickets_per_player
+    {
+        return Err(LotteryError::PlayerTicketCapExceeded.into());
+    }
+
+    if house_funded {
+        round_tickets.voucher_tickets = round_tickets
+            .voucher_tickets
+            .checked_add(ticket_range.count)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+
+    round_tickets.add_range(ticket_range)?;
+    round_tickets.store(&mut round_tickets_account.data.borrow_mut())?;
+
+    Ok(())
+}
+
+// Creates the tickets account of the player for the round, unless it already exists.
+pub(crate) fn initialize_round_tickets_account<'a>(
+    program_id: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    player_account: &AccountInfo<'a>,
+    round_account: &AccountInfo<'a>,
+    round_tickets_account: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+    round_id: u64,
+) -> ProgramResult {
+    let (round_tickets_address, bump) =
+        find_round_tickets_pda(program_id, round_account.key, player_account.key);
+
+    if round_tickets_address != *round_tickets_account.key {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    if round_tickets_account.data_is_empty() {
+        let rent = Rent::get()?;
+
+        create_pda_account(
+            payer_account,
+            round_tickets_account,
+            rent.minimum_balance(ROUND_TICKETS_DATA_SIZE as usize),
+            ROUND_TICKETS_DATA_SIZE as u64,
+            program_id,
+            system_program_account,
+            &[
+                PoolStorageSeed::RoundTickets.as_bytes(),
+                round_account.key.as_ref(),
+                player_account.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let round_tickets = RoundTicketsData {
+            owner: *player_account.key,
+            round_id,
+            tickets: 0,
+            refunded: false,
+            voucher_tickets: 0,
+            ranges: vec![],
+        };
+
+        round_tickets.store(&mut round_tickets_account.data.borrow_mut())?;
+    }
+
+    Ok(())
+}
+
+/// Verifies the SHA-256 hash of the wallet is a leaf of the allowlist.
+fn is_allowlisted(
+    allowlist_root: &[u8; 32],
+    wallet: &Pubkey,
+    allowlist_proof: &AllowlistProof,
+) -> bool {
+    let Ok(proof) = MerkleProof::<Sha256>::try_from(allowlist_proof.proof.clone()) else {
+        return false;
+    };
+
+    proof.verify(
+        *allowlist_root,
+        &[allowlist_proof.leaf_index as usize],
+        &[Sha256::hash(wallet.as_ref())],
+        allowlist_proof.total_leaves as usize,
+    )
+}
+
+// Counts the tickets purchased from this program among the top level instructions of the transaction.
+fn purchases_in_transaction(
+    program_id: &Pubkey,
+    instructions_sysvar_account: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    if !solana_program::sysvar::instructions::check_id(instructions_sysvar_account.key) {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let mut purchases = 0;
+    let mut index = 0;
+
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions_sysvar_account) {
+        if instruction.program_id == *program_id {
+            purchases += match Instruction::try_from_slice(&instruction.data) {
+                Ok(Instruction::PurchaseTicket(_))
+                | Ok(Instruction::PurchaseAllowlistedTicket(_, _)) => 1,
+                Ok(Instruction::SponsoredPurchase(intent)) => intent.tickets,
+                Ok(Instruction::RedeemVoucher(voucher)) => voucher.tickets,
+                Ok(Instruction::CrankSubscription { tickets_per_round }) => tickets_per_round,
+                _ => 0,
+            }
+            .min(u64::MAX - purchases);
+        }
+        index += 1;
+    }
+
+    Ok(purchases)
+}
+
 // This function is be responsible for creating a system account
 // for the player and initializing it with the ticket data.
+#[allow(clippy::too_many_arguments)]
 fn initialize_player_account<'a>(
     program_id: &Pubkey,
     ticket_price: u64,
+    payer_account: &AccountInfo<'a>,
     player_account: &AccountInfo<'a>,
     player_pda_account: &AccountInfo<'a>,
     mint_account: &AccountInfo<'a>,
@@ -162,7 +563,7 @@ fn initialize_player_account<'a>(
 ) -> ProgramResult {
     let rent = Rent::get()?;
 
-    if !rent.is_exempt(player_account.lamports(), TICKET_ACCOUNT_DATA_SIZE as usize) {
+    if !rent.is_exempt(payer_account.lamports(), TICKET_ACCOUNT_DATA_SIZE as usize) {
         return Err(solana_program::program_error::ProgramError::AccountNotRentExempt);
     }
 
@@ -173,7 +574,7 @@ fn initialize_player_account<'a>(
 
     // The PDA account for the player
     let (player_pda_account_address, bump_seed, player_account_seed) =
-        find_player_pda_account(program_id, &player_account.key);
+        find_player_pda_account(program_id, player_account.key);
 
     if &player_pda_account_address != player_pda_account.key {
         return Err(LotteryError::InvalidPlayerPdaAccount.into());
@@ -182,14 +583,6 @@ fn initialize_player_account<'a>(
     // The minimum balance required to create the account
     let minimum_balance = rent.minimum_balance(TICKET_ACCOUNT_DATA_SIZE as usize);
 
-    let instruction = system_instruction::create_account(
-        &player_account.key,
-        &player_pda_account_address,
-        minimum_balance + ticket_price,
-        TICKET_ACCOUNT_DATA_SIZE as u64,
-        program_id,
-    );
-
     let mut seed_ref = player_account_seed
         .iter()
         .map(|s| s.as_slice())
@@ -197,36 +590,37 @@ fn initialize_player_account<'a>(
     let s = [bump_seed];
     seed_ref.push(&s[..]);
 
-    invoke_signed(
-        &instruction,
-        &[
-            player_account.clone(),
-            player_pda_account.clone(),
-            system_program_account.clone(),
-        ],
-        &[&seed_ref[..]],
+    create_pda_account(
+        payer_account,
+        player_pda_account,
+        minimum_balance + ticket_price,
+        TICKET_ACCOUNT_DATA_SIZE as u64,
+        program_id,
+        system_program_account,
+        &seed_ref[..],
     )?;
 
     let mut player_account_data = player_pda_account.try_borrow_mut_data()?;
 
     if let Some(data) = initial_data {
-        data.serialize(&mut &mut player_account_data[..])?;
+        data.store(&mut player_account_data)?;
     } else {
         let ticket_data = TicketAccountData {
             merkle_root: [0; 32],
             total_tickets: 0,
         };
 
-        ticket_data.serialize(&mut &mut player_account_data[..])?;
+        ticket_data.store(&mut player_account_data)?;
     }
 
     Ok(())
 }
 
 // This function is be repsonsible for creating a token 2022 account for the player.
-fn initialize_player_token_account<'a>(
+pub(crate) fn initialize_player_token_account<'a>(
     program_id: &Pubkey,
     pool_authority_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
     player_account: &AccountInfo<'a>,
     player_token_pda_account: &AccountInfo<'a>,
     mint_account: &AccountInfo<'a>,
@@ -234,18 +628,31 @@ fn initialize_player_token_account<'a>(
 ) -> ProgramResult {
     let rent = Rent::get()?;
 
-    let exempt_balance = rent.minimum_balance(spl_token_2022::state::Account::LEN);
-    let size = spl_token_2022::state::Account::LEN as u64;
+    // The account carries the extensions required by the receipt mint and an immutable owner
+    let (mut account_extensions, transferable) = {
+        let mint_data = mint_account.data.borrow();
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+        (
+            ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?),
+            mint.get_extension::<TransferHook>().is_ok(),
+        )
+    };
+
+    // Transferable receipts are held by the player, the others by the mint so only the program moves them
+    let token_owner = if transferable {
+        player_account.key
+    } else {
+        mint_account.key
+    };
 
-    let player_account_instr = system_instruction::create_account(
-        &player_account.key,
-        &player_token_pda_account.key,
-        exempt_balance,
-        size,
-        &spl_token_2022::id(),
-    );
+    if !account_extensions.contains(&ExtensionType::ImmutableOwner) {
+        account_extensions.push(ExtensionType::ImmutableOwner);
+    }
+
+    let size = ExtensionType::try_calculate_account_len::<Account>(&account_extensions)?;
+    let exempt_balance = rent.minimum_balance(size);
 
-    let (_a, bump, signers_seeds) = find_player_token_pda_account(program_id, &player_account.key);
+    let (_a, bump, signers_seeds) = find_player_token_pda_account(program_id, player_account.key);
 
     let mut seed_ref = signers_seeds
         .iter()
@@ -254,23 +661,31 @@ fn initialize_player_token_account<'a>(
     let s = [bump];
     seed_ref.push(&s[..]);
 
-    invoke_signed(
-        &player_account_instr,
-        &[
-            player_account.clone(),
-            player_token_pda_account.clone(),
-            rent_account.clone(),
-        ],
-        &[&seed_ref[..]],
+    create_pda_account(
+        payer_account,
+        player_token_pda_account,
+        exempt_balance,
+        size as u64,
+        &spl_token_2022::id(),
+        rent_account,
+        &seed_ref[..],
+    )?;
+
+    invoke(
+        &spl_token_2022::instruction::initialize_immutable_owner(
+            &spl_token_2022::id(),
+            player_token_pda_account.key,
+        )?,
+        std::slice::from_ref(player_token_pda_account),
     )?;
 
     let (.., bump_seed, player_account_seed) =
-        find_player_pda_account(program_id, &player_account.key);
+        find_player_pda_account(program_id, player_account.key);
     let init_account_instr = spl_token_2022::instruction::initialize_account(
         &spl_token_2022::id(),
-        &player_token_pda_account.key,
-        &mint_account.key,
-        &mint_account.key,
+        player_token_pda_account.key,
+        mint_account.key,
+        token_owner,
     )?;
 
     let mut seed_ref = player_account_seed
@@ -286,6 +701,7 @@ fn initialize_player_token_account<'a>(
             player_token_pda_account.clone(),
             mint_account.clone(),
             pool_authority_account.clone(),
+            player_account.clone(),
             rent_account.clone(),
         ],
         &[&seed_ref[..]],
@@ -0,0 +1,49 @@
+use borsh::to_vec;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::set_return_data,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::state::TicketQuote;
+
+use super::{process_open_round::load_current_round, validation::PoolVault};
+
+/// Returns the price of `count` tickets of the current round.
+pub fn process_quote_ticket_price(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    count: u64,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The current round of the pool
+    let round_account = next_account_info(&mut accounts)?;
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?;
+
+    let pool_storage = pool_vault.load()?;
+
+    let round_data =
+        load_current_round(program_id, pool_vault_account, round_account, &pool_storage)?;
+
+    let ticket_quote = TicketQuote {
+        round_id: round_data.round_id,
+        ticket_price: round_data.ticket_price,
+        count,
+        total_price: round_data
+            .ticket_price
+            .checked_mul(count)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+        selling: round_data.is_selling(Clock::get()?.slot),
+    };
+
+    set_return_data(&to_vec(&ticket_quote)?);
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use borsh::to_vec;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{PoolStorageSeed, Voucher},
+};
+
+use super::{
+    pda::create_pda_account,
+    process_purchase_ticket::{purchase_tickets, TicketFunding},
+    signature::is_signed_by,
+    validation::PoolVault,
+};
+
+/// Credits the free tickets of a promo voucher signed by the pool voucher signer, paid out of the house balance.
+/// The voucher nullifier comes first, followed by the accounts of a ticket purchase, the transaction holds
+/// the ed25519 verification of the voucher signature.
+pub fn process_redeem_voucher(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    voucher: Voucher,
+) -> ProgramResult {
+    let mut accounts_iter = accounts.iter();
+    // The nullifier of the voucher id, created on redemption
+    let voucher_nullifier_account = next_account_info(&mut accounts_iter)?;
+
+    let purchase_accounts = accounts_iter.as_slice();
+
+    let _pool_authority_account = next_account_info(&mut accounts_iter)?;
+    // Pays the rent of the nullifier and of the player accounts
+    let payer_account = next_account_info(&mut accounts_iter)?;
+
+    let _player_pda_account = next_account_info(&mut accounts_iter)?;
+
+    let _player_token_pda_account = next_account_info(&mut accounts_iter)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts_iter)?;
+
+    let _pool_mint_account = next_account_info(&mut accounts_iter)?;
+
+    let _rent_account = next_account_info(&mut accounts_iter)?;
+
+    let _spl_2022_account = next_account_info(&mut accounts_iter)?;
+
+    let system_account = next_account_info(&mut accounts_iter)?;
+
+    let _round_account = next_account_info(&mut accounts_iter)?;
+
+    let _round_tickets_account = next_account_info(&mut accounts_iter)?;
+    // The instructions sysvar, holding the ed25519 verification of the voucher
+    let instructions_sysvar_account = next_account_info(&mut accounts_iter)?;
+    // The player receiving the tickets, the payer itself when omitted
+    let player_account = next_account_info(&mut accounts_iter).unwrap_or(payer_account);
+
+    if voucher.pool_vault != *pool_vault_account.key
+        || voucher.tickets == 0
+        || voucher
+            .beneficiary
+            .is_some_and(|beneficiary| beneficiary != *player_account.key)
+    {
+        return Err(LotteryError::InvalidVoucher.into());
+    }
+
+    let voucher_signer = PoolVault::new(program_id, pool_vault_account)?
+        .load()?
+        .voucher_signer;
+
+    if voucher_signer == Pubkey::default()
+        || !is_signed_by(
+            instructions_sysvar_account,
+            &voucher_signer,
+            &to_vec(&voucher)?,
+        )?
+    {
+        return Err(LotteryError::InvalidVoucher.into());
+    }
+
+    if Clock::get()?.unix_timestamp > voucher.expires_at {
+        return Err(LotteryError::VoucherExpired.into());
+    }
+
+    let (nullifier_address, bump) =
+        find_voucher_nullifier_pda(program_id, pool_vault_account.key, voucher.voucher_id);
+
+    if nullifier_address != *voucher_nullifier_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    // Anyone can send lamports to the nullifier address, only the program-owned nullifier marks a redemption
+    if voucher_nullifier_account.owner == program_id {
+        return Err(LotteryError::VoucherAlreadyRedeemed.into());
+    }
+
+    // The nullifier holds no data, its existence marks the voucher id as redeemed
+    create_pda_account(
+        payer_account,
+        voucher_nullifier_account,
+        Rent::get()?.minimum_balance(0),
+        0,
+        program_id,
+        system_account,
+        &[
+            PoolStorageSeed::VoucherNullifier.as_bytes(),
+            pool_vault_account.key.as_ref(),
+            &voucher.voucher_id.to_le_bytes(),
+            &[bump],
+        ],
+    )?;
+
+    purchase_tickets(
+        program_id,
+        purchase_accounts,
+        voucher.tickets,
+        None,
+        TicketFunding::HouseBalance,
+        None,
+    )?;
+
+    LotteryEvent::VoucherRedeemed {
+        pool_vault: *pool_vault_account.key,
+        voucher_id: voucher.voucher_id,
+        player: *player_account.key,
+        tickets: voucher.tickets,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn find_voucher_nullifier_pda(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    voucher_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::VoucherNullifier.as_bytes(),
+            pool_vault_account.as_ref(),
+            &voucher_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
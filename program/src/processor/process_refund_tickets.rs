@@ -0,0 +1,128 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{ProgramAccount, RoundData, RoundStatus, RoundTicketsData},
+};
+
+use super::{
+    find_round_pda, find_round_tickets_pda,
+    validation::{PlayerTokenAccount, PoolVault, ReceiptMint},
+};
+
+/// Refunds the tickets a player bought in a cancelled round and burns their receipts.
+pub fn process_refund_tickets(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The player refunded
+    let player_account = next_account_info(&mut accounts)?;
+    // The tickets of the player in the round
+    let round_tickets_account = next_account_info(&mut accounts)?;
+    // The cancelled round
+    let round_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Pool authority
+    let pool_authority_account = next_account_info(&mut accounts)?;
+    // Stake pool mint account
+    let pool_mint_account = next_account_info(&mut accounts)?;
+    // The player token account
+    let player_token_pda_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, pool_authority_account.key)?.writable()?;
+
+    // The receipts burned are those of the refunded player
+    PlayerTokenAccount::new(program_id, player_token_pda_account, player_account.key)?
+        .writable()?;
+
+    if round_tickets_account.owner != program_id
+        || find_round_tickets_pda(program_id, round_account.key, player_account.key).0
+            != *round_tickets_account.key
+    {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    let mut round_tickets = RoundTicketsData::load(&round_tickets_account.data.borrow())?;
+
+    if round_account.owner != program_id
+        || find_round_pda(program_id, pool_vault_account.key, round_tickets.round_id).0
+            != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    let round_data = RoundData::load(&round_account.data.borrow())?;
+
+    if round_data.status != RoundStatus::Cancelled {
+        return Err(LotteryError::RoundNotCancelled.into());
+    }
+
+    if round_tickets.refunded {
+        return Err(LotteryError::AlreadyRefunded.into());
+    }
+
+    let mut pool_storage = pool_vault.load()?;
+
+    let refund = round_tickets.refund(round_data.ticket_price)?;
+
+    if refund > pool_vault_account.lamports() {
+        return Err(LotteryError::InsufficientFunds.into());
+    }
+
+    round_tickets.refunded = true;
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_sub(refund)
+        .ok_or(LotteryError::InsufficientFunds)?;
+
+    round_tickets.store(&mut round_tickets_account.data.borrow_mut())?;
+    pool_vault.store(&pool_storage)?;
+
+    **pool_vault_account.try_borrow_mut_lamports()? -= refund;
+    **player_account.try_borrow_mut_lamports()? += refund;
+
+    let burn_instr = spl_token_2022::instruction::burn(
+        &spl_token_2022::id(),
+        player_token_pda_account.key,
+        pool_mint_account.key,
+        pool_mint_account.key,
+        &[],
+        round_tickets.tickets,
+    )?;
+
+    invoke_signed(
+        &burn_instr,
+        &[
+            player_token_pda_account.clone(),
+            pool_mint_account.clone(),
+            spl_2022_account.clone(),
+        ],
+        &[&receipt_mint.signer_seeds()],
+    )?;
+
+    LotteryEvent::TicketsRefunded {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        player: *player_account.key,
+        amount: refund,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use borsh::{to_vec, BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        Attestation, AttestationData, PoolStorageData, ATTESTED_PLAYER_ACCOUNT_DATA_SIZE,
+        SPONSORED_PLAYER_ACCOUNT_DATA_SIZE,
+    },
+};
+
+use super::{
+    process_set_player_limits::{create_player_account, extend_player_account},
+    signature::is_signed_by,
+    validation::{PlayerTicketAccount, PoolVault},
+};
+
+/// Caches the attestation of the player in its player PDA, the transaction holds the ed25519
+/// verification of the attestation signed by the pool attestor.
+pub fn process_register_attestation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    attestation: Attestation,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The attested player, pays the rent of its player PDA
+    let player_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Account PDA for the player
+    let player_pda_account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+    // The instructions sysvar, holding the ed25519 verification of the attestation
+    let instructions_sysvar_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_storage = PoolVault::new(program_id, pool_vault_account)?.load()?;
+
+    if !pool_storage.requires_attestation()
+        || attestation.wallet != *player_account.key
+        || Clock::get()?.unix_timestamp > attestation.expires_at
+        || !is_signed_by(
+            instructions_sysvar_account,
+            &pool_storage.attestor,
+            &to_vec(&attestation)?,
+        )?
+    {
+        return Err(LotteryError::InvalidAttestation.into());
+    }
+
+    let player_pda =
+        PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?.writable()?;
+
+    if !player_pda.is_initialized() {
+        create_player_account(
+            program_id,
+            player_account,
+            player_pda_account,
+            system_account,
+            player_pda.bump,
+            player_pda.seeds,
+        )?;
+    }
+
+    // The attestation follows the intent nonce
+    extend_player_account(
+        program_id,
+        player_account,
+        player_pda_account,
+        system_account,
+        ATTESTED_PLAYER_ACCOUNT_DATA_SIZE,
+    )?;
+
+    AttestationData {
+        attestor: pool_storage.attestor,
+        expires_at: attestation.expires_at,
+        level: attestation.level,
+    }
+    .serialize(
+        &mut &mut player_pda_account.data.borrow_mut()
+            [SPONSORED_PLAYER_ACCOUNT_DATA_SIZE as usize..],
+    )?;
+
+    LotteryEvent::AttestationRegistered {
+        player: *player_account.key,
+        attestor: pool_storage.attestor,
+        expires_at: attestation.expires_at,
+        level: attestation.level,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Rejects the players without an unexpired attestation of the pool attestor at the pool minimum level,
+/// anyone can play the pools without an attestor.
+pub(crate) fn enforce_attestation(
+    program_id: &Pubkey,
+    pool_storage: &PoolStorageData,
+    player_pda_account: &AccountInfo,
+) -> ProgramResult {
+    if !pool_storage.requires_attestation() {
+        return Ok(());
+    }
+
+    if player_pda_account.owner != program_id
+        || player_pda_account.data_len() < ATTESTED_PLAYER_ACCOUNT_DATA_SIZE as usize
+    {
+        return Err(LotteryError::AttestationRequired.into());
+    }
+
+    let attestation = AttestationData::deserialize(
+        &mut &player_pda_account.data.borrow()[SPONSORED_PLAYER_ACCOUNT_DATA_SIZE as usize..],
+    )?;
+
+    if attestation.attestor != pool_storage.attestor
+        || attestation.level < pool_storage.min_attestation_level
+        || Clock::get()?.unix_timestamp > attestation.expires_at
+    {
+        return Err(LotteryError::AttestationRequired.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{ProgramAccount, RoundData},
+};
+
+use super::validation::{PlayerTokenAccount, PoolVault, ReceiptMint};
+
+use super::{
+    process_open_round::find_round_pda,
+    process_purchase_ticket::{initialize_player_token_account, initialize_round_tickets_account},
+};
+
+/// Creates the player token account and the round tickets account of a player, so it can receive
+/// tickets transferred by another player of a transferable pool. Any round of the pool can be
+/// registered for, the tickets of settled and cancelled rounds being transferable too.
+pub fn process_register_ticket_holder(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Pool authority
+    let pool_authority_account = next_account_info(&mut accounts)?;
+    // The player receiving the tickets, pays for its accounts
+    let player_account = next_account_info(&mut accounts)?;
+    // The player token account
+    let player_token_pda_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Stake pool mint account
+    let pool_mint_account = next_account_info(&mut accounts)?;
+
+    let rent_account = next_account_info(&mut accounts)?;
+
+    let _spl_2022_account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+    // The round the tickets are received in
+    let round_account = next_account_info(&mut accounts)?;
+    // The tickets of the player in the round
+    let round_tickets_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?;
+
+    ReceiptMint::new(program_id, pool_mint_account, pool_authority_account.key)?;
+
+    let player_token =
+        PlayerTokenAccount::new(program_id, player_token_pda_account, player_account.key)?
+            .writable()?;
+
+    let pool_storage = pool_vault.load()?;
+
+    if !pool_storage.transferable_tickets {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    if round_account.owner != program_id {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    let round_data = RoundData::load(&round_account.data.borrow())?;
+
+    if find_round_pda(program_id, pool_vault_account.key, round_data.round_id).0
+        != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    if !player_token.is_initialized() {
+        initialize_player_token_account(
+            program_id,
+            pool_authority_account,
+            player_account,
+            player_account,
+            player_token_pda_account,
+            pool_mint_account,
+            rent_account,
+        )?;
+    }
+
+    initialize_round_tickets_account(
+        program_id,
+        player_account,
+        player_account,
+        round_account,
+        round_tickets_account,
+        system_account,
+        round_data.round_id,
+    )?;
+
+    LotteryEvent::TicketHolderRegistered {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        holder: *player_account.key,
+    }
+    .emit();
+
+    Ok(())
+}
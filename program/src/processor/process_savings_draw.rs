@@ -0,0 +1,203 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{PoolMode, ProgramAccount, SavingsAccountData},
+};
+
+use super::{
+    find_savings_pda_account,
+    randomness::{draw_randomness, draw_seed, DrawRandomness},
+    validation::{PlayerTokenAccount, PoolVault, ReceiptMint},
+};
+
+/// Draws the prize reserve of a savings pool from the hash of its committed draw slot.
+/// Anyone can crank the draw once that hash is known. Every participant must be passed as a
+/// `(savings_pda, player_token_account)` pair ordered by savings PDA, the winner is picked with a
+/// probability proportional to its principal × slots weight up to the draw slot and the prize is
+/// added to its principal, a new draw period starts afterwards.
+pub fn process_savings_draw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Whoever cranks the draw
+    let cranker_account = next_account_info(&mut accounts)?;
+    // Pool authority
+    let authority_account = next_account_info(&mut accounts)?;
+
+    let pool_vault_account = next_account_info(&mut accounts)?;
+
+    let mint_account = next_account_info(&mut accounts)?;
+
+    let slot_hashes_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+
+    if !cranker_account.is_signer {
+        return Err(LotteryError::InvalidSigner.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, mint_account, authority_account.key)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Savings {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    if pool_storage.prize_reserve == 0 {
+        return Err(LotteryError::EmptyPrizeReserve.into());
+    }
+
+    let draw_slot = pool_storage.savings_draw_slot;
+
+    if draw_slot == 0 {
+        return Err(LotteryError::SavingsDrawNotCommitted.into());
+    }
+
+    let slot = Clock::get()?.slot;
+
+    if slot <= draw_slot {
+        return Err(LotteryError::DrawTooEarly.into());
+    }
+
+    // Past the frozen period the principal may have moved since the draw slot, the draw must be committed again
+    if !pool_storage.savings_draw_pending(slot) {
+        return Err(LotteryError::RandomnessNotAvailable.into());
+    }
+
+    let DrawRandomness::Available(slot_hash) = draw_randomness(slot_hashes_account, draw_slot)?
+    else {
+        return Err(LotteryError::RandomnessNotAvailable.into());
+    };
+
+    // The weights are frozen at the draw slot, so the time the draw is cranked at can't bias it
+    pool_storage.accrue_weight(draw_slot)?;
+
+    if pool_storage.total_weight == 0 {
+        return Err(LotteryError::NoParticipants.into());
+    }
+
+    let mut participants: Vec<(&AccountInfo, &AccountInfo, SavingsAccountData)> = Vec::new();
+    let mut weight_sum: u128 = 0;
+
+    while let (Ok(savings_pda_account), Ok(player_token_account)) = (
+        next_account_info(&mut accounts),
+        next_account_info(&mut accounts),
+    ) {
+        // The participants are in a fixed order, so the winner can't be picked by ordering them
+        if savings_pda_account.owner != program_id
+            || participants
+                .last()
+                .is_some_and(|(account, ..)| account.key >= savings_pda_account.key)
+        {
+            return Err(LotteryError::InvalidAccount.into());
+        }
+
+        let mut savings_data = SavingsAccountData::load(&savings_pda_account.data.borrow())?;
+
+        if find_savings_pda_account(program_id, pool_vault_account.key, &savings_data.owner).0
+            != *savings_pda_account.key
+        {
+            return Err(LotteryError::InvalidAccount.into());
+        }
+
+        let player_token =
+            PlayerTokenAccount::new(program_id, player_token_account, &savings_data.owner)?
+                .writable()?;
+
+        savings_data.accrue_weight(&pool_storage, draw_slot)?;
+        weight_sum = weight_sum
+            .checked_add(savings_data.weight)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        participants.push((savings_pda_account, player_token.account, savings_data));
+    }
+
+    // All the participants must be provided, otherwise the draw could be biased by leaving some out.
+    if weight_sum != pool_storage.total_weight {
+        return Err(LotteryError::ParticipantsMismatch.into());
+    }
+
+    let mut winning_weight =
+        draw_seed(&slot_hash, pool_vault_account.key, pool_storage.draft_count)
+            % pool_storage.total_weight;
+
+    let winner_index = participants
+        .iter()
+        .position(|(.., savings_data)| {
+            if winning_weight < savings_data.weight {
+                return true;
+            }
+            winning_weight -= savings_data.weight;
+            false
+        })
+        .ok_or(LotteryError::ParticipantsMismatch)?;
+
+    let prize = pool_storage.prize_reserve;
+
+    pool_storage.prize_reserve = 0;
+    pool_storage.total_principal = pool_storage
+        .total_principal
+        .checked_add(prize)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool_storage.complete_round(slot);
+    pool_storage.period_start_slot = draw_slot;
+    pool_storage.total_weight = 0;
+    pool_storage.savings_draw_slot = 0;
+
+    for (index, (savings_pda_account, _, savings_data)) in participants.iter_mut().enumerate() {
+        if index == winner_index {
+            savings_data.principal = savings_data
+                .principal
+                .checked_add(prize)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+        }
+        savings_data.accrue_weight(&pool_storage, draw_slot)?;
+        savings_data.store(&mut savings_pda_account.data.borrow_mut())?;
+    }
+
+    pool_vault.store(&pool_storage)?;
+
+    let (_, winner_token_account, _) = participants[winner_index];
+
+    let mint_shares_instr = spl_token_2022::instruction::mint_to(
+        &spl_token_2022::id(),
+        mint_account.key,
+        winner_token_account.key,
+        mint_account.key,
+        &[],
+        prize,
+    )?;
+
+    invoke_signed(
+        &mint_shares_instr,
+        &[
+            mint_account.clone(),
+            winner_token_account.clone(),
+            spl_2022_account.clone(),
+        ],
+        &[&receipt_mint.signer_seeds()],
+    )?;
+
+    LotteryEvent::SavingsPrizeDrawn {
+        pool_vault: *pool_vault_account.key,
+        winner: participants[winner_index].2.owner,
+        prize,
+    }
+    .emit();
+
+    Ok(())
+}
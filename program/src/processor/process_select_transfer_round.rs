@@ -0,0 +1,113 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{PoolStorageSeed, ProgramAccount, TransferRoundData, TRANSFER_ROUND_DATA_SIZE},
+};
+
+use super::pda::create_pda_account;
+use super::process_open_round::find_round_pda;
+use super::validation::PoolVault;
+
+/// Selects the round whose tickets move along with the receipts the player transfers next, the transfer
+/// hook reading it from the transfer round account of the player. Any round of the pool can be selected,
+/// so the prize of a settled round or the refund of a cancelled one follows the receipt too.
+pub fn process_select_transfer_round(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    round_id: u64,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The player transferring receipts, pays for its transfer round account
+    let player_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The selected round
+    let round_account = next_account_info(&mut accounts)?;
+    // The transfer round of the player in the pool
+    let transfer_round_account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    if !PoolVault::new(program_id, pool_vault_account)?
+        .load()?
+        .transferable_tickets
+    {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    if round_account.owner != program_id
+        || find_round_pda(program_id, pool_vault_account.key, round_id).0 != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    let (transfer_round_address, bump) =
+        find_transfer_round_pda(program_id, pool_vault_account.key, player_account.key);
+
+    if transfer_round_address != *transfer_round_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    if transfer_round_account.data_is_empty() {
+        create_pda_account(
+            player_account,
+            transfer_round_account,
+            Rent::get()?.minimum_balance(TRANSFER_ROUND_DATA_SIZE as usize),
+            TRANSFER_ROUND_DATA_SIZE as u64,
+            program_id,
+            system_account,
+            &[
+                PoolStorageSeed::TransferRound.as_bytes(),
+                pool_vault_account.key.as_ref(),
+                player_account.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+    } else if transfer_round_account.owner != program_id {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let transfer_round = TransferRoundData {
+        owner: *player_account.key,
+        pool_vault: *pool_vault_account.key,
+        round_id,
+    };
+
+    transfer_round.store(&mut transfer_round_account.data.borrow_mut())?;
+
+    LotteryEvent::TransferRoundSelected {
+        pool_vault: *pool_vault_account.key,
+        owner: *player_account.key,
+        round_id,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn find_transfer_round_pda(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    owner_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::TransferRound.as_bytes(),
+            pool_vault_account.as_ref(),
+            owner_account.as_ref(),
+        ],
+        program_id,
+    )
+}
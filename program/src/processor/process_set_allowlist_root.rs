@@ -0,0 +1,56 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    pubkey::Pubkey,
+};
+
+use crate::{error::LotteryError, events::LotteryEvent};
+
+use super::{find_round_pda, validation::PoolVault};
+
+/// Rotates the allowlist of the pool, a zeroed root opens the pool to anyone.
+/// The root can only be changed between rounds, so the players of a round are held to a single allowlist.
+pub fn process_set_allowlist_root(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    allowlist_root: [u8; 32],
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Pool authority
+    let authority_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The current round of the pool, must not be opened
+    let round_account = next_account_info(&mut accounts)?;
+
+    if !authority_account.is_signer {
+        return Err(LotteryError::AuthorityMustSign.into());
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, authority_account.key)?
+            .writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if find_round_pda(program_id, pool_vault_account.key, pool_storage.draft_count).0
+        != *round_account.key
+    {
+        return Err(LotteryError::InvalidRound.into());
+    }
+
+    if !round_account.data_is_empty() {
+        return Err(LotteryError::RoundStillOpen.into());
+    }
+
+    pool_storage.allowlist_root = allowlist_root;
+    pool_vault.store(&pool_storage)?;
+
+    LotteryEvent::AllowlistRootSet {
+        pool_vault: *pool_vault_account.key,
+        allowlist_root,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        PlayerLimits, PlayerLimitsData, ProgramAccount, TicketAccountData,
+        PLAYER_ACCOUNT_DATA_SIZE, TICKET_ACCOUNT_DATA_SIZE,
+    },
+};
+
+use super::pda::create_pda_account;
+use super::validation::PlayerTicketAccount;
+
+/// Sets the responsible gaming limits of the player, kept in its player PDA.
+/// Stricter limits apply immediately while looser ones only apply after the cooldown.
+pub fn process_set_player_limits(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    requested: PlayerLimits,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The player setting its limits
+    let player_account = next_account_info(&mut accounts)?;
+    // Account PDA for the player
+    let player_pda_account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    if requested.spending_cap > 0 && requested.spending_period <= 0 {
+        return Err(LotteryError::InvalidPlayerLimits.into());
+    }
+
+    let player_pda =
+        PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?.writable()?;
+
+    if !player_pda.is_initialized() {
+        create_player_account(
+            program_id,
+            player_account,
+            player_pda_account,
+            system_account,
+            player_pda.bump,
+            player_pda.seeds,
+        )?;
+    } else {
+        // The player account may predate the limits, it is extended to hold them.
+        extend_player_account(
+            program_id,
+            player_account,
+            player_pda_account,
+            system_account,
+            PLAYER_ACCOUNT_DATA_SIZE,
+        )?;
+    }
+
+    let mut limits_data = load_player_limits(program_id, player_pda_account)?.unwrap_or_default();
+
+    limits_data.request(requested, Clock::get()?.unix_timestamp);
+
+    limits_data.serialize(
+        &mut &mut player_pda_account.data.borrow_mut()[TICKET_ACCOUNT_DATA_SIZE as usize..],
+    )?;
+
+    LotteryEvent::PlayerLimitsSet {
+        player: *player_account.key,
+        limits: limits_data.limits,
+        pending: limits_data.pending,
+        pending_effective_at: limits_data.pending_effective_at,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Creates the player account of a player who never bought a ticket, holding no tickets and no limits.
+pub(crate) fn create_player_account<'a>(
+    program_id: &Pubkey,
+    player_account: &AccountInfo<'a>,
+    player_pda_account: &AccountInfo<'a>,
+    system_account: &AccountInfo<'a>,
+    bump: u8,
+    player_seeds: Vec<Vec<u8>>,
+) -> ProgramResult {
+    let rent = Rent::get()?;
+
+    let mut seed_ref = player_seeds
+        .iter()
+        .map(|s| s.as_slice())
+        .collect::<Vec<&[u8]>>();
+    let s = [bump];
+    seed_ref.push(&s[..]);
+
+    create_pda_account(
+        player_account,
+        player_pda_account,
+        rent.minimum_balance(PLAYER_ACCOUNT_DATA_SIZE as usize),
+        PLAYER_ACCOUNT_DATA_SIZE as u64,
+        program_id,
+        system_account,
+        &seed_ref[..],
+    )?;
+
+    let ticket_data = TicketAccountData {
+        merkle_root: [0; 32],
+        total_tickets: 0,
+    };
+
+    ticket_data.store(&mut player_pda_account.data.borrow_mut())?;
+    PlayerLimitsData::default().serialize(
+        &mut &mut player_pda_account.data.borrow_mut()[TICKET_ACCOUNT_DATA_SIZE as usize..],
+    )?;
+
+    Ok(())
+}
+
+/// Grows the player account to `size` bytes, the payer funding the extra rent.
+/// The added space is zeroed, which reads as no limits and a zero intent nonce.
+pub(crate) fn extend_player_account<'a>(
+    program_id: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    player_pda_account: &AccountInfo<'a>,
+    system_account: &AccountInfo<'a>,
+    size: u32,
+) -> ProgramResult {
+    if player_pda_account.owner != program_id {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    if player_pda_account.data_len() >= size as usize {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let missing_rent = rent
+        .minimum_balance(size as usize)
+        .saturating_sub(rent.minimum_balance(player_pda_account.data_len()));
+
+    invoke(
+        &system_instruction::transfer(payer_account.key, player_pda_account.key, missing_rent),
+        &[
+            payer_account.clone(),
+            player_pda_account.clone(),
+            system_account.clone(),
+        ],
+    )?;
+
+    player_pda_account.realloc(size as usize, true)?;
+
+    Ok(())
+}
+
+/// Records a purchase against the limits of the player, players without limits can always purchase.
+pub(crate) fn enforce_player_limits(
+    program_id: &Pubkey,
+    player_pda_account: &AccountInfo,
+    amount: u64,
+) -> ProgramResult {
+    let Some(mut limits_data) = load_player_limits(program_id, player_pda_account)? else {
+        return Ok(());
+    };
+
+    limits_data.record_spending(amount, Clock::get()?.unix_timestamp)?;
+
+    limits_data.serialize(
+        &mut &mut player_pda_account.data.borrow_mut()[TICKET_ACCOUNT_DATA_SIZE as usize..],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn load_player_limits(
+    program_id: &Pubkey,
+    player_pda_account: &AccountInfo,
+) -> Result<Option<PlayerLimitsData>, solana_program::program_error::ProgramError> {
+    if player_pda_account.owner != program_id
+        || player_pda_account.data_len() < PLAYER_ACCOUNT_DATA_SIZE as usize
+    {
+        return Ok(None);
+    }
+
+    let data = player_pda_account.data.borrow();
+
+    Ok(Some(PlayerLimitsData::deserialize(
+        &mut &data[TICKET_ACCOUNT_DATA_SIZE as usize..],
+    )?))
+}
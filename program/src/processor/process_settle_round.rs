@@ -0,0 +1,152 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{PoolMode, ProgramAccount, RoundStatus, BASIS_POINTS},
+};
+
+use super::{
+    process_open_round::load_current_round,
+    randomness::{draw_randomness, draw_seed, DrawRandomness},
+    validation::PoolVault,
+};
+
+/// Settles the current round of a lottery pool.
+/// Anyone can crank the settlement once the draw slot is reached and its slot hash is known,
+/// the winning ticket is derived from that hash and the cranker is paid a tip out of the house fee.
+/// The prize, topped up with the pool jackpot, is kept in the vault as a liability until the
+/// winner claims it or its claim deadline passes.
+/// A round below its minimum participation is cancelled instead, refunding its players, and so is a
+/// round whose draw slot dropped out of the SlotHashes sysvar before it was settled.
+pub fn process_settle_round(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Whoever cranks the settlement, receives the tip
+    let cranker_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The current round of the pool
+    let round_account = next_account_info(&mut accounts)?;
+
+    let slot_hashes_account = next_account_info(&mut accounts)?;
+
+    if !cranker_account.is_signer {
+        return Err(LotteryError::InvalidSigner.into());
+    }
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let mut round_data =
+        load_current_round(program_id, pool_vault_account, round_account, &pool_storage)?;
+
+    if round_data.status != RoundStatus::Open {
+        return Err(LotteryError::RoundNotOpen.into());
+    }
+
+    let slot = Clock::get()?.slot;
+
+    let draw_slot = round_data.draw_slot(&pool_storage);
+    let randomness = if slot < draw_slot {
+        DrawRandomness::Pending
+    } else {
+        draw_randomness(slot_hashes_account, draw_slot)?
+    };
+
+    // A round that didn't reach its minimum participation is cancelled as soon as its sales close,
+    // and a round no longer drawable from the hash of its draw slot is cancelled too
+    if (slot >= round_data.sales_close_slot && !round_data.meets_minimum_participation())
+        || matches!(randomness, DrawRandomness::Expired)
+    {
+        pool_storage.cancel_round(&mut round_data, slot)?;
+
+        round_data.store(&mut round_account.data.borrow_mut())?;
+        pool_vault.store(&pool_storage)?;
+
+        LotteryEvent::RoundCancelled {
+            pool_vault: *pool_vault_account.key,
+            round_id: round_data.round_id,
+            refunds: round_data.cash_raised(),
+        }
+        .emit();
+
+        return Ok(());
+    }
+
+    if slot < draw_slot {
+        return Err(LotteryError::DrawTooEarly.into());
+    }
+
+    let DrawRandomness::Available(slot_hash) = randomness else {
+        return Err(LotteryError::RandomnessNotAvailable.into());
+    };
+
+    let house_fee = pool_storage.house_fee(round_data.lamports_raised);
+    let tip =
+        (house_fee as u128 * pool_storage.crank_tip_bps as u128 / BASIS_POINTS as u128) as u64;
+
+    if round_data.tickets_sold > 0 {
+        let seed = draw_seed(&slot_hash, pool_vault_account.key, round_data.round_id);
+
+        round_data.winning_ticket = (seed % round_data.tickets_sold as u128) as u64;
+        round_data.prize = round_data.settled_prize(&pool_storage)?;
+        pool_storage.liabilities = pool_storage
+            .liabilities
+            .checked_add(round_data.prize)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        pool_storage.jackpot = 0;
+
+        if pool_storage.claim_period_slots > 0 {
+            round_data.claim_deadline_slot = slot.saturating_add(pool_storage.claim_period_slots);
+        }
+    }
+
+    // The referral rewards of the round are paid out of the house fee, owed until the referrers withdraw them
+    let house_share = house_fee
+        .checked_sub(tip)
+        .and_then(|share| share.checked_sub(round_data.referral_fees))
+        .ok_or(LotteryError::InsufficientFunds)?;
+
+    pool_storage.house_balance = pool_storage
+        .house_balance
+        .checked_add(house_share)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_add(round_data.referral_fees)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    round_data.status = RoundStatus::Settled;
+    pool_storage.complete_round(slot);
+
+    round_data.store(&mut round_account.data.borrow_mut())?;
+    pool_vault.store(&pool_storage)?;
+
+    **pool_vault_account.try_borrow_mut_lamports()? -= tip;
+    **cranker_account.try_borrow_mut_lamports()? += tip;
+
+    LotteryEvent::RoundSettled {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        tickets_sold: round_data.tickets_sold,
+        winning_ticket: round_data.winning_ticket,
+        prize: round_data.prize,
+        house_fee,
+        crank_tip: tip,
+    }
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use borsh::{to_vec, BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    state::{PurchaseIntent, PLAYER_ACCOUNT_DATA_SIZE, SPONSORED_PLAYER_ACCOUNT_DATA_SIZE},
+};
+
+use super::{
+    process_purchase_ticket::{purchase_tickets, TicketFunding},
+    process_set_player_limits::extend_player_account,
+    signature::is_signed_by,
+    validation::{PlayerTicketAccount, PoolVault},
+};
+
+/// Buys tickets for a player who signed a purchase intent, the relayer submitting it pays the tickets,
+/// the rent and the fees. The accounts are the ones of a ticket purchase, with the player following
+/// the instructions sysvar, and the transaction holds the ed25519 verification of the intent signature.
+pub fn process_sponsored_purchase(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    intent: PurchaseIntent,
+) -> ProgramResult {
+    let mut accounts_iter = accounts.iter();
+    // Pool authority
+    let pool_authority_account = next_account_info(&mut accounts_iter)?;
+    // The relayer paying the purchase
+    let payer_account = next_account_info(&mut accounts_iter)?;
+    // Account PDA for the player
+    let player_pda_account = next_account_info(&mut accounts_iter)?;
+
+    let _player_token_pda_account = next_account_info(&mut accounts_iter)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts_iter)?;
+
+    let _pool_mint_account = next_account_info(&mut accounts_iter)?;
+
+    let _rent_account = next_account_info(&mut accounts_iter)?;
+
+    let _spl_2022_account = next_account_info(&mut accounts_iter)?;
+
+    let system_account = next_account_info(&mut accounts_iter)?;
+
+    let _round_account = next_account_info(&mut accounts_iter)?;
+
+    let _round_tickets_account = next_account_info(&mut accounts_iter)?;
+    // The instructions sysvar, holding the ed25519 verification of the intent
+    let instructions_sysvar_account = next_account_info(&mut accounts_iter)?;
+    // The player who signed the intent
+    let player_account = next_account_info(&mut accounts_iter)?;
+
+    PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?;
+    PlayerTicketAccount::new(program_id, player_pda_account, player_account.key)?;
+
+    if intent.player != *player_account.key
+        || intent.pool_vault != *pool_vault_account.key
+        || intent.tickets == 0
+    {
+        return Err(LotteryError::IntentMismatch.into());
+    }
+
+    if Clock::get()?.unix_timestamp > intent.expires_at {
+        return Err(LotteryError::IntentExpired.into());
+    }
+
+    if !is_signed_by(
+        instructions_sysvar_account,
+        player_account.key,
+        &to_vec(&intent)?,
+    )? {
+        return Err(LotteryError::InvalidIntentSignature.into());
+    }
+
+    let round_data = purchase_tickets(
+        program_id,
+        accounts,
+        intent.tickets,
+        None,
+        TicketFunding::Payer,
+        None,
+    )?;
+
+    if round_data.round_id != intent.round_id || round_data.ticket_price > intent.max_ticket_price {
+        return Err(LotteryError::IntentMismatch.into());
+    }
+
+    // The nonce is kept after the player limits, the relayer funds the extra space
+    extend_player_account(
+        program_id,
+        payer_account,
+        player_pda_account,
+        system_account,
+        SPONSORED_PLAYER_ACCOUNT_DATA_SIZE,
+    )?;
+
+    let mut player_data = player_pda_account.try_borrow_mut_data()?;
+    let nonce_data = &mut player_data[PLAYER_ACCOUNT_DATA_SIZE as usize..];
+
+    if u64::deserialize(&mut &nonce_data[..])? != intent.nonce {
+        return Err(LotteryError::InvalidIntentNonce.into());
+    }
+
+    (intent.nonce + 1).serialize(&mut &mut nonce_data[..])?;
+
+    Ok(())
+}
+
+/// The nonce of the next purchase intent of the player, 0 until the player PDA is extended for it.
+pub(crate) fn load_intent_nonce(player_pda_account: &AccountInfo) -> Result<u64, ProgramError> {
+    if player_pda_account.data_len() < SPONSORED_PLAYER_ACCOUNT_DATA_SIZE as usize {
+        return Ok(0);
+    }
+
+    Ok(u64::deserialize(
+        &mut &player_pda_account.data.borrow()[PLAYER_ACCOUNT_DATA_SIZE as usize..],
+    )?)
+}
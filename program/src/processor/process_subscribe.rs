@@ -0,0 +1,130 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        PoolMode, PoolStorageSeed, ProgramAccount, SubscriptionData, SubscriptionTerms,
+        SUBSCRIPTION_DATA_SIZE,
+    },
+};
+
+use super::pda::create_pda_account;
+use super::validation::PoolVault;
+
+/// Subscribes the player to the pool, or updates its subscription, adding the deposit to its escrow.
+pub fn process_subscribe(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    terms: SubscriptionTerms,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The subscribing player, funds the escrow
+    let player_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The subscription of the player to the pool
+    let subscription_account = next_account_info(&mut accounts)?;
+
+    let system_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    if terms.tickets_per_round == 0 {
+        return Err(LotteryError::InvalidSubscription.into());
+    }
+
+    if PoolVault::new(program_id, pool_vault_account)?.load()?.mode != PoolMode::Lottery {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let (subscription_address, bump) =
+        find_subscription_pda(program_id, pool_vault_account.key, player_account.key);
+
+    if subscription_address != *subscription_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    if subscription_account.data_is_empty() {
+        create_pda_account(
+            player_account,
+            subscription_account,
+            Rent::get()?.minimum_balance(SUBSCRIPTION_DATA_SIZE as usize),
+            SUBSCRIPTION_DATA_SIZE as u64,
+            program_id,
+            system_account,
+            &[
+                PoolStorageSeed::Subscription.as_bytes(),
+                pool_vault_account.key.as_ref(),
+                player_account.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let subscription = SubscriptionData {
+            owner: *player_account.key,
+            pool_vault: *pool_vault_account.key,
+            tickets_per_round: 0,
+            escrow: 0,
+            last_round_id: None,
+            rounds_played: 0,
+        };
+
+        subscription.store(&mut subscription_account.data.borrow_mut())?;
+    }
+
+    invoke(
+        &system_instruction::transfer(player_account.key, subscription_account.key, terms.deposit),
+        &[
+            player_account.clone(),
+            subscription_account.clone(),
+            system_account.clone(),
+        ],
+    )?;
+
+    let mut subscription = SubscriptionData::load(&subscription_account.data.borrow())?;
+
+    subscription.tickets_per_round = terms.tickets_per_round;
+    subscription.escrow = subscription
+        .escrow
+        .checked_add(terms.deposit)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    subscription.store(&mut subscription_account.data.borrow_mut())?;
+
+    LotteryEvent::Subscribed {
+        pool_vault: *pool_vault_account.key,
+        owner: *player_account.key,
+        tickets_per_round: terms.tickets_per_round,
+        deposit: terms.deposit,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn find_subscription_pda(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    player_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::Subscription.as_bytes(),
+            pool_vault_account.as_ref(),
+            player_account.as_ref(),
+        ],
+        program_id,
+    )
+}
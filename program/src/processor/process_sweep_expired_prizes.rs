@@ -0,0 +1,84 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{ProgramAccount, RoundData, RoundStatus, SweepDestination},
+};
+
+use super::{find_round_pda, validation::PoolVault};
+
+/// Sweeps the prizes left unclaimed past their deadline.
+/// Anyone can pass the expired rounds, their prizes are released from the pool liabilities
+/// into the jackpot or the house balance depending on the pool sweep destination.
+pub fn process_sweep_expired_prizes(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?.writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    let slot = Clock::get()?.slot;
+
+    // The expired rounds
+    for round_account in accounts {
+        let mut round_data = RoundData::load(&round_account.data.borrow())?;
+
+        if round_account.owner != program_id
+            || find_round_pda(program_id, pool_vault_account.key, round_data.round_id).0
+                != *round_account.key
+        {
+            return Err(LotteryError::InvalidRound.into());
+        }
+
+        if round_data.status != RoundStatus::Settled
+            || round_data.prize == 0
+            || round_data.prize_claimed
+            || round_data.prize_swept
+            || !round_data.claim_expired(slot)
+        {
+            return Err(LotteryError::PrizeNotSweepable.into());
+        }
+
+        round_data.prize_swept = true;
+        pool_storage.liabilities = pool_storage
+            .liabilities
+            .checked_sub(round_data.prize)
+            .ok_or(LotteryError::InsufficientFunds)?;
+
+        let destination_balance = match pool_storage.sweep_destination {
+            SweepDestination::Jackpot => &mut pool_storage.jackpot,
+            SweepDestination::House => &mut pool_storage.house_balance,
+        };
+
+        *destination_balance = destination_balance
+            .checked_add(round_data.prize)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        round_data.store(&mut round_account.data.borrow_mut())?;
+
+        LotteryEvent::PrizeSwept {
+            pool_vault: *pool_vault_account.key,
+            round_id: round_data.round_id,
+            amount: round_data.prize,
+            destination: pool_storage.sweep_destination,
+        }
+        .emit();
+    }
+
+    pool_vault.store(&pool_storage)?;
+
+    Ok(())
+}
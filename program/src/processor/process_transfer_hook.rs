@@ -0,0 +1,261 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_token_2022::{
+    extension::{transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions},
+    state::Account,
+};
+use spl_transfer_hook_interface::{
+    collect_extra_account_metas_signer_seeds, get_extra_account_metas_address_and_bump_seed,
+    instruction::ExecuteInstruction,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        PoolStorageSeed, ProgramAccount, RoundData, RoundStatus, RoundTicketsData,
+        TransferRoundData, TRANSFER_ROUND_ID_OFFSET,
+    },
+};
+
+use super::pda::create_pda_account;
+use super::validation::PoolVault;
+
+// The accounts of the execute instruction, the extra accounts follow the validation account
+const SOURCE_INDEX: u8 = 0;
+const DESTINATION_INDEX: u8 = 2;
+const POOL_VAULT_INDEX: u8 = 5;
+const TRANSFER_ROUND_INDEX: u8 = 6;
+const ROUND_INDEX: u8 = 7;
+// The owner of a token account is stored right after its mint
+const TOKEN_OWNER_OFFSET: u8 = 32;
+
+/// Moves the ticket entitlement along with transferred receipts, invoked by Token-2022 on every
+/// transfer of a transferable pool receipt. Each receipt moves a ticket of the sender in the round
+/// it selected to the receiver, the winning ticket of a settled round first, so the prize claim or
+/// refund follows the receipt.
+pub fn process_transfer_hook(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+    amount: u64,
+) -> ProgramResult {
+    let mut accounts_iter = accounts.iter();
+
+    let source_account = next_account_info(&mut accounts_iter)?;
+
+    let mint_account = next_account_info(&mut accounts_iter)?;
+
+    let _destination_account = next_account_info(&mut accounts_iter)?;
+
+    let _owner_account = next_account_info(&mut accounts_iter)?;
+
+    let extra_account_metas_account = next_account_info(&mut accounts_iter)?;
+
+    let pool_vault_account = next_account_info(&mut accounts_iter)?;
+    // The round selected by the sender, whose tickets are transferred
+    let transfer_round_account = next_account_info(&mut accounts_iter)?;
+
+    let round_account = next_account_info(&mut accounts_iter)?;
+    // The tickets of the sender and the receiver in the round
+    let sender_tickets_account = next_account_info(&mut accounts_iter)?;
+
+    let receiver_tickets_account = next_account_info(&mut accounts_iter)?;
+
+    // The hook can only be run by Token-2022 in the middle of a transfer
+    {
+        let source_data = source_account.data.borrow();
+        let source = StateWithExtensions::<Account>::unpack(&source_data)?;
+
+        if !bool::from(source.get_extension::<TransferHookAccount>()?.transferring) {
+            return Err(LotteryError::NotTransferring.into());
+        }
+    }
+
+    let (extra_account_metas_address, _) =
+        get_extra_account_metas_address_and_bump_seed(mint_account.key, program_id);
+
+    if extra_account_metas_address != *extra_account_metas_account.key
+        || extra_account_metas_account.owner != program_id
+    {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    // The extra accounts are derived from the pool vault stored for this mint
+    ExtraAccountMetaList::check_account_infos::<ExecuteInstruction>(
+        accounts,
+        instruction_data,
+        program_id,
+        &extra_account_metas_account.data.borrow(),
+    )?;
+
+    PoolVault::new(program_id, pool_vault_account)?;
+
+    if transfer_round_account.owner != program_id
+        || round_account.owner != program_id
+        || sender_tickets_account.owner != program_id
+        || receiver_tickets_account.owner != program_id
+    {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    // The seeds already tie the round to the selection of the sender
+    TransferRoundData::check_header(&transfer_round_account.data.borrow())?;
+
+    let round_data = RoundData::load(&round_account.data.borrow())?;
+
+    let mut sender_tickets = RoundTicketsData::load(&sender_tickets_account.data.borrow())?;
+    let mut receiver_tickets = RoundTicketsData::load(&receiver_tickets_account.data.borrow())?;
+
+    let ticket_ranges = match round_data.status {
+        RoundStatus::Settled
+            if amount > 0
+                && !round_data.prize_claimed
+                && !round_data.prize_swept
+                && sender_tickets.holds_ticket(round_data.winning_ticket) =>
+        {
+            let mut ticket_ranges = vec![sender_tickets.take_ticket(round_data.winning_ticket)?];
+            ticket_ranges.extend(sender_tickets.take_tickets(amount - 1)?);
+            ticket_ranges
+        }
+        RoundStatus::Cancelled if sender_tickets.refunded || receiver_tickets.refunded => {
+            return Err(LotteryError::AlreadyRefunded.into());
+        }
+        _ => sender_tickets.take_tickets(amount)?,
+    };
+
+    for ticket_range in ticket_ranges {
+        receiver_tickets.add_range(ticket_range)?;
+    }
+
+    // The voucher tickets of the sender go first, so no ticket paid by the house is ever refunded in cash
+    let voucher_tickets = amount.min(sender_tickets.voucher_tickets);
+
+    sender_tickets.voucher_tickets -= voucher_tickets;
+    receiver_tickets.voucher_tickets = receiver_tickets
+        .voucher_tickets
+        .checked_add(voucher_tickets)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    sender_tickets.store(&mut sender_tickets_account.data.borrow_mut())?;
+    receiver_tickets.store(&mut receiver_tickets_account.data.borrow_mut())?;
+
+    LotteryEvent::TicketsTransferred {
+        pool_vault: *pool_vault_account.key,
+        round_id: round_data.round_id,
+        from: sender_tickets.owner,
+        to: receiver_tickets.owner,
+        tickets: amount,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Creates the transfer hook validation account of the receipt mint, listing the accounts
+/// needed to move the tickets of the round selected by the sender.
+pub(crate) fn initialize_extra_account_metas<'a>(
+    program_id: &Pubkey,
+    pool_authority_account: &AccountInfo<'a>,
+    pool_vault_account: &AccountInfo<'a>,
+    mint_account: &AccountInfo<'a>,
+    extra_account_metas_account: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+) -> ProgramResult {
+    let (extra_account_metas_address, bump) =
+        get_extra_account_metas_address_and_bump_seed(mint_account.key, program_id);
+
+    if extra_account_metas_address != *extra_account_metas_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let extra_account_metas = ticket_transfer_account_metas(pool_vault_account.key)?;
+    let size = ExtraAccountMetaList::size_of(extra_account_metas.len())?;
+
+    let bump_seed = [bump];
+    let signer_seeds = collect_extra_account_metas_signer_seeds(mint_account.key, &bump_seed);
+
+    create_pda_account(
+        pool_authority_account,
+        extra_account_metas_account,
+        Rent::get()?.minimum_balance(size),
+        size as u64,
+        program_id,
+        system_program_account,
+        &signer_seeds,
+    )?;
+
+    ExtraAccountMetaList::init::<ExecuteInstruction>(
+        &mut extra_account_metas_account.try_borrow_mut_data()?,
+        &extra_account_metas,
+    )?;
+
+    Ok(())
+}
+
+fn ticket_transfer_account_metas(
+    pool_vault_address: &Pubkey,
+) -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    let round_tickets_seeds = |token_account_index| {
+        [
+            Seed::Literal {
+                bytes: PoolStorageSeed::RoundTickets.as_bytes().to_vec(),
+            },
+            Seed::AccountKey { index: ROUND_INDEX },
+            Seed::AccountData {
+                account_index: token_account_index,
+                data_index: TOKEN_OWNER_OFFSET,
+                length: 32,
+            },
+        ]
+    };
+
+    Ok(vec![
+        ExtraAccountMeta::new_with_pubkey(pool_vault_address, false, false)?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: PoolStorageSeed::TransferRound.as_bytes().to_vec(),
+                },
+                Seed::AccountKey {
+                    index: POOL_VAULT_INDEX,
+                },
+                Seed::AccountData {
+                    account_index: SOURCE_INDEX,
+                    data_index: TOKEN_OWNER_OFFSET,
+                    length: 32,
+                },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: PoolStorageSeed::Round.as_bytes().to_vec(),
+                },
+                Seed::AccountKey {
+                    index: POOL_VAULT_INDEX,
+                },
+                Seed::AccountData {
+                    account_index: TRANSFER_ROUND_INDEX,
+                    data_index: TRANSFER_ROUND_ID_OFFSET,
+                    length: 8,
+                },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(&round_tickets_seeds(SOURCE_INDEX), false, true)?,
+        ExtraAccountMeta::new_with_seeds(&round_tickets_seeds(DESTINATION_INDEX), false, true)?,
+    ])
+}
@@ -0,0 +1,131 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{PoolMode, ProgramAccount, SavingsAccountData},
+};
+
+use super::{
+    find_savings_pda_account,
+    validation::{PlayerTokenAccount, PoolVault, ReceiptMint},
+};
+
+/// Process a principal withdrawal from a savings pool.
+/// The principal can be withdrawn at any time but while the savings draw is pending, the matching
+/// receipt shares are burned and the lamports are sent back to the player.
+pub fn process_withdraw_principal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // Pool authority
+    let pool_authority_account = next_account_info(&mut accounts)?;
+    // The depositor
+    let player_account = next_account_info(&mut accounts)?;
+    // The savings PDA of the player for this pool
+    let savings_pda_account = next_account_info(&mut accounts)?;
+    // The player token account holding the shares
+    let player_token_pda_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // Stake pool mint account
+    let pool_mint_account = next_account_info(&mut accounts)?;
+
+    let spl_2022_account = next_account_info(&mut accounts)?;
+
+    if !player_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_vault =
+        PoolVault::of_authority(program_id, pool_vault_account, pool_authority_account.key)?
+            .writable()?;
+
+    let receipt_mint =
+        ReceiptMint::new(program_id, pool_mint_account, pool_authority_account.key)?.writable()?;
+
+    if savings_pda_account.owner != program_id
+        || find_savings_pda_account(program_id, pool_vault_account.key, player_account.key).0
+            != *savings_pda_account.key
+    {
+        return Err(LotteryError::InvalidPlayerPdaAccount.into());
+    }
+
+    let player_token =
+        PlayerTokenAccount::new(program_id, player_token_pda_account, player_account.key)?
+            .writable()?;
+
+    let mut pool_storage = pool_vault.load()?;
+
+    if pool_storage.mode != PoolMode::Savings {
+        return Err(LotteryError::InvalidPoolMode.into());
+    }
+
+    let mut savings_data = SavingsAccountData::load(&savings_pda_account.data.borrow())?;
+
+    if amount > savings_data.principal {
+        return Err(LotteryError::InsufficientFunds.into());
+    }
+
+    let slot = Clock::get()?.slot;
+
+    if pool_storage.savings_draw_pending(slot) {
+        return Err(LotteryError::SavingsDrawPending.into());
+    }
+
+    pool_storage.accrue_weight(slot)?;
+    savings_data.accrue_weight(&pool_storage, slot)?;
+
+    savings_data.principal = savings_data
+        .principal
+        .checked_sub(amount)
+        .ok_or(LotteryError::InsufficientFunds)?;
+    pool_storage.total_principal = pool_storage
+        .total_principal
+        .checked_sub(amount)
+        .ok_or(LotteryError::InsufficientFunds)?;
+
+    savings_data.store(&mut savings_pda_account.data.borrow_mut())?;
+    pool_vault.store(&pool_storage)?;
+
+    // The token account is owned by the mint, so the program burns the shares on behalf of the player.
+    let burn_shares_instr = spl_token_2022::instruction::burn(
+        &spl_token_2022::id(),
+        player_token.account.key,
+        pool_mint_account.key,
+        pool_mint_account.key,
+        &[],
+        amount,
+    )?;
+
+    invoke_signed(
+        &burn_shares_instr,
+        &[
+            player_token.account.clone(),
+            pool_mint_account.clone(),
+            spl_2022_account.clone(),
+        ],
+        &[&receipt_mint.signer_seeds()],
+    )?;
+
+    **pool_vault_account.try_borrow_mut_lamports()? -= amount;
+    **player_account.try_borrow_mut_lamports()? += amount;
+
+    LotteryEvent::PrincipalWithdrawn {
+        pool_vault: *pool_vault_account.key,
+        player: *player_account.key,
+        amount,
+    }
+    .emit();
+
+    Ok(())
+}
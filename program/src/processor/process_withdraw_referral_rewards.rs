@@ -0,0 +1,199 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::LotteryError,
+    events::LotteryEvent,
+    state::{
+        PoolStorageData, PoolStorageSeed, ProgramAccount, ReferrerData, RoundData,
+        REFERRER_DATA_SIZE,
+    },
+};
+
+use super::{find_round_pda, pda::create_pda_account, validation::PoolVault};
+
+/// Pays the referral rewards accrued by the referrer out of the pool vault, once the rounds they were
+/// earned in are settled. The rewards of cancelled rounds are void.
+pub fn process_withdraw_referral_rewards(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let mut accounts = accounts.iter();
+    // The referrer, receives its rewards
+    let referrer_account = next_account_info(&mut accounts)?;
+    // Stake pool vault
+    let pool_vault_account = next_account_info(&mut accounts)?;
+    // The referrer PDA of the pool
+    let referrer_pda_account = next_account_info(&mut accounts)?;
+
+    if !referrer_account.is_signer {
+        return Err(solana_program::program_error::ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_vault = PoolVault::new(program_id, pool_vault_account)?.writable()?;
+
+    if referrer_pda_account.owner != program_id
+        || find_referrer_pda(program_id, pool_vault_account.key, referrer_account.key).0
+            != *referrer_pda_account.key
+    {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    let mut pool_storage = pool_vault.load()?;
+    let mut referrer_data = ReferrerData::load(&referrer_pda_account.data.borrow())?;
+
+    // The rounds the referrer has rewards pending on
+    for round_account in accounts {
+        let round_data = RoundData::load(&round_account.data.borrow())?;
+
+        if round_account.owner != program_id
+            || find_round_pda(program_id, pool_vault_account.key, round_data.round_id).0
+                != *round_account.key
+        {
+            return Err(LotteryError::InvalidRound.into());
+        }
+
+        referrer_data.resolve_pending(&round_data)?;
+    }
+
+    let rewards = referrer_data.balance;
+
+    pool_storage.liabilities = pool_storage
+        .liabilities
+        .checked_sub(rewards)
+        .ok_or(LotteryError::InsufficientFunds)?;
+    referrer_data.balance = 0;
+
+    pool_vault.store(&pool_storage)?;
+    referrer_data.store(&mut referrer_pda_account.data.borrow_mut())?;
+
+    **pool_vault_account.try_borrow_mut_lamports()? -= rewards;
+    **referrer_account.try_borrow_mut_lamports()? += rewards;
+
+    LotteryEvent::ReferralRewardsWithdrawn {
+        pool_vault: *pool_vault_account.key,
+        referrer: *referrer_account.key,
+        amount: rewards,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Credits the referrer with its share of the house fee of a purchase and records the referral,
+/// the referrer PDA is created by the payer on the first referral.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_referral<'a>(
+    program_id: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    player_account: &AccountInfo<'a>,
+    referrer_account: &AccountInfo<'a>,
+    referrer_pda_account: &AccountInfo<'a>,
+    pool_vault_account: &AccountInfo<'a>,
+    system_account: &AccountInfo<'a>,
+    pool_storage: &PoolStorageData,
+    round_data: &mut RoundData,
+    ticket_count: u64,
+    purchase_price: u64,
+) -> ProgramResult {
+    if referrer_account.key == player_account.key || referrer_account.key == payer_account.key {
+        return Err(LotteryError::SelfReferral.into());
+    }
+
+    let (referrer_pda_address, bump) =
+        find_referrer_pda(program_id, pool_vault_account.key, referrer_account.key);
+
+    if referrer_pda_address != *referrer_pda_account.key {
+        return Err(LotteryError::InvalidAccount.into());
+    }
+
+    if referrer_pda_account.data_is_empty() {
+        create_pda_account(
+            payer_account,
+            referrer_pda_account,
+            Rent::get()?.minimum_balance(REFERRER_DATA_SIZE as usize),
+            REFERRER_DATA_SIZE as u64,
+            program_id,
+            system_account,
+            &[
+                PoolStorageSeed::Referrer.as_bytes(),
+                pool_vault_account.key.as_ref(),
+                referrer_account.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let referrer_data = ReferrerData {
+            referrer: *referrer_account.key,
+            pool_vault: *pool_vault_account.key,
+            referrals: 0,
+            tickets_referred: 0,
+            lamports_referred: 0,
+            earned: 0,
+            balance: 0,
+            pending: Vec::new(),
+        };
+
+        referrer_data.store(&mut referrer_pda_account.data.borrow_mut())?;
+    }
+
+    let mut referrer_data = ReferrerData::load(&referrer_pda_account.data.borrow())?;
+    let reward = pool_storage.referral_reward(purchase_price);
+
+    referrer_data.referrals = referrer_data
+        .referrals
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    referrer_data.tickets_referred = referrer_data
+        .tickets_referred
+        .checked_add(ticket_count)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    referrer_data.lamports_referred = referrer_data
+        .lamports_referred
+        .checked_add(purchase_price)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    referrer_data.earned = referrer_data
+        .earned
+        .checked_add(reward)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // The reward is only owed by the vault once the round is settled, a cancelled round voids it
+    referrer_data.add_pending(round_data.round_id, reward)?;
+    round_data.referral_fees = round_data
+        .referral_fees
+        .checked_add(reward)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    referrer_data.store(&mut referrer_pda_account.data.borrow_mut())?;
+
+    LotteryEvent::ReferralRecorded {
+        pool_vault: *pool_vault_account.key,
+        referrer: *referrer_account.key,
+        player: *player_account.key,
+        reward,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn find_referrer_pda(
+    program_id: &Pubkey,
+    pool_vault_account: &Pubkey,
+    referrer_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PoolStorageSeed::Referrer.as_bytes(),
+            pool_vault_account.as_ref(),
+            referrer_account.as_ref(),
+        ],
+        program_id,
+    )
+}
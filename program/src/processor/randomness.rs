@@ -0,0 +1,74 @@
+use solana_program::{
+    account_info::AccountInfo, hash::hashv, program_error::ProgramError, pubkey::Pubkey,
+    slot_hashes, sysvar,
+};
+
+// The SlotHashes sysvar is a bincode `Vec<(Slot, Hash)>`, it's too large to be
+// deserialized on chain so the entries are read directly from the account data.
+const SLOT_HASHES_LEN_SIZE: usize = 8;
+const SLOT_HASH_ENTRY_SIZE: usize = 40;
+
+// The randomness of a draw, the hash of its draw slot.
+pub enum DrawRandomness {
+    // The hash of the draw slot isn't known yet
+    Pending,
+    Available([u8; 32]),
+    // The draw slot dropped out of the SlotHashes sysvar, its hash can't be read anymore
+    Expired,
+}
+
+/// Reads the hash of the draw slot from the SlotHashes sysvar, the hash of a slot is only known once
+/// that slot has passed. A skipped draw slot has no hash, the hash of the first slot after it is used
+/// instead. It is only read while the sysvar still reaches back to the draw slot, so the hash of a draw
+/// can't change once it is known.
+pub fn draw_randomness(
+    slot_hashes_account: &AccountInfo,
+    draw_slot: u64,
+) -> Result<DrawRandomness, ProgramError> {
+    if *slot_hashes_account.key != sysvar::slot_hashes::id() {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let data = slot_hashes_account.try_borrow_data()?;
+
+    if data.len() < SLOT_HASHES_LEN_SIZE {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let len = u64::from_le_bytes(data[..SLOT_HASHES_LEN_SIZE].try_into().unwrap()) as usize;
+
+    // The entries are sorted from the most recent slot
+    let entries: Vec<(u64, [u8; 32])> = data[SLOT_HASHES_LEN_SIZE..]
+        .chunks_exact(SLOT_HASH_ENTRY_SIZE)
+        .take(len)
+        .map(|entry| {
+            let entry_slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+            let hash: [u8; 32] = entry[8..].try_into().unwrap();
+            (entry_slot, hash)
+        })
+        .collect();
+
+    // A full sysvar drops its oldest entries, the draw slot is gone once they are all more recent
+    if entries.len() >= slot_hashes::MAX_ENTRIES
+        && entries
+            .last()
+            .is_some_and(|(oldest_slot, _)| *oldest_slot > draw_slot)
+    {
+        return Ok(DrawRandomness::Expired);
+    }
+
+    Ok(entries
+        .into_iter()
+        .take_while(|(entry_slot, _)| *entry_slot >= draw_slot)
+        .last()
+        .map_or(DrawRandomness::Pending, |(_, hash)| {
+            DrawRandomness::Available(hash)
+        }))
+}
+
+/// Derives the draw randomness from a slot hash, so each pool and draw gets its own value.
+pub fn draw_seed(slot_hash: &[u8; 32], pool_vault: &Pubkey, draw_index: u64) -> u128 {
+    let seed = hashv(&[slot_hash, pool_vault.as_ref(), &draw_index.to_le_bytes()]);
+
+    u128::from_le_bytes(seed.to_bytes()[..16].try_into().unwrap())
+}
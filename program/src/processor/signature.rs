@@ -0,0 +1,66 @@
+use solana_program::{
+    account_info::AccountInfo,
+    ed25519_program,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::instructions::{self, load_instruction_at_checked},
+};
+
+// The ed25519 program data starts with the signature count and a padding byte,
+// followed by the offsets of each signature.
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_SIZE: usize = 14;
+const PUBKEY_SIZE: usize = 32;
+// The offsets point into the ed25519 instruction itself
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Returns whether the transaction includes an ed25519 program instruction verifying a signature
+/// of `message` by `signer`, the signature itself is checked by the runtime before the program runs.
+pub fn is_signed_by(
+    instructions_sysvar_account: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<bool, ProgramError> {
+    if !instructions::check_id(instructions_sysvar_account.key) {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut index = 0;
+
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions_sysvar_account) {
+        index += 1;
+
+        if instruction.program_id != ed25519_program::id() {
+            continue;
+        }
+
+        let data = &instruction.data;
+        let signatures = data.first().copied().unwrap_or(0) as usize;
+
+        for signature in 0..signatures {
+            let start = SIGNATURE_OFFSETS_START + signature * SIGNATURE_OFFSETS_SIZE;
+            let Some(offsets) = data.get(start..start + SIGNATURE_OFFSETS_SIZE) else {
+                break;
+            };
+            let offset =
+                |field: usize| u16::from_le_bytes([offsets[field * 2], offsets[field * 2 + 1]]);
+
+            // signature, public key and message instruction indexes
+            if [offset(1), offset(3), offset(6)] != [CURRENT_INSTRUCTION; 3] {
+                continue;
+            }
+
+            let public_key_offset = offset(2) as usize;
+            let message_offset = offset(4) as usize;
+            let message_size = offset(5) as usize;
+
+            if data.get(public_key_offset..public_key_offset + PUBKEY_SIZE) == Some(signer.as_ref())
+                && data.get(message_offset..message_offset + message_size) == Some(message)
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
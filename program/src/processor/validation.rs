@@ -0,0 +1,203 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::Mint,
+};
+
+use crate::{
+    error::LotteryError,
+    state::{PoolStorageData, PoolStorageSeed, ProgramAccount, TicketAccountData},
+};
+
+use super::{
+    find_player_pda_account, find_player_token_pda_account, find_stake_pool_mint_pda,
+    find_stake_pool_vault_pda,
+};
+
+/// Rejects the accounts the instruction writes to but the transaction passed as read-only.
+fn check_writable(account: &AccountInfo) -> ProgramResult {
+    if !account.is_writable {
+        return Err(LotteryError::AccountNotWritable.into());
+    }
+
+    Ok(())
+}
+
+// The vault of a pool, owned by the program and holding the pool storage.
+pub(crate) struct PoolVault<'a, 'info> {
+    pub account: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> PoolVault<'a, 'info> {
+    /// Any pool vault of the program, for the instructions anyone can send to any pool.
+    pub fn new(program_id: &Pubkey, account: &'a AccountInfo<'info>) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            return Err(LotteryError::InvalidStakePoolVault.into());
+        }
+
+        PoolStorageData::check_header(&account.data.borrow())?;
+
+        Ok(Self { account })
+    }
+
+    /// The vault of the pool created by `authority`.
+    pub fn of_authority(
+        program_id: &Pubkey,
+        account: &'a AccountInfo<'info>,
+        authority: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        if find_stake_pool_vault_pda(program_id, authority).0 != *account.key {
+            return Err(LotteryError::InvalidStakePoolVault.into());
+        }
+
+        Self::new(program_id, account)
+    }
+
+    pub fn writable(self) -> Result<Self, ProgramError> {
+        check_writable(self.account)?;
+
+        Ok(self)
+    }
+
+    pub fn load(&self) -> Result<PoolStorageData, ProgramError> {
+        PoolStorageData::load(&self.account.data.borrow())
+    }
+
+    pub fn store(&self, pool_storage: &PoolStorageData) -> ProgramResult {
+        pool_storage.store(&mut self.account.data.borrow_mut())
+    }
+}
+
+// The receipt mint of a pool, a Token-2022 mint that is its own mint authority.
+pub(crate) struct ReceiptMint<'a, 'info> {
+    pub account: &'a AccountInfo<'info>,
+    authority: Pubkey,
+    bump: [u8; 1],
+}
+
+impl<'a, 'info> ReceiptMint<'a, 'info> {
+    /// The receipt mint of the pool created by `authority`.
+    pub fn new(
+        program_id: &Pubkey,
+        account: &'a AccountInfo<'info>,
+        authority: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let (mint_address, bump) = find_stake_pool_mint_pda(program_id, authority);
+
+        if mint_address != *account.key || account.owner != &spl_token_2022::ID {
+            return Err(LotteryError::InvalidAccount.into());
+        }
+
+        Ok(Self {
+            account,
+            authority: *authority,
+            bump: [bump],
+        })
+    }
+
+    pub fn writable(self) -> Result<Self, ProgramError> {
+        check_writable(self.account)?;
+
+        Ok(self)
+    }
+
+    /// Whether the mint carries the extension, the mints of the first release were created without any.
+    pub fn has_extension(&self, extension_type: ExtensionType) -> Result<bool, ProgramError> {
+        let data = self.account.data.borrow();
+        let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+
+        Ok(mint.get_extension_types()?.contains(&extension_type))
+    }
+
+    /// The seeds the mint signs with, as the mint authority and the owner of the non-transferable receipts.
+    pub fn signer_seeds(&self) -> [&[u8]; 3] {
+        [
+            PoolStorageSeed::ReceiptMint.as_bytes(),
+            self.authority.as_ref(),
+            &self.bump,
+        ]
+    }
+}
+
+// The PDA of a player holding its ticket data, created along with its first tickets.
+pub(crate) struct PlayerTicketAccount<'a, 'info> {
+    pub account: &'a AccountInfo<'info>,
+    pub bump: u8,
+    pub seeds: Vec<Vec<u8>>,
+}
+
+impl<'a, 'info> PlayerTicketAccount<'a, 'info> {
+    /// The player PDA of `player`, an existing one must be owned by the program and hold ticket data.
+    pub fn new(
+        program_id: &Pubkey,
+        account: &'a AccountInfo<'info>,
+        player: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let (address, bump, seeds) = find_player_pda_account(program_id, player);
+
+        if address != *account.key {
+            return Err(LotteryError::InvalidPlayerPdaAccount.into());
+        }
+
+        if !account.data_is_empty() {
+            if account.owner != program_id {
+                return Err(LotteryError::InvalidPlayerPdaAccount.into());
+            }
+
+            TicketAccountData::check_header(&account.data.borrow())?;
+        }
+
+        Ok(Self {
+            account,
+            bump,
+            seeds,
+        })
+    }
+
+    pub fn writable(self) -> Result<Self, ProgramError> {
+        check_writable(self.account)?;
+
+        Ok(self)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        !self.account.data_is_empty()
+    }
+}
+
+// The receipt token account of a player, created along with its first tickets.
+pub(crate) struct PlayerTokenAccount<'a, 'info> {
+    pub account: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> PlayerTokenAccount<'a, 'info> {
+    /// The receipt token account of `player`, an existing one must be a Token-2022 account.
+    pub fn new(
+        program_id: &Pubkey,
+        account: &'a AccountInfo<'info>,
+        player: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        if find_player_token_pda_account(program_id, player).0 != *account.key {
+            return Err(LotteryError::InvalidPlayerPdaAccount.into());
+        }
+
+        if !account.data_is_empty() && account.owner != &spl_token_2022::ID {
+            return Err(LotteryError::InvalidAccount.into());
+        }
+
+        Ok(Self { account })
+    }
+
+    pub fn writable(self) -> Result<Self, ProgramError> {
+        check_writable(self.account)?;
+
+        Ok(self)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        !self.account.data_is_empty()
+    }
+}
@@ -1,6 +1,11 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{
+    entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey, slot_hashes,
+};
 
+use crate::error::LotteryError;
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, BorshDeserialize, BorshSerialize)]
 pub enum Instruction {
     InitializePool(PoolStorageData),
@@ -9,18 +14,538 @@ pub enum Instruction {
     PurchaseTicket(TicketAccountData),
     SelectWinnersAndAirdrop(Vec<DraftWinner>),
     ClosePlayerAccount,
+    DepositPrincipal(u64),
+    WithdrawPrincipal(u64),
+    HarvestYield,
+    DrawSavingsPrize,
+    OpenRound(RoundSchedule),
+    SettleRound,
+    ClaimPrize,
+    CancelRound,
+    RefundTickets,
+    SetPlayerLimits(PlayerLimits),
+    SweepExpiredPrizes,
+    ClosePool,
+    RegisterTicketHolder,
+    SponsoredPurchase(PurchaseIntent),
+    RedeemVoucher(Voucher),
+    WithdrawReferralRewards,
+    Subscribe(SubscriptionTerms),
+    CrankSubscription { tickets_per_round: u64 },
+    CancelSubscription,
+    SetAllowlistRoot([u8; 32]),
+    PurchaseAllowlistedTicket(TicketAccountData, AllowlistProof),
+    RegisterAttestation(Attestation),
+    GetPoolInfo,
+    GetPlayerInfo,
+    QuoteTicketPrice { count: u64 },
+    GetRoundResult,
+    MigrateAccount,
+    BeginDraw,
+    PayDrawWinners(Vec<DraftWinner>),
+    FinalizeDraw,
+    AbortDraw,
+    CommitSavingsDraw,
+    SelectTransferRound { round_id: u64 },
 }
 
-#[derive(Debug, BorshSerialize, BorshDeserialize)]
-pub struct TicketAccountData {
-    // The merkle root of the ticket stored offchain
-    pub merkle_root: [u8; 32],
-    pub total_tickets: u64,
+// The borsh size of a type whose encoding has a bounded length, the sizes of the accounts are derived from it.
+pub trait FixedSize {
+    const PACKED_SIZE: usize;
+}
+
+macro_rules! impl_fixed_size {
+    ($($ty:ty => $size:expr),* $(,)?) => {
+        $(impl FixedSize for $ty {
+            const PACKED_SIZE: usize = $size;
+        })*
+    };
+}
+
+impl_fixed_size! {
+    bool => 1,
+    u8 => 1,
+    u16 => 2,
+    u64 => 8,
+    i64 => 8,
+    u128 => 16,
+    Pubkey => 32,
+    // The enums without fields are encoded as their variant index
+    PoolMode => 1,
+    SweepDestination => 1,
+    RoundStatus => 1,
+}
+
+impl<const N: usize> FixedSize for [u8; N] {
+    const PACKED_SIZE: usize = N;
+}
+
+// Options are sized for their value
+impl<T: FixedSize> FixedSize for Option<T> {
+    const PACKED_SIZE: usize = 1 + T::PACKED_SIZE;
+}
+
+// Only the length prefix, the accounts holding a vector allocate its elements themselves
+impl<T> FixedSize for Vec<T> {
+    const PACKED_SIZE: usize = 4;
+}
+
+// Declares a struct along with its fixed size, the sum of the sizes of its fields.
+macro_rules! fixed_size_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl FixedSize for $name {
+            const PACKED_SIZE: usize = 0 $(+ <$ty as FixedSize>::PACKED_SIZE)*;
+        }
+    };
+}
+
+fixed_size_struct! {
+    // Every program-owned account starts with its header, so one account type can't be passed
+    // for another and its layout can be upgraded in place by `MigrateAccount`.
+    #[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+    pub struct AccountHeader {
+        pub discriminator: [u8; 8],
+        pub version: u8,
+    }
+}
+
+pub const ACCOUNT_HEADER_SIZE: usize = AccountHeader::PACKED_SIZE;
+
+// The data of a program-owned account, stored after the account header.
+pub trait ProgramAccount: FixedSize + BorshSerialize + BorshDeserialize {
+    const DISCRIMINATOR: [u8; 8];
+    const VERSION: u8;
+    // The size of the account, header included
+    const SIZE: usize = ACCOUNT_HEADER_SIZE + Self::PACKED_SIZE;
+
+    fn header() -> AccountHeader {
+        AccountHeader {
+            discriminator: Self::DISCRIMINATOR,
+            version: Self::VERSION,
+        }
+    }
+
+    /// Rejects the account data of another type or of an older version.
+    fn check_header(data: &[u8]) -> ProgramResult {
+        let header = AccountHeader::deserialize(&mut &data[..])
+            .map_err(|_| LotteryError::InvalidAccountType)?;
+
+        if header.discriminator != Self::DISCRIMINATOR {
+            return Err(LotteryError::InvalidAccountType.into());
+        }
+
+        if header.version != Self::VERSION {
+            return Err(LotteryError::AccountNotMigrated.into());
+        }
+
+        Ok(())
+    }
+
+    /// Reads the account data, rejecting the accounts of another type or of an older version.
+    fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::check_header(data)?;
+
+        Ok(Self::deserialize(&mut &data[ACCOUNT_HEADER_SIZE..])?)
+    }
+
+    /// Writes the account data behind its header, the bytes following it are left untouched.
+    fn store(&self, data: &mut [u8]) -> ProgramResult {
+        let mut writer = data;
+
+        Self::header().serialize(&mut writer)?;
+        self.serialize(&mut writer)?;
+
+        Ok(())
+    }
+}
+
+macro_rules! impl_program_account {
+    ($($ty:ty => $discriminator:expr),* $(,)?) => {
+        $(impl ProgramAccount for $ty {
+            const DISCRIMINATOR: [u8; 8] = *$discriminator;
+            const VERSION: u8 = 1;
+        })*
+    };
+}
+
+impl_program_account! {
+    PoolStorageData => b"lsolpool",
+    TicketAccountData => b"lsolplyr",
+    SavingsAccountData => b"lsolsave",
+    RoundData => b"lsolrond",
+    RoundTicketsData => b"lsoltckt",
+    ReferrerData => b"lsolrefr",
+    SubscriptionData => b"lsolsubs",
+    DrawSessionData => b"lsoldraw",
+    TransferRoundData => b"lsoltrnd",
+}
+
+// The layouts of the first release, whose accounts were created without a header. `MigrateAccount`
+// tells them apart by their size and converts them to the current layouts.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct LegacyPoolStorageDataV0 {
+        pub ticket_price: u64,
+        pub draft_count: u64,
+        pub initial_amount: u64,
+    }
+}
+
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct LegacyTicketAccountDataV0 {
+        pub merkle_root: [u8; 32],
+        pub total_tickets: u64,
+    }
+}
+
+// The settings added since the first release are off for a migrated pool
+impl From<LegacyPoolStorageDataV0> for PoolStorageData {
+    fn from(legacy: LegacyPoolStorageDataV0) -> Self {
+        PoolStorageData {
+            ticket_price: legacy.ticket_price,
+            draft_count: legacy.draft_count,
+            initial_amount: legacy.initial_amount,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<LegacyTicketAccountDataV0> for TicketAccountData {
+    fn from(legacy: LegacyTicketAccountDataV0) -> Self {
+        TicketAccountData {
+            merkle_root: legacy.merkle_root,
+            total_tickets: legacy.total_tickets,
+        }
+    }
+}
+
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct TicketAccountData {
+        // The merkle root of the ticket stored offchain
+        pub merkle_root: [u8; 32],
+        pub total_tickets: u64,
+    }
 }
 
-pub const TICKET_ACCOUNT_DATA_SIZE: u32 = 40;
+pub const TICKET_ACCOUNT_DATA_SIZE: u32 = TicketAccountData::SIZE as u32;
 
-#[derive(Debug, BorshSerialize, BorshDeserialize)]
+// The time a player waits before looser limits apply, in seconds
+pub const PLAYER_LIMITS_COOLDOWN: i64 = 7 * 24 * 60 * 60;
+
+fixed_size_struct! {
+    #[derive(Debug, Default, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+    pub struct PlayerLimits {
+        // The lamports the player can spend per period, 0 means uncapped
+        pub spending_cap: u64,
+        // The length of the spending period, in seconds
+        pub spending_period: i64,
+        // The unix timestamp until which the player can't purchase tickets
+        pub self_excluded_until: i64,
+    }
+}
+
+impl PlayerLimits {
+    /// Keeps the strictest of both limits, a spending cap only replaces another one when
+    /// it is lower over a period at least as long.
+    pub fn strictest(&self, other: &PlayerLimits) -> PlayerLimits {
+        let other_spending_is_stricter = other.spending_cap > 0
+            && (self.spending_cap == 0
+                || (other.spending_cap <= self.spending_cap
+                    && other.spending_period >= self.spending_period));
+
+        let (spending_cap, spending_period) = if other_spending_is_stricter {
+            (other.spending_cap, other.spending_period)
+        } else {
+            (self.spending_cap, self.spending_period)
+        };
+
+        PlayerLimits {
+            spending_cap,
+            spending_period,
+            self_excluded_until: self.self_excluded_until.max(other.self_excluded_until),
+        }
+    }
+}
+
+// The limits are stored in the player PDA right after its ticket data.
+fixed_size_struct! {
+    #[derive(Debug, Default, Clone, BorshSerialize, BorshDeserialize)]
+    pub struct PlayerLimitsData {
+        pub limits: PlayerLimits,
+        // Looser limits waiting for the cooldown, applied at `pending_effective_at` when set
+        pub pending: PlayerLimits,
+        pub pending_effective_at: i64,
+        pub period_start: i64,
+        pub period_spent: u64,
+    }
+}
+
+pub const PLAYER_LIMITS_DATA_SIZE: u32 = PlayerLimitsData::PACKED_SIZE as u32;
+pub const PLAYER_ACCOUNT_DATA_SIZE: u32 = TICKET_ACCOUNT_DATA_SIZE + PLAYER_LIMITS_DATA_SIZE;
+
+impl PlayerLimitsData {
+    pub fn apply_pending(&mut self, now: i64) {
+        if self.pending_effective_at != 0 && now >= self.pending_effective_at {
+            self.limits = self.pending;
+            self.pending = PlayerLimits::default();
+            self.pending_effective_at = 0;
+        }
+    }
+
+    /// Tightened limits apply right away, the requested limits become pending when they loosen any of them.
+    pub fn request(&mut self, requested: PlayerLimits, now: i64) {
+        self.apply_pending(now);
+        self.limits = self.limits.strictest(&requested);
+
+        if self.limits == requested {
+            self.pending = PlayerLimits::default();
+            self.pending_effective_at = 0;
+        } else {
+            self.pending = requested;
+            self.pending_effective_at = now + PLAYER_LIMITS_COOLDOWN;
+        }
+    }
+
+    /// Whether the limits still restrict the player, looser limits pending until their cooldown ends.
+    pub fn are_active(&self, now: i64) -> bool {
+        let mut limits_data = self.clone();
+        limits_data.apply_pending(now);
+
+        limits_data.limits.spending_cap != 0
+            || now < limits_data.limits.self_excluded_until
+            || limits_data.pending_effective_at != 0
+    }
+
+    /// Records a purchase against the limits, the spending period restarts once it has elapsed.
+    pub fn record_spending(&mut self, amount: u64, now: i64) -> Result<(), ProgramError> {
+        self.apply_pending(now);
+
+        if now < self.limits.self_excluded_until {
+            return Err(LotteryError::PlayerSelfExcluded.into());
+        }
+
+        if self.limits.spending_cap == 0 {
+            return Ok(());
+        }
+
+        // A period too long to end in an i64 timestamp never elapses
+        if self
+            .period_start
+            .checked_add(self.limits.spending_period)
+            .is_some_and(|period_end| now >= period_end)
+        {
+            self.period_start = now;
+            self.period_spent = 0;
+        }
+
+        let period_spent = self
+            .period_spent
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        if period_spent > self.limits.spending_cap {
+            return Err(LotteryError::SpendingLimitExceeded.into());
+        }
+
+        self.period_spent = period_spent;
+
+        Ok(())
+    }
+}
+
+// The nonce of the next purchase intent follows the player limits in the player PDA.
+pub const PLAYER_INTENT_NONCE_SIZE: u32 = u64::PACKED_SIZE as u32;
+pub const SPONSORED_PLAYER_ACCOUNT_DATA_SIZE: u32 =
+    PLAYER_ACCOUNT_DATA_SIZE + PLAYER_INTENT_NONCE_SIZE;
+
+// An attestor statement that the wallet passed its checks up to the given level, signed with ed25519
+// over its borsh encoding. No personal data is stored on-chain, only the attestor, the expiry and the level.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct Attestation {
+    pub wallet: Pubkey,
+    pub expires_at: i64,
+    pub level: u8,
+}
+
+// The last attestation registered by the player, cached in the player PDA after its intent nonce.
+fixed_size_struct! {
+    #[derive(Debug, Default, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+    pub struct AttestationData {
+        pub attestor: Pubkey,
+        pub expires_at: i64,
+        pub level: u8,
+    }
+}
+
+pub const ATTESTATION_DATA_SIZE: u32 = AttestationData::PACKED_SIZE as u32;
+pub const ATTESTED_PLAYER_ACCOUNT_DATA_SIZE: u32 =
+    SPONSORED_PLAYER_ACCOUNT_DATA_SIZE + ATTESTATION_DATA_SIZE;
+
+// A purchase authorized by the player with an ed25519 signature over its borsh encoding,
+// submitted by a relayer paying the tickets, the rent and the fees.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct PurchaseIntent {
+    pub player: Pubkey,
+    // The vault of the pool the tickets are bought from
+    pub pool_vault: Pubkey,
+    pub round_id: u64,
+    pub tickets: u64,
+    // The highest ticket price the player agrees to
+    pub max_ticket_price: u64,
+    // The unix timestamp after which the intent can't be submitted
+    pub expires_at: i64,
+    // Must match the nonce kept in the player PDA, which moves forward on each sponsored purchase
+    pub nonce: u64,
+}
+
+// Free tickets signed by the pool voucher signer with an ed25519 signature over its borsh encoding,
+// paid out of the house balance.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct Voucher {
+    // The vault of the pool the tickets are credited from
+    pub pool_vault: Pubkey,
+    // The only player who can redeem the voucher, anyone when not set
+    pub beneficiary: Option<Pubkey>,
+    pub tickets: u64,
+    // The unix timestamp after which the voucher can't be redeemed
+    pub expires_at: i64,
+    // Each voucher id is redeemed once per pool
+    pub voucher_id: u64,
+}
+
+fixed_size_struct! {
+    // The rewards of a referrer in a round, owed once the round is settled and void if it is cancelled.
+    #[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+    pub struct PendingReferral {
+        pub round_id: u64,
+        pub reward: u64,
+    }
+}
+
+pub const MAX_PENDING_REFERRALS: usize = 8;
+
+// The referral stats and rewards of a referrer in a pool.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct ReferrerData {
+        pub referrer: Pubkey,
+        pub pool_vault: Pubkey,
+        // The referred purchases, with their tickets and lamports
+        pub referrals: u64,
+        pub tickets_referred: u64,
+        pub lamports_referred: u64,
+        // The rewards earned overall and the ones not withdrawn yet
+        pub earned: u64,
+        pub balance: u64,
+        // The rewards of the rounds not resolved yet
+        pub pending: Vec<PendingReferral>,
+    }
+}
+
+// The account is allocated for `MAX_PENDING_REFERRALS` pending rounds
+pub const REFERRER_DATA_SIZE: u32 =
+    (ReferrerData::SIZE + MAX_PENDING_REFERRALS * PendingReferral::PACKED_SIZE) as u32;
+
+impl ReferrerData {
+    /// Adds a reward to the pending rewards of its round.
+    pub fn add_pending(&mut self, round_id: u64, reward: u64) -> Result<(), ProgramError> {
+        if let Some(pending) = self
+            .pending
+            .iter_mut()
+            .find(|pending| pending.round_id == round_id)
+        {
+            pending.reward = pending
+                .reward
+                .checked_add(reward)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+
+            return Ok(());
+        }
+
+        if self.pending.len() >= MAX_PENDING_REFERRALS {
+            return Err(LotteryError::TooManyPendingReferrals.into());
+        }
+
+        self.pending.push(PendingReferral { round_id, reward });
+
+        Ok(())
+    }
+
+    /// Resolves the pending rewards of a round once it is over, crediting them to the balance when the round
+    /// is settled and voiding them when it is cancelled. Returns the credited rewards.
+    pub fn resolve_pending(&mut self, round: &RoundData) -> Result<u64, ProgramError> {
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|pending| pending.round_id == round.round_id)
+        else {
+            return Ok(0);
+        };
+
+        let reward = self.pending[index].reward;
+
+        let credited = match round.status {
+            RoundStatus::Open => return Ok(0),
+            RoundStatus::Settled => reward,
+            RoundStatus::Cancelled => {
+                self.earned = self
+                    .earned
+                    .checked_sub(reward)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+
+                0
+            }
+        };
+
+        self.balance = self
+            .balance
+            .checked_add(credited)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.pending.remove(index);
+
+        Ok(credited)
+    }
+}
+
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct SubscriptionTerms {
+    // The tickets bought for the player in each round
+    pub tickets_per_round: u64,
+    // The lamports added to the escrow
+    pub deposit: u64,
+}
+
+// The subscription of a player to a pool, its escrow pays for the tickets bought each round by the crank
+// along with the rent of the player accounts the crank creates.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct SubscriptionData {
+        pub owner: Pubkey,
+        pub pool_vault: Pubkey,
+        pub tickets_per_round: u64,
+        // The lamports held on top of the rent of the subscription account
+        pub escrow: u64,
+        // The last round the subscription bought tickets in
+        pub last_round_id: Option<u64>,
+        pub rounds_played: u64,
+    }
+}
+
+pub const SUBSCRIPTION_DATA_SIZE: u32 = SubscriptionData::SIZE as u32;
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct DraftWinner {
     pub amount: u64,
     pub proof: Vec<u8>, // The proof bytes
@@ -30,14 +555,578 @@ pub struct DraftWinner {
     pub token_account: Pubkey,
 }
 
-#[derive(Debug, BorshSerialize, BorshDeserialize)]
-pub struct PoolStorageData {
-    pub ticket_price: u64, // in lamports
-    pub draft_count: u64,
-    pub initial_amount: u64,
+// The proof the player wallet is a leaf of the pool allowlist, the leaves are the SHA-256 hashes of the wallets
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct AllowlistProof {
+    pub proof: Vec<u8>, // The proof bytes
+    pub leaf_index: u64,
+    pub total_leaves: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum PoolMode {
+    #[default]
+    Lottery,
+    // No-loss mode, the deposits are kept as principal and only the harvested yield is drawn.
+    Savings,
+}
+
+// Where the prizes left unclaimed past their deadline go
+#[derive(Debug, Default, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum SweepDestination {
+    #[default]
+    Jackpot,
+    House,
+}
+
+fixed_size_struct! {
+    #[derive(Debug, Default, Clone, BorshSerialize, BorshDeserialize)]
+    pub struct PoolStorageData {
+        pub ticket_price: u64, // in lamports
+        pub draft_count: u64,
+        pub initial_amount: u64,
+        pub mode: PoolMode,
+        // The program invoked to harvest the yield and the account it is harvested from
+        pub yield_program: Pubkey,
+        pub yield_source: Pubkey,
+        // The principal deposited by all the players of a savings pool
+        pub total_principal: u64,
+        // The harvested yield waiting for the next savings draw
+        pub prize_reserve: u64,
+        // The sum of principal × slots of all the players in the current draw period
+        pub total_weight: u128,
+        pub weight_updated_slot: u64,
+        pub period_start_slot: u64,
+        // The number of slots to wait after the sales close before the round can be drawn
+        pub draw_buffer_slots: u64,
+        // The share of the round sales kept by the house, in basis points
+        pub house_fee_bps: u16,
+        // The share of the house fee paid to whoever settles a round, in basis points
+        pub crank_tip_bps: u16,
+        // The prizes settled but not claimed yet, and the refunds owed by cancelled rounds
+        pub liabilities: u64,
+        // The number of slots after the draw slot anyone can cancel a round that wasn't settled
+        pub cancel_timeout_slots: u64,
+        // The ticket caps, 0 means uncapped
+        pub max_tickets_per_player: u64,
+        pub max_tickets_per_round: u64,
+        pub max_tickets_per_transaction: u64,
+        // The number of slots the winner has to claim its prize after the settlement, 0 means no deadline
+        pub claim_period_slots: u64,
+        pub sweep_destination: SweepDestination,
+        // The lamports added to the prize of the next round that has a winner
+        pub jackpot: u64,
+        // The house fees kept in the vault
+        pub house_balance: u64,
+        // The slot the last round was completed at
+        pub last_round_slot: u64,
+        // The number of slots after the last round the pool can be closed with receipts outstanding
+        pub close_grace_slots: u64,
+        // The name and symbol of the receipt token, zero padded
+        pub name: [u8; 32],
+        pub symbol: [u8; 10],
+        // Whether the receipts can be transferred, moving the ticket entitlement along
+        pub transferable_tickets: bool,
+        // The key signing the promo vouchers of the pool, the default key disables them
+        pub voucher_signer: Pubkey,
+        // The share of the house fee of referred purchases paid to the referrer, in basis points
+        pub referral_share_bps: u16,
+        // The Merkle root of the wallets allowed to buy tickets, zeroed when the pool is open to anyone
+        pub allowlist_root: [u8; 32],
+        // The key signing the attestations of the players, the default key lets anyone play
+        pub attestor: Pubkey,
+        // The lowest attestation level allowed to buy tickets and claim prizes
+        pub min_attestation_level: u8,
+        // The slot whose hash draws the savings prize, committed ahead of the draw, 0 when none is
+        pub savings_draw_slot: u64,
+    }
+}
+
+pub const POOL_STORAGE_SIZE: u32 = PoolStorageData::SIZE as u32;
+
+// The receipt metadata field holding the current round of the pool
+pub const ROUND_METADATA_KEY: &str = "round";
+
+pub const BASIS_POINTS: u64 = 10_000;
+
+impl PoolStorageData {
+    /// Accumulates the principal × slots weight of the whole pool up to the given slot.
+    pub fn accrue_weight(&mut self, slot: u64) -> Result<(), ProgramError> {
+        let elapsed = slot.saturating_sub(self.weight_updated_slot);
+        self.total_weight = (self.total_principal as u128)
+            .checked_mul(elapsed as u128)
+            .and_then(|weight| self.total_weight.checked_add(weight))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.weight_updated_slot = slot;
+
+        Ok(())
+    }
+
+    /// Whether the principal is frozen for the committed savings draw. It is from the draw slot for as many
+    /// slots as the SlotHashes sysvar holds, so the weights can't move once the hash of the draw is known.
+    pub fn savings_draw_pending(&self, slot: u64) -> bool {
+        self.savings_draw_slot != 0
+            && slot >= self.savings_draw_slot
+            && slot - self.savings_draw_slot < slot_hashes::MAX_ENTRIES as u64
+    }
+
+    pub fn receipt_name(&self) -> String {
+        padded_string(&self.name)
+    }
+
+    pub fn receipt_symbol(&self) -> String {
+        padded_string(&self.symbol)
+    }
+
+    /// Moves the pool to its next round once the current one is drawn, settled or cancelled.
+    pub fn complete_round(&mut self, slot: u64) {
+        self.draft_count += 1;
+        self.last_round_slot = slot;
+    }
+
+    /// Cancels the round, owing its sales back to its players and returning the voucher tickets to the
+    /// house balance. The house never earns a fee on the round, and the referral rewards pending on it are void.
+    pub fn cancel_round(&mut self, round: &mut RoundData, slot: u64) -> Result<(), ProgramError> {
+        self.liabilities = self
+            .liabilities
+            .checked_add(round.cash_raised())
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.house_balance = self
+            .house_balance
+            .checked_add(round.voucher_lamports)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        round.status = RoundStatus::Cancelled;
+        self.complete_round(slot);
+
+        Ok(())
+    }
+
+    pub fn requires_attestation(&self) -> bool {
+        self.attestor != Pubkey::default()
+    }
+
+    pub fn is_allowlisted(&self) -> bool {
+        self.allowlist_root != [0; 32]
+    }
+
+    /// The share of the round sales kept by the house.
+    pub fn house_fee(&self, lamports_raised: u64) -> u64 {
+        (lamports_raised as u128 * self.house_fee_bps as u128 / BASIS_POINTS as u128) as u64
+    }
+
+    /// The referral reward of a purchase, the referrer share of its house fee.
+    pub fn referral_reward(&self, purchase_price: u64) -> u64 {
+        (purchase_price as u128 * self.house_fee_bps as u128 * self.referral_share_bps as u128
+            / (BASIS_POINTS as u128 * BASIS_POINTS as u128)) as u64
+    }
+}
+
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct SavingsAccountData {
+        pub owner: Pubkey,
+        pub principal: u64,
+        // The principal × slots accumulated in the draw period `period`
+        pub weight: u128,
+        pub updated_slot: u64,
+        pub period: u64,
+    }
+}
+
+pub const SAVINGS_ACCOUNT_DATA_SIZE: u32 = SavingsAccountData::SIZE as u32;
+
+impl SavingsAccountData {
+    /// Accumulates the player weight up to the given slot, starting over when
+    /// a draw happened since the last update.
+    pub fn accrue_weight(&mut self, pool: &PoolStorageData, slot: u64) -> Result<(), ProgramError> {
+        if self.period != pool.draft_count {
+            self.period = pool.draft_count;
+            self.weight = 0;
+            self.updated_slot = self.updated_slot.max(pool.period_start_slot);
+        }
+
+        let elapsed = slot.saturating_sub(self.updated_slot);
+        self.weight = (self.principal as u128)
+            .checked_mul(elapsed as u128)
+            .and_then(|weight| self.weight.checked_add(weight))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.updated_slot = slot;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct RoundSchedule {
+    pub sales_open_slot: u64,
+    pub sales_close_slot: u64,
+    // The round is cancelled instead of drawn when either minimum isn't reached by the close
+    pub min_tickets_sold: u64,
+    pub min_lamports_raised: u64,
 }
 
-pub const POOL_STORAGE_SIZE: u32 = 24;
+#[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum RoundStatus {
+    Open,
+    Settled,
+    // The tickets of a cancelled round are refunded to the players
+    Cancelled,
+}
+
+// The rounds of a pool are numbered by its draft count, the current round is the one being sold.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct RoundData {
+        pub round_id: u64,
+        // The tickets are sold in the slots [sales_open_slot, sales_close_slot)
+        pub sales_open_slot: u64,
+        pub sales_close_slot: u64,
+        pub status: RoundStatus,
+        // The pool ticket price when the round was opened
+        pub ticket_price: u64,
+        pub tickets_sold: u64,
+        pub lamports_raised: u64,
+        // The index of the winning ticket and its prize, set once the round is settled
+        pub winning_ticket: u64,
+        pub prize: u64,
+        pub prize_claimed: bool,
+        pub min_tickets_sold: u64,
+        pub min_lamports_raised: u64,
+        // The last slot the prize can be claimed at, 0 means no deadline
+        pub claim_deadline_slot: u64,
+        // Whether the unclaimed prize was swept after the deadline
+        pub prize_swept: bool,
+        // The referral rewards credited on the round sales, taken out of the house fee
+        pub referral_fees: u64,
+        // The sales paid out of the house balance for redeemed vouchers, returned to it if the round is cancelled
+        pub voucher_lamports: u64,
+    }
+}
+
+pub const ROUND_DATA_SIZE: u32 = RoundData::SIZE as u32;
+
+impl RoundData {
+    pub fn is_selling(&self, slot: u64) -> bool {
+        self.status == RoundStatus::Open
+            && slot >= self.sales_open_slot
+            && slot < self.sales_close_slot
+    }
+
+    /// The first slot the round can be drawn at.
+    pub fn draw_slot(&self, pool: &PoolStorageData) -> u64 {
+        self.sales_close_slot.saturating_add(pool.draw_buffer_slots)
+    }
+
+    pub fn meets_minimum_participation(&self) -> bool {
+        self.tickets_sold >= self.min_tickets_sold
+            && self.lamports_raised >= self.min_lamports_raised
+    }
+
+    /// The first slot anyone can cancel the round at, when it wasn't settled before.
+    pub fn cancel_slot(&self, pool: &PoolStorageData) -> u64 {
+        self.draw_slot(pool)
+            .saturating_add(pool.cancel_timeout_slots)
+    }
+
+    /// The prize the round pays once drawn, the sales minus the house fee along with the pool jackpot.
+    pub fn settled_prize(&self, pool: &PoolStorageData) -> Result<u64, ProgramError> {
+        self.lamports_raised
+            .checked_sub(pool.house_fee(self.lamports_raised))
+            .and_then(|prize| prize.checked_add(pool.jackpot))
+            .ok_or(ProgramError::ArithmeticOverflow)
+    }
+
+    /// The prize of the round, the sales minus the house fee along with the pool jackpot until it is settled.
+    pub fn prize_pool(&self, pool: &PoolStorageData) -> Result<u64, ProgramError> {
+        match self.status {
+            RoundStatus::Open => self.settled_prize(pool),
+            RoundStatus::Settled => Ok(self.prize),
+            RoundStatus::Cancelled => Ok(0),
+        }
+    }
+
+    /// The sales paid by the players, refunded to them if the round is cancelled.
+    pub fn cash_raised(&self) -> u64 {
+        self.lamports_raised.saturating_sub(self.voucher_lamports)
+    }
+
+    pub fn claim_expired(&self, slot: u64) -> bool {
+        self.claim_deadline_slot != 0 && slot > self.claim_deadline_slot
+    }
+
+    /// Assigns the next ticket indices of the round to a purchase.
+    pub fn sell_tickets(&mut self, count: u64) -> Result<TicketRange, ProgramError> {
+        let range = TicketRange {
+            first_ticket: self.tickets_sold,
+            count,
+        };
+
+        self.tickets_sold = self
+            .tickets_sold
+            .checked_add(count)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.lamports_raised = count
+            .checked_mul(self.ticket_price)
+            .and_then(|price| self.lamports_raised.checked_add(price))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok(range)
+    }
+}
+
+fixed_size_struct! {
+    #[derive(Debug, Clone, Copy, PartialEq, BorshSerialize, BorshDeserialize)]
+    pub struct TicketRange {
+        pub first_ticket: u64,
+        pub count: u64,
+    }
+}
+
+pub const MAX_TICKET_RANGES: usize = 16;
+
+// The tickets bought by a player in a round, each purchase gets the next range of ticket indices.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct RoundTicketsData {
+        pub owner: Pubkey,
+        pub round_id: u64,
+        pub tickets: u64,
+        pub refunded: bool,
+        // The tickets paid by vouchers, whose price goes back to the house balance instead of the player
+        pub voucher_tickets: u64,
+        pub ranges: Vec<TicketRange>,
+    }
+}
+
+// The account is allocated for `MAX_TICKET_RANGES` ranges
+pub const ROUND_TICKETS_DATA_SIZE: u32 =
+    (RoundTicketsData::SIZE + MAX_TICKET_RANGES * TicketRange::PACKED_SIZE) as u32;
+
+impl RoundTicketsData {
+    pub fn add_range(&mut self, range: TicketRange) -> Result<(), ProgramError> {
+        self.tickets = self
+            .tickets
+            .checked_add(range.count)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // Consecutive purchases extend the last range
+        if let Some(last) = self.ranges.last_mut() {
+            if last.first_ticket.checked_add(last.count) == Some(range.first_ticket) {
+                last.count = last
+                    .count
+                    .checked_add(range.count)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+                return Ok(());
+            }
+        }
+
+        if self.ranges.len() >= MAX_TICKET_RANGES {
+            return Err(LotteryError::TooManyPurchases.into());
+        }
+
+        self.ranges.push(range);
+
+        Ok(())
+    }
+
+    /// The refund of the tickets if the round is cancelled, the voucher tickets aren't refunded to the player.
+    pub fn refund(&self, ticket_price: u64) -> Result<u64, ProgramError> {
+        self.tickets
+            .saturating_sub(self.voucher_tickets)
+            .checked_mul(ticket_price)
+            .ok_or(ProgramError::ArithmeticOverflow)
+    }
+
+    pub fn holds_ticket(&self, ticket: u64) -> bool {
+        self.ranges
+            .iter()
+            .any(|range| ticket >= range.first_ticket && ticket - range.first_ticket < range.count)
+    }
+
+    /// Removes the last `count` tickets, returning their ranges in ascending order.
+    pub fn take_tickets(&mut self, count: u64) -> Result<Vec<TicketRange>, LotteryError> {
+        if count > self.tickets {
+            return Err(LotteryError::NotEnoughTickets);
+        }
+
+        let mut taken = Vec::new();
+        let mut remaining = count;
+
+        while remaining > 0 {
+            let last = self
+                .ranges
+                .last_mut()
+                .ok_or(LotteryError::NotEnoughTickets)?;
+            let moved = remaining.min(last.count);
+
+            last.count -= moved;
+            taken.push(TicketRange {
+                first_ticket: last.first_ticket + last.count,
+                count: moved,
+            });
+
+            if last.count == 0 {
+                self.ranges.pop();
+            }
+            remaining -= moved;
+        }
+
+        self.tickets -= count;
+        taken.reverse();
+
+        Ok(taken)
+    }
+
+    /// Removes a single ticket, splitting the range holding it.
+    pub fn take_ticket(&mut self, ticket: u64) -> Result<TicketRange, LotteryError> {
+        let index = self
+            .ranges
+            .iter()
+            .position(|range| {
+                ticket >= range.first_ticket && ticket - range.first_ticket < range.count
+            })
+            .ok_or(LotteryError::NotEnoughTickets)?;
+
+        let range = self.ranges.remove(index);
+        let before = TicketRange {
+            first_ticket: range.first_ticket,
+            count: ticket - range.first_ticket,
+        };
+        let after = TicketRange {
+            first_ticket: ticket + 1,
+            count: range.count - before.count - 1,
+        };
+
+        for split in [after, before] {
+            if split.count > 0 {
+                if self.ranges.len() >= MAX_TICKET_RANGES {
+                    return Err(LotteryError::TooManyPurchases);
+                }
+
+                self.ranges.insert(index, split);
+            }
+        }
+
+        self.tickets -= 1;
+
+        Ok(TicketRange {
+            first_ticket: ticket,
+            count: 1,
+        })
+    }
+}
+
+pub const MAX_DRAW_WINNERS: usize = 128;
+
+// The progress of a draw paid out over several transactions, the winners already paid and what
+// is left of the prize pool declared when the draw began.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct DrawSessionData {
+        pub pool_vault: Pubkey,
+        pub round_id: u64,
+        pub prize_pool: u64,
+        pub remaining_budget: u64,
+        pub paid_winners: Vec<Pubkey>,
+    }
+}
+
+// The account is allocated for `MAX_DRAW_WINNERS` winners
+pub const DRAW_SESSION_DATA_SIZE: u32 =
+    (DrawSessionData::SIZE + MAX_DRAW_WINNERS * Pubkey::PACKED_SIZE) as u32;
+
+impl DrawSessionData {
+    /// Records the payment of a winner, each winner is paid once and within the remaining budget.
+    pub fn record_payment(&mut self, winner: Pubkey, amount: u64) -> Result<(), LotteryError> {
+        if self.paid_winners.contains(&winner) {
+            return Err(LotteryError::WinnerAlreadyPaid);
+        }
+
+        if amount > self.remaining_budget {
+            return Err(LotteryError::DrawBudgetExceeded);
+        }
+
+        if self.paid_winners.len() >= MAX_DRAW_WINNERS {
+            return Err(LotteryError::TooManyDrawWinners);
+        }
+
+        self.remaining_budget -= amount;
+        self.paid_winners.push(winner);
+
+        Ok(())
+    }
+}
+
+// The round whose tickets move along with the receipts transferred by the owner, the receipts of all
+// the rounds of a pool being the same token.
+fixed_size_struct! {
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    pub struct TransferRoundData {
+        pub owner: Pubkey,
+        pub pool_vault: Pubkey,
+        pub round_id: u64,
+    }
+}
+
+pub const TRANSFER_ROUND_DATA_SIZE: u32 = TransferRoundData::SIZE as u32;
+
+// The offset of the round id in the transfer round account, used to derive the round in the transfer hook
+pub const TRANSFER_ROUND_ID_OFFSET: u8 = (ACCOUNT_HEADER_SIZE + 2 * Pubkey::PACKED_SIZE) as u8;
+
+// The summaries returned by the view instructions through the return data,
+// the instructions write nothing so they can be simulated.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct PoolInfo {
+    pub mode: PoolMode,
+    pub ticket_price: u64,
+    // The current round and its status, none until the round is opened
+    pub current_round_id: u64,
+    pub round_status: Option<RoundStatus>,
+    pub tickets_sold: u64,
+    // The prize the current round would pay if it was settled now
+    pub prize_pool: u64,
+    pub jackpot: u64,
+    pub house_balance: u64,
+    pub liabilities: u64,
+    pub total_principal: u64,
+    pub prize_reserve: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct PlayerInfo {
+    pub player: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_tickets: u64,
+    pub round_id: u64,
+    // The tickets of the player in the round
+    pub round_tickets: u64,
+    // The prize or the refund the player can claim from the round
+    pub claimable: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct TicketQuote {
+    pub round_id: u64,
+    pub ticket_price: u64,
+    pub count: u64,
+    pub total_price: u64,
+    // Whether the round is selling tickets at the current slot
+    pub selling: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct RoundResult {
+    pub round_id: u64,
+    pub status: RoundStatus,
+    pub tickets_sold: u64,
+    pub lamports_raised: u64,
+    // The winning ticket, once the round is settled with tickets sold
+    pub winning_ticket: Option<u64>,
+    pub prize: u64,
+    pub prize_claimed: bool,
+    pub prize_swept: bool,
+    pub claim_deadline_slot: u64,
+}
 
 pub enum PoolStorageSeed {
     // The stake pool storage seed is used to create the PDA for the stake pool,
@@ -47,6 +1136,14 @@ pub enum PoolStorageSeed {
     StakeHouse,
     PlayerAccount,
     PlayerTokenAccount,
+    SavingsAccount,
+    Round,
+    RoundTickets,
+    VoucherNullifier,
+    Referrer,
+    Subscription,
+    DrawSession,
+    TransferRound,
 }
 
 impl PoolStorageSeed {
@@ -57,8 +1154,22 @@ impl PoolStorageSeed {
             PoolStorageSeed::StakeHouse => "StakeHouse".as_bytes(),
             PoolStorageSeed::PlayerAccount => "PlayerAccount".as_bytes(),
             PoolStorageSeed::PlayerTokenAccount => "PlayerTokenAccount".as_bytes(),
+            PoolStorageSeed::SavingsAccount => "SavingsAccount".as_bytes(),
+            PoolStorageSeed::Round => "Round".as_bytes(),
+            PoolStorageSeed::RoundTickets => "RoundTickets".as_bytes(),
+            PoolStorageSeed::VoucherNullifier => "VoucherNullifier".as_bytes(),
+            PoolStorageSeed::Referrer => "Referrer".as_bytes(),
+            PoolStorageSeed::Subscription => "Subscription".as_bytes(),
+            PoolStorageSeed::DrawSession => "DrawSession".as_bytes(),
+            PoolStorageSeed::TransferRound => "TransferRound".as_bytes(),
         }
     }
 }
 
 pub struct HousePoolStorage {}
+
+fn padded_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
@@ -1,28 +1,127 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use rs_merkle::{algorithms::Sha256, Hasher, MerkleTree};
 use solana_lottery_program::{
-    processor::{find_stake_pool_mint_pda, find_stake_pool_vault_pda},
-    state::{DraftWinner, Instruction as LotoInstruction, PoolStorageData},
+    cpi,
+    error::LotteryError,
+    events::LotteryEvent,
+    instruction,
+    processor::{
+        find_player_pda_account, find_player_token_pda_account, find_round_pda,
+        find_round_tickets_pda, find_stake_pool_mint_pda, find_stake_pool_vault_pda,
+        find_transfer_round_pda,
+    },
+    state::{
+        AllowlistProof, Attestation, DraftWinner, Instruction as LotoInstruction, PlayerLimits,
+        PoolStorageData, PurchaseIntent, RoundSchedule, SubscriptionTerms, TicketAccountData,
+        Voucher,
+    },
     ID,
 };
 use solana_program_test::ProgramTest;
 use solana_program_test::*;
 use solana_sdk::{
     account::Account,
+    account_info::AccountInfo,
+    ed25519_program,
+    entrypoint::ProgramResult,
     hash::Hash,
-    instruction::{AccountMeta, Instruction},
+    instruction::{AccountMeta, Instruction, InstructionError},
     native_token::LAMPORTS_PER_SOL,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
     pubkey::Pubkey,
     signature::Keypair,
     signer::Signer,
-    system_program, sysvar,
-    transaction::Transaction,
+    system_instruction, system_program, sysvar,
+    transaction::{Transaction, TransactionError},
 };
+use spl_transfer_hook_interface::get_extra_account_metas_address;
+use std::sync::{Once, OnceLock};
 
-pub async fn setup() -> (BanksClient, Keypair, solana_sdk::hash::Hash, Keypair) {
-    let mut program = ProgramTest::new(
+// The amount paid by the stand-in yield program on each harvest
+pub const YIELD_PER_HARVEST: u64 = LAMPORTS_PER_SOL;
+
+fn program_test() -> ProgramTest {
+    // The stubs logging the events are installed before the first test starts its bank, so they are
+    // never swapped while a test runs
+    static LOG_DATA_STUBS: Once = Once::new();
+
+    LOG_DATA_STUBS.call_once(install_log_data_stubs);
+
+    ProgramTest::new(
         "solana_lottery_program",
         ID,
         processor!(solana_lottery_program::processor::processor),
-    );
+    )
+}
+
+// Stands in for a staking program, each call moves a fixed yield from the source to the vault.
+fn yield_source_processor(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let yield_source = &accounts[0];
+    let pool_vault = &accounts[1];
+
+    **yield_source.try_borrow_mut_lamports()? -= YIELD_PER_HARVEST;
+    **pool_vault.try_borrow_mut_lamports()? += YIELD_PER_HARVEST;
+
+    Ok(())
+}
+
+// The seed of the PDA the stand-in partner program buys tickets for
+pub const TICKET_BUYER_SEED: &[u8] = b"ticket_buyer";
+
+// Stands in for a partner program, buys a ticket of the round through CPI for the PDA it owns.
+// The PDA is both the payer and the player, so it signs the purchase with its seeds.
+fn ticket_buyer_processor(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let pool_authority = &accounts[0];
+    let buyer = &accounts[1];
+    let round_id = u64::try_from_slice(instruction_data)?;
+
+    let (buyer_address, bump) = Pubkey::find_program_address(&[TICKET_BUYER_SEED], program_id);
+    assert_eq!(buyer_address, *buyer.key);
+
+    cpi::purchase_ticket_signed(
+        pool_authority.key,
+        buyer.key,
+        buyer.key,
+        round_id,
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 1,
+        },
+        accounts,
+        &[&[TICKET_BUYER_SEED, &[bump]]],
+    )
+}
+
+pub fn assert_lottery_error(result: Result<(), BanksClientError>, error: LotteryError) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, error as u32),
+        other => panic!("Expected {:?}, got {:?}", error, other),
+    }
+}
+
+pub fn assert_instruction_error(result: Result<(), BanksClientError>, error: InstructionError) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(_, code))) => {
+            assert_eq!(code, error)
+        }
+        other => panic!("Expected {:?}, got {:?}", error, other),
+    }
+}
+
+pub async fn setup() -> (BanksClient, Keypair, solana_sdk::hash::Hash, Keypair) {
+    let mut program = program_test();
 
     let player = Keypair::new();
     program.add_account(
@@ -36,41 +135,225 @@ pub async fn setup() -> (BanksClient, Keypair, solana_sdk::hash::Hash, Keypair)
     (banks_client, payer, recent_blockhash, player)
 }
 
+pub async fn setup_with_context() -> (ProgramTestContext, Keypair) {
+    let mut program = program_test();
+
+    let player = Keypair::new();
+    program.add_account(
+        player.pubkey(),
+        Account::new(100_000_000_000, 0, &system_program::ID),
+    );
+
+    (program.start_with_context().await, player)
+}
+
+// Sets up a test with accounts already on chain at genesis, for the layouts no instruction creates anymore.
+// Accounts set once the test runs aren't counted in the capitalization, which fails any later warp.
+pub async fn setup_with_accounts(
+    player: &Keypair,
+    accounts: Vec<(Pubkey, Account)>,
+) -> ProgramTestContext {
+    let mut program = program_test();
+
+    program.add_account(
+        player.pubkey(),
+        Account::new(100_000_000_000, 0, &system_program::ID),
+    );
+
+    for (address, account) in accounts {
+        program.add_account(address, account);
+    }
+
+    program.start_with_context().await
+}
+
+// Sets up a savings pool test with two funded players and the stand-in yield program.
+// The returned pubkeys are the yield program and the yield source account.
+pub async fn setup_savings() -> (ProgramTestContext, Keypair, Keypair, Pubkey, Pubkey) {
+    let mut program = program_test();
+
+    let yield_program = Pubkey::new_unique();
+    program.add_program(
+        "yield_source",
+        yield_program,
+        processor!(yield_source_processor),
+    );
+
+    let yield_source = Pubkey::new_unique();
+    program.add_account(
+        yield_source,
+        Account::new(100 * LAMPORTS_PER_SOL, 0, &yield_program),
+    );
+
+    let player = Keypair::new();
+    let other_player = Keypair::new();
+
+    for player in [&player, &other_player] {
+        program.add_account(
+            player.pubkey(),
+            Account::new(100_000_000_000, 0, &system_program::ID),
+        );
+    }
+
+    let context = program.start_with_context().await;
+
+    (context, player, other_player, yield_program, yield_source)
+}
+
+// Sets up a test with the stand-in partner program, returning it along with the funded PDA it buys tickets for.
+pub async fn setup_ticket_buyer() -> (ProgramTestContext, Pubkey, Pubkey) {
+    let mut program = program_test();
+
+    let ticket_buyer_program = Pubkey::new_unique();
+    program.add_program(
+        "ticket_buyer",
+        ticket_buyer_program,
+        processor!(ticket_buyer_processor),
+    );
+
+    let (buyer, _) = Pubkey::find_program_address(&[TICKET_BUYER_SEED], &ticket_buyer_program);
+    program.add_account(
+        buyer,
+        Account::new(100 * LAMPORTS_PER_SOL, 0, &system_program::ID),
+    );
+
+    (
+        program.start_with_context().await,
+        ticket_buyer_program,
+        buyer,
+    )
+}
+
+// The tickets of the players of the draw tests, the same tree for both of them.
+const DRAW_TICKETS: [&str; 4] = ["0", "1", "2", "3"];
+
+// Sets up a draw test: a pool with a 10% house fee whose round 0 sold a ticket to each of the two
+// players, ready to be drawn.
+pub async fn setup_draw() -> (ProgramTestContext, Keypair, Keypair) {
+    let mut program = program_test();
+
+    let player = Keypair::new();
+    let other_player = Keypair::new();
+
+    for player in [&player, &other_player] {
+        program.add_account(
+            player.pubkey(),
+            Account::new(100 * LAMPORTS_PER_SOL, 0, &system_program::ID),
+        );
+    }
+
+    let mut context = program.start_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let tx = initialize_pool_tx(
+        &ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            house_fee_bps: 1_000,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let merkle_tree = draw_merkle_tree();
+
+    for buyer in [&player, &other_player] {
+        let tx = purchase_ticket_data_tx(
+            &pool_authority.pubkey(),
+            buyer,
+            0,
+            TicketAccountData {
+                merkle_root: merkle_tree.root().unwrap(),
+                total_tickets: DRAW_TICKETS.len() as u64,
+            },
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    context.warp_to_slot(50).unwrap();
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    (context, player, other_player)
+}
+
+fn draw_merkle_tree() -> MerkleTree<Sha256> {
+    let ticket_hashes: Vec<[u8; 32]> = DRAW_TICKETS
+        .iter()
+        .map(|ticket| Sha256::hash(ticket.as_bytes()))
+        .collect();
+
+    MerkleTree::<Sha256>::from_leaves(&ticket_hashes)
+}
+
+// Drafts the player of a draw test as a winner of the given amount, with the proof of its second ticket.
+pub fn draw_winner(player: &Keypair, amount: u64) -> DraftWinner {
+    DraftWinner {
+        amount,
+        token_account: find_player_token_pda_account(&ID, &player.pubkey()).0,
+        address: find_player_pda_account(&ID, &player.pubkey()).0,
+        tickets: vec![Sha256::hash(DRAW_TICKETS[1].as_bytes())],
+        proof: draw_merkle_tree().proof(&[1]).to_bytes(),
+        ticket_indices: vec![1],
+    }
+}
+
 pub fn initialize_stake_pool_tx(
     program_id: &Pubkey,
     pool_authority: &Keypair,
     recent_blockhash: &Hash,
 ) -> Transaction {
-    let (pool_mint_account, ..) = find_stake_pool_mint_pda(&program_id, &pool_authority.pubkey());
-    let (pool_vault_account, ..) = find_stake_pool_vault_pda(&program_id, &pool_authority.pubkey());
-
     let pool_storage_data = PoolStorageData {
         ticket_price: 100_000_500,
         draft_count: 0,
         initial_amount: 10 * LAMPORTS_PER_SOL,
+        ..Default::default()
     };
 
-    let instruction_data = LotoInstruction::InitializePool(pool_storage_data);
-    let accounts = vec![
-        AccountMeta::new(pool_authority.pubkey(), true),
-        AccountMeta::new(pool_vault_account, false),
-        AccountMeta::new(pool_mint_account, false),
-        AccountMeta::new_readonly(sysvar::rent::id(), false),
-        AccountMeta::new_readonly(spl_token_2022::id(), false),
-        AccountMeta::new_readonly(system_program::id(), false),
-    ];
+    initialize_pool_tx(
+        program_id,
+        pool_authority,
+        pool_storage_data,
+        recent_blockhash,
+    )
+}
 
-    let instruction =
-        Instruction::new_with_borsh(program_id.to_owned(), &instruction_data, accounts);
+pub fn initialize_pool_tx(
+    program_id: &Pubkey,
+    pool_authority: &Keypair,
+    pool_storage_data: PoolStorageData,
+    recent_blockhash: &Hash,
+) -> Transaction {
+    assert_eq!(*program_id, ID);
 
     Transaction::new_signed_with_payer(
-        &[instruction],
+        &[instruction::initialize_pool(
+            &pool_authority.pubkey(),
+            pool_storage_data,
+        )],
         Some(&pool_authority.pubkey()),
         &[&pool_authority],
         recent_blockhash.to_owned(),
     )
 }
 
+// Buys tickets with the accounts given as is, so a test can pass the accounts of another pool or player.
+#[allow(clippy::too_many_arguments)]
 pub fn purchase_ticket_tx(
     program_id: &Pubkey,
     pool_authority: &Keypair,
@@ -79,6 +362,7 @@ pub fn purchase_ticket_tx(
     player_token_pda_address: Pubkey,
     pool_vault_account: Pubkey,
     pool_mint_account: Pubkey,
+    round_account: Pubkey,
     recent_blockhash: Hash,
     ticket_data: &LotoInstruction,
 ) -> Transaction {
@@ -92,6 +376,12 @@ pub fn purchase_ticket_tx(
         AccountMeta::new_readonly(sysvar::rent::id(), false),
         AccountMeta::new_readonly(spl_token_2022::id(), false),
         AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(round_account, false),
+        AccountMeta::new(
+            find_round_tickets_pda(program_id, &round_account, &player.pubkey()).0,
+            false,
+        ),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
     ];
 
     let instruction = Instruction::new_with_borsh(*program_id, &ticket_data, accounts);
@@ -104,30 +394,258 @@ pub fn purchase_ticket_tx(
     )
 }
 
+// Buys one ticket per entry of `total_tickets` within a single transaction.
+pub fn purchase_tickets_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    round_id: u64,
+    total_tickets: &[u64],
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instructions: Vec<Instruction> = total_tickets
+        .iter()
+        .map(|total_tickets| {
+            instruction::purchase_ticket(
+                pool_authority,
+                &player.pubkey(),
+                &player.pubkey(),
+                round_id,
+                TicketAccountData {
+                    merkle_root: [0; 32],
+                    total_tickets: *total_tickets,
+                },
+            )
+        })
+        .collect();
+
+    Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Buys the tickets of the given merkle root for the player.
+pub fn purchase_ticket_data_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    round_id: u64,
+    ticket_data: TicketAccountData,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::purchase_ticket(
+            pool_authority,
+            &player.pubkey(),
+            &player.pubkey(),
+            round_id,
+            ticket_data,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Buys a ticket paid by the payer on behalf of the beneficiary.
+pub fn gift_ticket_tx(
+    pool_authority: &Pubkey,
+    payer: &Keypair,
+    beneficiary: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instruction = instruction::purchase_ticket(
+        pool_authority,
+        &payer.pubkey(),
+        beneficiary,
+        round_id,
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 1,
+        },
+    );
+
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+// Verifies a signature of the message by the signer, with the offsets pointing into the instruction itself.
+pub fn ed25519_signature_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+
+    let mut data = vec![1, 0];
+    for offset in [
+        SIGNATURE_OFFSET,
+        u16::MAX,
+        PUBLIC_KEY_OFFSET,
+        u16::MAX,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+}
+
+// Submits a purchase intent signed by `signer` for the intent player, paid by the relayer.
+pub fn sponsored_purchase_tx(
+    pool_authority: &Pubkey,
+    relayer: &Keypair,
+    signer: &Keypair,
+    intent: PurchaseIntent,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let signature_instruction =
+        ed25519_signature_instruction(signer, &borsh::to_vec(&intent).unwrap());
+    let instruction = instruction::sponsored_purchase(pool_authority, &relayer.pubkey(), intent);
+
+    Transaction::new_signed_with_payer(
+        &[signature_instruction, instruction],
+        Some(&relayer.pubkey()),
+        &[&relayer],
+        recent_blockhash,
+    )
+}
+
+// Redeems a voucher signed by `signer` for the player.
+pub fn redeem_voucher_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    signer: &Keypair,
+    round_id: u64,
+    voucher: Voucher,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let signature_instruction =
+        ed25519_signature_instruction(signer, &borsh::to_vec(&voucher).unwrap());
+    let instruction =
+        instruction::redeem_voucher(pool_authority, &player.pubkey(), round_id, voucher);
+
+    Transaction::new_signed_with_payer(
+        &[signature_instruction, instruction],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Buys a ticket for the player, referred by the referrer.
+pub fn referred_purchase_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    referrer: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instruction = instruction::referred_purchase_ticket(
+        pool_authority,
+        &player.pubkey(),
+        referrer,
+        round_id,
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 1,
+        },
+    );
+
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn withdraw_referral_rewards_tx(
+    referrer: &Keypair,
+    pool_authority: &Pubkey,
+    round_ids: &[u64],
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::withdraw_referral_rewards(
+            &referrer.pubkey(),
+            pool_authority,
+            round_ids,
+        )],
+        Some(&referrer.pubkey()),
+        &[&referrer],
+        recent_blockhash,
+    )
+}
+
 pub fn process_winners_tx(
     pool_authority: &Keypair,
-    winners_instruction_data: Vec<DraftWinner>,
-    winners_accounts: Vec<AccountMeta>,
+    round_id: u64,
+    draft_winners: Vec<DraftWinner>,
     recent_blockhash: Hash,
 ) -> Transaction {
-    let (pool_mint_account, ..) =
-        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
-    let (pool_vault_account, ..) =
-        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    Transaction::new_signed_with_payer(
+        &[instruction::select_winners(
+            &pool_authority.pubkey(),
+            round_id,
+            draft_winners,
+        )],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
 
-    let instruction_data = LotoInstruction::SelectWinnersAndAirdrop(winners_instruction_data);
-    let mut accounts = vec![
-        AccountMeta::new(pool_authority.pubkey(), true),
-        AccountMeta::new(pool_vault_account, false),
-        AccountMeta::new(pool_mint_account, false),
-    ];
+pub fn begin_draw_tx(
+    pool_authority: &Keypair,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::begin_draw(&pool_authority.pubkey(), round_id)],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
 
-    winners_accounts.iter().for_each(|account| {
-        accounts.push(account.clone());
-    });
+pub fn pay_draw_winners_tx(
+    pool_authority: &Keypair,
+    round_id: u64,
+    draft_winners: Vec<DraftWinner>,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::pay_draw_winners(
+            &pool_authority.pubkey(),
+            round_id,
+            draft_winners,
+        )],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
 
-    let instruction =
-        Instruction::new_with_borsh(solana_lottery_program::ID, &instruction_data, accounts);
+// Pays the winners without passing their player PDAs and token accounts.
+pub fn pay_draw_winners_without_accounts_tx(
+    pool_authority: &Keypair,
+    round_id: u64,
+    draft_winners: Vec<DraftWinner>,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut instruction =
+        instruction::pay_draw_winners(&pool_authority.pubkey(), round_id, draft_winners);
+    instruction.accounts.truncate(4);
 
     Transaction::new_signed_with_payer(
         &[instruction],
@@ -137,23 +655,42 @@ pub fn process_winners_tx(
     )
 }
 
+pub fn finalize_draw_tx(
+    pool_authority: &Keypair,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::finalize_draw(
+            &pool_authority.pubkey(),
+            round_id,
+        )],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
+
+pub fn abort_draw_tx(
+    pool_authority: &Keypair,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::abort_draw(&pool_authority.pubkey(), round_id)],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
+
 pub fn process_withdraw_tx(
     player_account: &Keypair,
-    player_pda_account: Pubkey,
     amount: u64,
     recent_blockhash: Hash,
 ) -> Transaction {
-    let instruction_data = LotoInstruction::PlayerWithdraw(amount);
-    let accounts = vec![
-        AccountMeta::new(player_account.pubkey(), true),
-        AccountMeta::new(player_pda_account, false),
-    ];
-
-    let instruction =
-        Instruction::new_with_borsh(solana_lottery_program::ID, &instruction_data, accounts);
-
     Transaction::new_signed_with_payer(
-        &[instruction],
+        &[instruction::withdraw(&player_account.pubkey(), amount)],
         Some(&player_account.pubkey()),
         &[&player_account],
         recent_blockhash,
@@ -162,29 +699,782 @@ pub fn process_withdraw_tx(
 
 pub fn close_account_tx(
     player: &Keypair,
-    player_pda_address: Pubkey,
-    player_token_pda_address: Pubkey,
     pool_authority: &Pubkey,
-    mint_account: &Pubkey,
     recent_blockhash: Hash,
 ) -> Transaction {
-    let instruction_data = LotoInstruction::ClosePlayerAccount;
-    let accounts = vec![
-        AccountMeta::new(player.pubkey(), true),
-        AccountMeta::new(player_pda_address, false),
-        AccountMeta::new(player_token_pda_address, false),
-        AccountMeta::new(*pool_authority, false),
-        AccountMeta::new_readonly(*mint_account, false),
-        AccountMeta::new_readonly(spl_token_2022::id(), false),
-    ];
-
-    let instruction =
-        Instruction::new_with_borsh(solana_lottery_program::ID, &instruction_data, accounts);
-
     Transaction::new_signed_with_payer(
-        &[instruction],
+        &[instruction::close_player_account(
+            &player.pubkey(),
+            pool_authority,
+        )],
         Some(&player.pubkey()),
         &[&player],
         recent_blockhash,
     )
 }
+
+pub fn deposit_principal_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    amount: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::deposit_principal(
+            pool_authority,
+            &player.pubkey(),
+            amount,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn withdraw_principal_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    amount: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::withdraw_principal(
+            pool_authority,
+            &player.pubkey(),
+            amount,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Sends the principal withdrawal of the player along with the receipt token account of another wallet.
+pub fn withdraw_principal_with_token_account_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    amount: u64,
+    player_token_account: Pubkey,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut instruction = instruction::withdraw_principal(pool_authority, &player.pubkey(), amount);
+    instruction.accounts[3].pubkey = player_token_account;
+
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn harvest_yield_tx(
+    payer: &Keypair,
+    pool_authority: &Pubkey,
+    yield_source: &Pubkey,
+    yield_program: &Pubkey,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::harvest_yield(
+            pool_authority,
+            yield_source,
+            yield_program,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+pub fn commit_savings_draw_tx(pool_authority: &Keypair, recent_blockhash: Hash) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::commit_savings_draw(&pool_authority.pubkey())],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
+
+pub fn draw_savings_prize_tx(
+    cranker: &Keypair,
+    pool_authority: &Pubkey,
+    participants: &[Pubkey],
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::draw_savings_prize(
+            &cranker.pubkey(),
+            pool_authority,
+            participants,
+        )],
+        Some(&cranker.pubkey()),
+        &[&cranker],
+        recent_blockhash,
+    )
+}
+
+pub fn open_round_tx(
+    pool_authority: &Keypair,
+    round_id: u64,
+    schedule: RoundSchedule,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::open_round(
+            &pool_authority.pubkey(),
+            round_id,
+            schedule,
+        )],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
+
+// Has the stand-in partner program buy a ticket of the round for its PDA.
+pub fn ticket_buyer_purchase_tx(
+    payer: &Keypair,
+    ticket_buyer_program: &Pubkey,
+    buyer: &Pubkey,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut accounts = instruction::purchase_ticket(
+        pool_authority,
+        buyer,
+        buyer,
+        round_id,
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 1,
+        },
+    )
+    .accounts;
+    // The partner program signs for its PDA
+    accounts[1].is_signer = false;
+    accounts.push(AccountMeta::new_readonly(ID, false));
+
+    let instruction = Instruction::new_with_borsh(*ticket_buyer_program, &round_id, accounts);
+
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+pub fn deposit_tx(
+    payer: &Keypair,
+    pool_authority: &Pubkey,
+    amount: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::deposit(
+            &payer.pubkey(),
+            pool_authority,
+            amount,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+pub fn fund_account_tx(
+    payer: &Keypair,
+    account: &Pubkey,
+    lamports: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            account,
+            lamports,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+pub fn settle_round_tx(
+    cranker: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::settle_round(
+            &cranker.pubkey(),
+            pool_authority,
+            round_id,
+        )],
+        Some(&cranker.pubkey()),
+        &[&cranker],
+        recent_blockhash,
+    )
+}
+
+pub fn claim_prize_tx(
+    player: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::claim_prize(
+            &player.pubkey(),
+            pool_authority,
+            round_id,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn refund_tickets_tx(
+    player: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::refund_tickets(
+            &player.pubkey(),
+            pool_authority,
+            round_id,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Sends the claim or refund of the player along with the receipt token account of another wallet.
+pub fn payout_with_token_account_tx(
+    payout: fn(&Pubkey, &Pubkey, u64) -> Instruction,
+    player: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    player_token_account: Pubkey,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut instruction = payout(&player.pubkey(), pool_authority, round_id);
+    instruction.accounts[6].pubkey = player_token_account;
+
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn cancel_round_tx(
+    caller: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::cancel_round(
+            &caller.pubkey(),
+            pool_authority,
+            round_id,
+        )],
+        Some(&caller.pubkey()),
+        &[&caller],
+        recent_blockhash,
+    )
+}
+
+pub fn set_player_limits_tx(
+    player: &Keypair,
+    limits: PlayerLimits,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::set_player_limits(&player.pubkey(), limits)],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn sweep_expired_prizes_tx(
+    payer: &Keypair,
+    pool_authority: &Pubkey,
+    round_ids: &[u64],
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::sweep_expired_prizes(pool_authority, round_ids)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+pub fn close_pool_tx(
+    pool_authority: &Keypair,
+    current_round_id: u64,
+    player_token_accounts: &[Pubkey],
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::close_pool(
+            &pool_authority.pubkey(),
+            current_round_id,
+            player_token_accounts,
+        )],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
+
+pub fn register_ticket_holder_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::register_ticket_holder(
+            pool_authority,
+            &player.pubkey(),
+            round_id,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Transfers receipts between two players of a transferable pool, selecting the round whose tickets move along
+// with them first, along with the accounts of the transfer hook.
+pub fn transfer_receipts_tx(
+    pool_authority: &Pubkey,
+    sender: &Keypair,
+    receiver: &Pubkey,
+    round_id: u64,
+    amount: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let (pool_vault_account, ..) = find_stake_pool_vault_pda(&ID, pool_authority);
+    let (pool_mint_account, ..) = find_stake_pool_mint_pda(&ID, pool_authority);
+    let (round_account, ..) = find_round_pda(&ID, &pool_vault_account, round_id);
+
+    let mut instruction = spl_token_2022::instruction::transfer_checked(
+        &spl_token_2022::id(),
+        &find_player_token_pda_account(&ID, &sender.pubkey()).0,
+        &pool_mint_account,
+        &find_player_token_pda_account(&ID, receiver).0,
+        &sender.pubkey(),
+        &[],
+        amount,
+        0,
+    )
+    .unwrap();
+
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(pool_vault_account, false),
+        AccountMeta::new_readonly(
+            find_transfer_round_pda(&ID, &pool_vault_account, &sender.pubkey()).0,
+            false,
+        ),
+        AccountMeta::new_readonly(round_account, false),
+        AccountMeta::new(
+            find_round_tickets_pda(&ID, &round_account, &sender.pubkey()).0,
+            false,
+        ),
+        AccountMeta::new(
+            find_round_tickets_pda(&ID, &round_account, receiver).0,
+            false,
+        ),
+        AccountMeta::new_readonly(ID, false),
+        AccountMeta::new_readonly(
+            get_extra_account_metas_address(&pool_mint_account, &ID),
+            false,
+        ),
+    ]);
+
+    Transaction::new_signed_with_payer(
+        &[
+            instruction::select_transfer_round(pool_authority, &sender.pubkey(), round_id),
+            instruction,
+        ],
+        Some(&sender.pubkey()),
+        &[&sender],
+        recent_blockhash,
+    )
+}
+
+pub fn subscribe_tx(
+    player: &Keypair,
+    pool_authority: &Pubkey,
+    terms: SubscriptionTerms,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::subscribe(
+            &player.pubkey(),
+            pool_authority,
+            terms,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Buys the subscription tickets of the player in the round, the cranker paying the rent upfront.
+pub fn crank_subscription_tx(
+    cranker: &Keypair,
+    pool_authority: &Pubkey,
+    player: &Pubkey,
+    round_id: u64,
+    tickets_per_round: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    crank_subscriptions_tx(
+        cranker,
+        pool_authority,
+        &[(*player, tickets_per_round)],
+        round_id,
+        recent_blockhash,
+    )
+}
+
+// Cranks the subscriptions of several players in a single transaction.
+pub fn crank_subscriptions_tx(
+    cranker: &Keypair,
+    pool_authority: &Pubkey,
+    subscriptions: &[(Pubkey, u64)],
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instructions: Vec<Instruction> = subscriptions
+        .iter()
+        .map(|(player, tickets_per_round)| {
+            instruction::crank_subscription(
+                &cranker.pubkey(),
+                pool_authority,
+                player,
+                round_id,
+                *tickets_per_round,
+            )
+        })
+        .collect();
+
+    Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&cranker.pubkey()),
+        &[&cranker],
+        recent_blockhash,
+    )
+}
+
+pub fn cancel_subscription_tx(
+    player: &Keypair,
+    pool_authority: &Pubkey,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::cancel_subscription(
+            &player.pubkey(),
+            pool_authority,
+        )],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+pub fn set_allowlist_root_tx(
+    pool_authority: &Keypair,
+    current_round_id: u64,
+    allowlist_root: [u8; 32],
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::set_allowlist_root(
+            &pool_authority.pubkey(),
+            current_round_id,
+            allowlist_root,
+        )],
+        Some(&pool_authority.pubkey()),
+        &[&pool_authority],
+        recent_blockhash,
+    )
+}
+
+pub fn allowlisted_purchase_tx(
+    pool_authority: &Pubkey,
+    player: &Keypair,
+    round_id: u64,
+    allowlist_proof: AllowlistProof,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instruction = instruction::purchase_allowlisted_ticket(
+        pool_authority,
+        &player.pubkey(),
+        round_id,
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 1,
+        },
+        allowlist_proof,
+    );
+
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Registers an attestation signed by `signer` for the player.
+pub fn register_attestation_tx(
+    player: &Keypair,
+    pool_authority: &Pubkey,
+    signer: &Keypair,
+    attestation: Attestation,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let signature_instruction =
+        ed25519_signature_instruction(signer, &borsh::to_vec(&attestation).unwrap());
+    let instruction =
+        instruction::register_attestation(&player.pubkey(), pool_authority, attestation);
+
+    Transaction::new_signed_with_payer(
+        &[signature_instruction, instruction],
+        Some(&player.pubkey()),
+        &[&player],
+        recent_blockhash,
+    )
+}
+
+// Simulates the view instruction of the transaction, decoding its return data.
+pub async fn simulate_view<T: BorshDeserialize>(
+    banks_client: &mut BanksClient,
+    tx: Transaction,
+) -> T {
+    let simulation = banks_client.simulate_transaction(tx).await.unwrap();
+    simulation.result.unwrap().unwrap();
+
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, ID);
+
+    T::try_from_slice(&return_data.data).unwrap()
+}
+
+// The native processor prints the data logged with `sol_log_data` instead of collecting it. These stubs
+// wrap the ones of the program test and collect it through `sol_log`, the lines being logged as
+// `Program log: Program data: ...` instead of `Program data: ...`.
+struct LogDataStubs;
+
+// The stubs of the program test wrapped by `LogDataStubs`
+static PROGRAM_TEST_STUBS: OnceLock<Box<dyn SyscallStubs>> = OnceLock::new();
+
+fn program_test_stubs() -> &'static dyn SyscallStubs {
+    PROGRAM_TEST_STUBS.get().unwrap().as_ref()
+}
+
+// The program test sets its stubs when its first bank is started, a throwaway one is started for them to
+// be wrapped. It runs on its own runtime, the tests calling it from theirs.
+fn install_log_data_stubs() {
+    std::thread::spawn(|| {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(ProgramTest::default().start());
+    })
+    .join()
+    .unwrap();
+
+    let stubs = set_syscall_stubs(Box::new(LogDataStubs));
+    let _ = PROGRAM_TEST_STUBS.set(stubs);
+}
+
+impl SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        program_test_stubs().sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        program_test_stubs().sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        program_test_stubs().sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        program_test_stubs().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_sysvar(
+        &self,
+        sysvar_id_addr: *const u8,
+        var_addr: *mut u8,
+        offset: u64,
+        length: u64,
+    ) -> u64 {
+        program_test_stubs().sol_get_sysvar(sysvar_id_addr, var_addr, offset, length)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_epoch_stake(&self, vote_address: *const u8) -> u64 {
+        program_test_stubs().sol_get_epoch_stake(vote_address)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        program_test_stubs().sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        program_test_stubs().sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+
+        program_test_stubs().sol_log(&format!("Program data: {}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        program_test_stubs().sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        program_test_stubs().sol_get_stack_height()
+    }
+}
+
+// Processes the transaction, which must succeed, and decodes the events the program logged.
+pub async fn process_transaction_events(
+    banks_client: &mut BanksClient,
+    tx: Transaction,
+) -> Vec<LotteryEvent> {
+    let result = banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    result.result.unwrap();
+
+    result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .filter_map(|log| {
+            log.strip_prefix("Program log: ")
+                .unwrap_or(log)
+                .strip_prefix("Program data: ")
+        })
+        .filter_map(|data| {
+            let fields = data
+                .split(' ')
+                .map(|field| STANDARD.decode(field).unwrap())
+                .collect::<Vec<_>>();
+            let fields = fields.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+            LotteryEvent::decode(&fields)
+        })
+        .collect()
+}
+
+fn view_tx(payer: &Keypair, instruction: Instruction, recent_blockhash: Hash) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
+
+pub fn get_pool_info_tx(
+    payer: &Keypair,
+    pool_authority: &Pubkey,
+    current_round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    view_tx(
+        payer,
+        instruction::get_pool_info(pool_authority, current_round_id),
+        recent_blockhash,
+    )
+}
+
+pub fn get_player_info_tx(
+    payer: &Keypair,
+    player: &Pubkey,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    view_tx(
+        payer,
+        instruction::get_player_info(player, pool_authority, round_id),
+        recent_blockhash,
+    )
+}
+
+pub fn quote_ticket_price_tx(
+    payer: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    count: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    view_tx(
+        payer,
+        instruction::quote_ticket_price(pool_authority, round_id, count),
+        recent_blockhash,
+    )
+}
+
+pub fn get_round_result_tx(
+    payer: &Keypair,
+    pool_authority: &Pubkey,
+    round_id: u64,
+    recent_blockhash: Hash,
+) -> Transaction {
+    view_tx(
+        payer,
+        instruction::get_round_result(pool_authority, round_id),
+        recent_blockhash,
+    )
+}
+
+pub fn migrate_account_tx(
+    payer: &Keypair,
+    account: &Pubkey,
+    recent_blockhash: Hash,
+) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[instruction::migrate_account(&payer.pubkey(), account)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    )
+}
@@ -1,24 +1,53 @@
 mod helpers;
-use borsh::BorshDeserialize;
 use helpers::initialize_stake_pool_tx;
 
 use solana_lottery_program::{
+    error::LotteryError,
+    events::{LotteryEvent, EVENT_TAG},
+    instruction,
     processor::{
-        find_player_pda_account, find_player_token_pda_account, find_stake_pool_mint_pda,
-        find_stake_pool_vault_pda,
+        find_draw_session_pda, find_player_pda_account, find_player_token_pda_account,
+        find_referrer_pda, find_round_pda, find_round_tickets_pda, find_savings_pda_account,
+        find_stake_pool_mint_pda, find_stake_pool_vault_pda, find_subscription_pda,
+        find_voucher_nullifier_pda,
     },
     state::{
-        DraftWinner, Instruction as LotoInstruction, PoolStorageData, TicketAccountData,
-        POOL_STORAGE_SIZE,
+        AllowlistProof, Attestation, DraftWinner, DrawSessionData, Instruction as LotoInstruction,
+        PendingReferral, PlayerInfo, PlayerLimits, PoolInfo, PoolMode, PoolStorageData,
+        ProgramAccount, PurchaseIntent, ReferrerData, RoundData, RoundResult, RoundSchedule,
+        RoundStatus, RoundTicketsData, SavingsAccountData, SubscriptionData, SubscriptionTerms,
+        SweepDestination, TicketAccountData, TicketQuote, Voucher,
+        ATTESTED_PLAYER_ACCOUNT_DATA_SIZE, BASIS_POINTS, PLAYER_ACCOUNT_DATA_SIZE,
+        PLAYER_LIMITS_COOLDOWN, POOL_STORAGE_SIZE, TICKET_ACCOUNT_DATA_SIZE,
     },
 };
 use solana_program_test::*;
 use solana_sdk::{
-    instruction::AccountMeta, native_token::LAMPORTS_PER_SOL, program_pack::Pack, rent::Rent,
+    clock::Clock,
+    hash::Hash,
+    instruction::InstructionError,
+    native_token::LAMPORTS_PER_SOL,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
     signer::Signer,
+    slot_hashes::{SlotHashes, MAX_ENTRIES},
 };
 
 use rs_merkle::{algorithms::Sha256, Hasher, MerkleTree};
+use spl_token_2022::{
+    extension::{
+        immutable_owner::ImmutableOwner,
+        metadata_pointer::MetadataPointer,
+        mint_close_authority::MintCloseAuthority,
+        non_transferable::{NonTransferable, NonTransferableAccount},
+        BaseStateWithExtensions, StateWithExtensions,
+    },
+    state::{Account, Mint},
+};
+use spl_token_metadata_interface::state::TokenMetadata;
 
 #[tokio::test]
 async fn initialize_pool() {
@@ -61,12 +90,41 @@ async fn initialize_pool() {
 
     assert_eq!(mint_account.owner, spl_token_2022::ID);
 
-    let unpacked = spl_token_2022::state::Mint::unpack(&mint_account.data).unwrap();
+    let unpacked = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .unwrap()
+        .base;
 
     assert_eq!(unpacked.supply, 0);
     assert_eq!(unpacked.decimals, 0);
 }
 
+#[tokio::test]
+async fn pools_reject_fee_rates_above_the_basis_points() {
+    let (mut client, pool_authority, recent_blockhash, ..) = helpers::setup().await;
+
+    for pool_storage in [
+        PoolStorageData {
+            house_fee_bps: BASIS_POINTS as u16 + 1,
+            ..Default::default()
+        },
+        PoolStorageData {
+            crank_tip_bps: BASIS_POINTS as u16 + 1,
+            ..Default::default()
+        },
+    ] {
+        let tx = helpers::initialize_pool_tx(
+            &solana_lottery_program::ID,
+            &pool_authority,
+            pool_storage,
+            &recent_blockhash,
+        );
+        helpers::assert_lottery_error(
+            client.process_transaction(tx).await,
+            LotteryError::InvalidFeeRate,
+        );
+    }
+}
+
 #[tokio::test]
 async fn ticket_purchase() {
     let (mut client, pool_authority, recent_blockhash, player) = helpers::setup().await;
@@ -89,6 +147,19 @@ async fn ticket_purchase() {
         .await
         .expect("Unable to process data");
 
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 1_000,
+            ..Default::default()
+        },
+        recent_blockhash,
+    );
+    client.process_transaction(tx).await.unwrap();
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
     let ticket_data =
         LotoInstruction::PurchaseTicket(solana_lottery_program::state::TicketAccountData {
             merkle_root: [0; 32],
@@ -108,6 +179,7 @@ async fn ticket_purchase() {
         player_token_pda_address,
         pool_vault_account,
         pool_mint_account,
+        round_account,
         recent_blockhash,
         &ticket_data,
     );
@@ -127,8 +199,7 @@ async fn ticket_purchase() {
         .unwrap();
 
     let unpacked =
-        solana_lottery_program::state::TicketAccountData::try_from_slice(&ticket_account.data)
-            .unwrap();
+        solana_lottery_program::state::TicketAccountData::load(&ticket_account.data).unwrap();
 
     assert_eq!(unpacked.merkle_root, [0; 32]);
 
@@ -138,7 +209,9 @@ async fn ticket_purchase() {
         .unwrap()
         .unwrap();
 
-    let unpacked = spl_token_2022::state::Account::unpack(&player_token_account.data).unwrap();
+    let unpacked = StateWithExtensions::<Account>::unpack(&player_token_account.data)
+        .unwrap()
+        .base;
 
     assert_eq!(unpacked.amount, 1);
 
@@ -157,6 +230,7 @@ async fn ticket_purchase() {
         player_token_pda_address,
         pool_vault_account,
         pool_mint_account,
+        round_account,
         recent_blockhash,
         &ticket_data,
     );
@@ -176,8 +250,7 @@ async fn ticket_purchase() {
         .unwrap();
 
     let unpacked =
-        solana_lottery_program::state::TicketAccountData::try_from_slice(&ticket_account.data)
-            .unwrap();
+        solana_lottery_program::state::TicketAccountData::load(&ticket_account.data).unwrap();
 
     dbg!(&unpacked);
     assert_eq!(unpacked.merkle_root, [1; 32]);
@@ -188,14 +261,19 @@ async fn ticket_purchase() {
         .unwrap()
         .unwrap();
 
-    let unpacked = spl_token_2022::state::Account::unpack(&player_token_account.data).unwrap();
+    let unpacked = StateWithExtensions::<Account>::unpack(&player_token_account.data)
+        .unwrap()
+        .base;
 
     assert_eq!(unpacked.amount, 2);
 }
 
 #[tokio::test]
 async fn can_select_winners_and_widthdraw_prize() {
-    let (mut client, pool_authority, recent_blockhash, player) = helpers::setup().await;
+    let (mut context, player) = helpers::setup_with_context().await;
+    let mut client = context.banks_client.clone();
+    let pool_authority = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
 
     let (pool_mint_account, ..) =
         find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
@@ -215,11 +293,24 @@ async fn can_select_winners_and_widthdraw_prize() {
         .await
         .expect("Unable to process data");
 
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        recent_blockhash,
+    );
+    client.process_transaction(tx).await.unwrap();
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
     let player_pda_account = find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
     let player_token_pda_account =
         find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
 
-    let tickets = vec!["0", "1", "3", "6", "2", "6"];
+    let tickets = ["0", "1", "3", "6", "2", "6"];
     let ticket_hashes: Vec<[u8; 32]> = tickets.iter().map(|t| Sha256::hash(t.as_bytes())).collect();
     let indices_to_prove = vec![4, 5]; // two winning tickets
     let merkle_tree = MerkleTree::<Sha256>::from_leaves(&ticket_hashes);
@@ -233,6 +324,7 @@ async fn can_select_winners_and_widthdraw_prize() {
         player_token_pda_account.0,
         pool_vault_account,
         pool_mint_account,
+        round_account,
         recent_blockhash,
         &LotoInstruction::PurchaseTicket(TicketAccountData {
             merkle_root,
@@ -269,16 +361,29 @@ async fn can_select_winners_and_widthdraw_prize() {
         ticket_indices: indices_to_prove,
     }];
 
-    let winner_accounts = vec![
-        AccountMeta::new(player_pda_account.0, false),
-        AccountMeta::new(player_token_pda_account.0, false),
-        AccountMeta::new(spl_token_2022::ID, false),
-    ];
+    // The round can only be drawn once its sales are closed
+    context.warp_to_slot(50).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    // The winners are paid out of the prize of the round, not out of the rest of the vault
+    let tx = helpers::process_winners_tx(
+        &pool_authority,
+        0,
+        vec![DraftWinner {
+            amount: 5 * LAMPORTS_PER_SOL,
+            ..winners_instruction_data[0].clone()
+        }],
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        client.process_transaction(tx).await,
+        LotteryError::DrawBudgetExceeded,
+    );
 
     let tx = helpers::process_winners_tx(
         &pool_authority,
+        0,
         winners_instruction_data,
-        winner_accounts,
         recent_blockhash,
     );
 
@@ -308,7 +413,7 @@ async fn can_select_winners_and_widthdraw_prize() {
         .unwrap();
 
     let PoolStorageData { draft_count, .. } =
-        PoolStorageData::deserialize(&mut pool_vault_account.data.as_slice()).unwrap();
+        PoolStorageData::load(&pool_vault_account.data).unwrap();
 
     assert_eq!(draft_count, 1);
 
@@ -320,12 +425,13 @@ async fn can_select_winners_and_widthdraw_prize() {
         .unwrap();
 
     let player_token_account_unpacked =
-        spl_token_2022::state::Account::unpack(&player_token_account.data).unwrap();
+        StateWithExtensions::<Account>::unpack(&player_token_account.data)
+            .unwrap()
+            .base;
 
     assert_eq!(player_token_account_unpacked.amount, 0);
 
-    let tx =
-        helpers::process_withdraw_tx(&player, player_pda_account.0, 100_000_000, recent_blockhash);
+    let tx = helpers::process_withdraw_tx(&player, 100_000_000, recent_blockhash);
 
     let tx_cost = client
         .get_fee_for_message(tx.message.clone())
@@ -345,14 +451,7 @@ async fn can_select_winners_and_widthdraw_prize() {
         100_000_000 + (player_total_lamports - tx_cost)
     );
 
-    let tx = helpers::close_account_tx(
-        &player,
-        player_pda_account.0,
-        player_token_pda_account.0,
-        &pool_authority.pubkey(),
-        &pool_mint_account,
-        recent_blockhash,
-    );
+    let tx = helpers::close_account_tx(&player, &pool_authority.pubkey(), recent_blockhash);
     client
         .process_transaction_with_commitment(
             tx,
@@ -370,3 +469,4312 @@ async fn can_select_winners_and_widthdraw_prize() {
 
     assert_eq!(updated_player_pda_token_account, None);
 }
+
+#[tokio::test]
+async fn savings_pool_draws_yield_and_returns_principal() {
+    let (mut context, player, other_player, yield_program, yield_source) =
+        helpers::setup_savings().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            initial_amount: LAMPORTS_PER_SOL,
+            mode: PoolMode::Savings,
+            yield_program,
+            yield_source,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let deposits = [
+        (&player, LAMPORTS_PER_SOL),
+        (&other_player, 3 * LAMPORTS_PER_SOL),
+    ];
+
+    for (depositor, amount) in deposits {
+        let tx = helpers::deposit_principal_tx(
+            &pool_authority.pubkey(),
+            depositor,
+            amount,
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    context.warp_to_slot(100).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    let tx = helpers::harvest_yield_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        &yield_source,
+        &yield_program,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.prize_reserve, helpers::YIELD_PER_HARVEST);
+    assert_eq!(pool_storage.total_principal, 4 * LAMPORTS_PER_SOL);
+
+    // The shares of another player can't be burned to withdraw the principal
+    let tx = helpers::withdraw_principal_with_token_account_tx(
+        &pool_authority.pubkey(),
+        &player,
+        LAMPORTS_PER_SOL,
+        find_player_token_pda_account(&solana_lottery_program::ID, &other_player.pubkey()).0,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidPlayerPdaAccount,
+    );
+
+    // The prize is drawn from the hash of a slot committed ahead of the draw
+    let participants = [player.pubkey(), other_player.pubkey()];
+    let tx = helpers::draw_savings_prize_tx(
+        &other_player,
+        &pool_authority.pubkey(),
+        &participants,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SavingsDrawNotCommitted,
+    );
+
+    let tx = helpers::commit_savings_draw_tx(&pool_authority, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::commit_savings_draw_tx(&pool_authority, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SavingsDrawCommitted,
+    );
+
+    let tx = helpers::draw_savings_prize_tx(
+        &other_player,
+        &pool_authority.pubkey(),
+        &participants,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::DrawTooEarly,
+    );
+
+    // The principal is frozen from the draw slot until the draw
+    context.warp_to_slot(110).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    let tx = helpers::deposit_principal_tx(
+        &pool_authority.pubkey(),
+        &player,
+        LAMPORTS_PER_SOL,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SavingsDrawPending,
+    );
+
+    // Leaving a participant out of the draw is rejected
+    let tx = helpers::draw_savings_prize_tx(
+        &other_player,
+        &pool_authority.pubkey(),
+        &[player.pubkey()],
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::ParticipantsMismatch,
+    );
+
+    // Anyone can crank the draw once the hash of the draw slot is known
+    let tx = helpers::draw_savings_prize_tx(
+        &other_player,
+        &pool_authority.pubkey(),
+        &participants,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.prize_reserve, 0);
+    assert_eq!(pool_storage.draft_count, 1);
+    assert_eq!(
+        pool_storage.total_principal,
+        4 * LAMPORTS_PER_SOL + helpers::YIELD_PER_HARVEST
+    );
+
+    let mut winners = 0;
+
+    for (depositor, amount) in deposits {
+        let (savings_pda_account, ..) = find_savings_pda_account(
+            &solana_lottery_program::ID,
+            &pool_vault_account,
+            &depositor.pubkey(),
+        );
+        let savings_account = context
+            .banks_client
+            .get_account(savings_pda_account)
+            .await
+            .unwrap()
+            .unwrap();
+        let savings_data = SavingsAccountData::load(&savings_account.data).unwrap();
+
+        if savings_data.principal == amount + helpers::YIELD_PER_HARVEST {
+            winners += 1;
+        } else {
+            assert_eq!(savings_data.principal, amount);
+        }
+
+        // The whole principal, prize included, can be withdrawn right away
+        let balance_before = context
+            .banks_client
+            .get_balance(depositor.pubkey())
+            .await
+            .unwrap();
+        let tx = helpers::withdraw_principal_tx(
+            &pool_authority.pubkey(),
+            depositor,
+            savings_data.principal,
+            recent_blockhash,
+        );
+        let tx_cost = context
+            .banks_client
+            .get_fee_for_message(tx.message.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        context.banks_client.process_transaction(tx).await.unwrap();
+
+        assert_eq!(
+            context
+                .banks_client
+                .get_balance(depositor.pubkey())
+                .await
+                .unwrap(),
+            balance_before + savings_data.principal - tx_cost
+        );
+    }
+
+    assert_eq!(winners, 1);
+
+    let mint_account = context
+        .banks_client
+        .get_account(pool_mint_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let unpacked = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .unwrap()
+        .base;
+
+    assert_eq!(unpacked.supply, 0);
+}
+
+#[tokio::test]
+async fn ticket_sales_follow_the_round_window() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (player_pda_address, ..) =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 10,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 50,
+            sales_close_slot: 100,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let purchase_ticket = |total_tickets: u64, recent_blockhash| {
+        helpers::purchase_ticket_tx(
+            &solana_lottery_program::ID,
+            &pool_authority,
+            &player,
+            player_pda_address,
+            player_token_pda_address,
+            pool_vault_account,
+            pool_mint_account,
+            round_account,
+            recent_blockhash,
+            &LotoInstruction::PurchaseTicket(TicketAccountData {
+                merkle_root: [0; 32],
+                total_tickets,
+            }),
+        )
+    };
+
+    // Before the sales open
+    let tx = purchase_ticket(1, context.last_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SalesClosed,
+    );
+
+    context.warp_to_slot(50).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = purchase_ticket(1, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(99).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = purchase_ticket(2, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The close slot is excluded from the sales
+    context.warp_to_slot(100).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = purchase_ticket(3, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SalesClosed,
+    );
+
+    // The draw waits for the buffer after the close
+    let tx = helpers::process_winners_tx(&pool_authority, 0, vec![], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::DrawTooEarly,
+    );
+
+    context.warp_to_slot(110).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::process_winners_tx(&pool_authority, 0, vec![], recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.status, RoundStatus::Settled);
+
+    // The next round can only be bought once it's opened
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        1,
+        RoundSchedule {
+            sales_open_slot: 110,
+            sales_close_slot: 200,
+            ..Default::default()
+        },
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    let tx = purchase_ticket(3, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidRound,
+    );
+}
+
+#[tokio::test]
+async fn anyone_can_settle_a_round_and_the_winner_claims() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let other_player = Keypair::new();
+    let cranker = Keypair::new();
+
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    for account in [other_player.pubkey(), cranker.pubkey()] {
+        let tx = helpers::fund_account_tx(
+            &pool_authority,
+            &account,
+            10 * LAMPORTS_PER_SOL,
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            house_fee_bps: 1_000,
+            crank_tip_bps: 1_000,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // Two tickets for the player and one for the other player
+    for (buyer, total_tickets) in [(&player, 1), (&other_player, 1), (&player, 2)] {
+        let (player_pda_address, ..) =
+            find_player_pda_account(&solana_lottery_program::ID, &buyer.pubkey());
+        let (player_token_pda_address, ..) =
+            find_player_token_pda_account(&solana_lottery_program::ID, &buyer.pubkey());
+
+        let tx = helpers::purchase_ticket_tx(
+            &solana_lottery_program::ID,
+            &pool_authority,
+            buyer,
+            player_pda_address,
+            player_token_pda_address,
+            pool_vault_account,
+            pool_mint_account,
+            round_account,
+            context.last_blockhash,
+            &LotoInstruction::PurchaseTicket(TicketAccountData {
+                merkle_root: [0; 32],
+                total_tickets,
+            }),
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    context.warp_to_slot(50).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&cranker, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::DrawTooEarly,
+    );
+
+    // The hash of the draw slot is only known once the draw slot has passed
+    context.warp_to_slot(55).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&cranker, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::RandomnessNotAvailable,
+    );
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let cranker_balance = context
+        .banks_client
+        .get_balance(cranker.pubkey())
+        .await
+        .unwrap();
+    let tx = helpers::settle_round_tx(&cranker, &pool_authority.pubkey(), 0, recent_blockhash);
+    let tx_cost = context
+        .banks_client
+        .get_fee_for_message(tx.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The house keeps 10% of the 3 SOL raised and the cranker gets 10% of that
+    let tip = 3 * LAMPORTS_PER_SOL / 100;
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(cranker.pubkey())
+            .await
+            .unwrap(),
+        cranker_balance + tip - tx_cost
+    );
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.status, RoundStatus::Settled);
+    assert_eq!(round_data.tickets_sold, 3);
+    assert_eq!(round_data.prize, 3 * LAMPORTS_PER_SOL * 9 / 10);
+    assert!(round_data.winning_ticket < 3);
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, round_data.prize);
+    assert_eq!(pool_storage.draft_count, 1);
+
+    // Tickets 0 and 2 belong to the player, ticket 1 to the other player
+    let (winner, loser) = if round_data.winning_ticket == 1 {
+        (&other_player, &player)
+    } else {
+        (&player, &other_player)
+    };
+
+    let tx = helpers::claim_prize_tx(loser, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::NotAWinner,
+    );
+
+    // The receipt burned by the claim must be the winner's own
+    let tx = helpers::payout_with_token_account_tx(
+        instruction::claim_prize,
+        winner,
+        &pool_authority.pubkey(),
+        0,
+        find_player_token_pda_account(&solana_lottery_program::ID, &loser.pubkey()).0,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidPlayerPdaAccount,
+    );
+
+    let winner_balance = context
+        .banks_client
+        .get_balance(winner.pubkey())
+        .await
+        .unwrap();
+    let tx = helpers::claim_prize_tx(winner, &pool_authority.pubkey(), 0, recent_blockhash);
+    let tx_cost = context
+        .banks_client
+        .get_fee_for_message(tx.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(winner.pubkey())
+            .await
+            .unwrap(),
+        winner_balance + round_data.prize - tx_cost
+    );
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::claim_prize_tx(winner, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::PrizeAlreadyClaimed,
+    );
+}
+
+#[tokio::test]
+async fn cancelled_rounds_refund_the_players() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let anyone = Keypair::new();
+
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (player_pda_address, ..) =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &anyone.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            cancel_timeout_slots: 20,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    for total_tickets in [1, 2] {
+        let tx = helpers::purchase_ticket_tx(
+            &solana_lottery_program::ID,
+            &pool_authority,
+            &player,
+            player_pda_address,
+            player_token_pda_address,
+            pool_vault_account,
+            pool_mint_account,
+            round_account,
+            context.last_blockhash,
+            &LotoInstruction::PurchaseTicket(TicketAccountData {
+                merkle_root: [0; 32],
+                total_tickets,
+            }),
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    // Refunds are only possible once the round is cancelled
+    let tx =
+        helpers::refund_tickets_tx(&player, &pool_authority.pubkey(), 0, context.last_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::RoundNotCancelled,
+    );
+
+    // Anyone but the authority has to wait for the timeout after the draw slot
+    context.warp_to_slot(74).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::cancel_round_tx(&anyone, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::CancelTooEarly,
+    );
+
+    context.warp_to_slot(75).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::cancel_round_tx(&anyone, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(pool_storage.draft_count, 1);
+
+    let player_balance = context
+        .banks_client
+        .get_balance(player.pubkey())
+        .await
+        .unwrap();
+    let tx = helpers::refund_tickets_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    let tx_cost = context
+        .banks_client
+        .get_fee_for_message(tx.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(player.pubkey())
+            .await
+            .unwrap(),
+        player_balance + 2 * LAMPORTS_PER_SOL - tx_cost
+    );
+
+    let player_token_account = context
+        .banks_client
+        .get_account(player_token_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let unpacked = StateWithExtensions::<Account>::unpack(&player_token_account.data)
+        .unwrap()
+        .base;
+
+    assert_eq!(unpacked.amount, 0);
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::refund_tickets_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::AlreadyRefunded,
+    );
+
+    // The authority can cancel the next round right away
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        1,
+        RoundSchedule {
+            sales_open_slot: 75,
+            sales_close_slot: 150,
+            ..Default::default()
+        },
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    let tx = helpers::cancel_round_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        1,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 1);
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        RoundData::load(&round.data).unwrap().status,
+        RoundStatus::Cancelled
+    );
+}
+
+#[tokio::test]
+async fn rounds_below_minimum_participation_are_cancelled() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (player_pda_address, ..) =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            min_tickets_sold: 3,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    for total_tickets in [1, 2] {
+        let tx = helpers::purchase_ticket_tx(
+            &solana_lottery_program::ID,
+            &pool_authority,
+            &player,
+            player_pda_address,
+            player_token_pda_address,
+            pool_vault_account,
+            pool_mint_account,
+            round_account,
+            context.last_blockhash,
+            &LotoInstruction::PurchaseTicket(TicketAccountData {
+                merkle_root: [0; 32],
+                total_tickets,
+            }),
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    // The round can't be drawn, it's cancelled by the settlement as soon as the sales close
+    context.warp_to_slot(50).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::process_winners_tx(&pool_authority, 0, vec![], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::DrawTooEarly,
+    );
+
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.status, RoundStatus::Cancelled);
+    assert_eq!(round_data.tickets_sold, 2);
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, 2 * LAMPORTS_PER_SOL);
+
+    let tx = helpers::refund_tickets_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, 0);
+}
+
+#[tokio::test]
+async fn ticket_purchases_respect_the_pool_caps() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let other_player = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &other_player.pubkey(),
+        10 * LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            max_tickets_per_player: 3,
+            max_tickets_per_round: 4,
+            max_tickets_per_transaction: 2,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 1000,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2, 3],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::TransactionTicketCapExceeded,
+    );
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[3],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[4],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::PlayerTicketCapExceeded,
+    );
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &other_player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &other_player,
+        0,
+        &[2],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::RoundTicketCapExceeded,
+    );
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.tickets_sold, 4);
+
+    // The tickets bought in a single transaction are merged into one range
+    let (round_tickets_account, ..) = find_round_tickets_pda(
+        &solana_lottery_program::ID,
+        &round_account,
+        &player.pubkey(),
+    );
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_tickets_data = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+    assert_eq!(round_tickets_data.tickets, 3);
+    assert_eq!(round_tickets_data.ranges.len(), 1);
+}
+
+#[tokio::test]
+async fn player_limits_restrict_purchases() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 1000,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The player account predates the limits
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let daily_cap = PlayerLimits {
+        spending_cap: 2 * LAMPORTS_PER_SOL,
+        spending_period: 24 * 60 * 60,
+        self_excluded_until: 0,
+    };
+    let tx = helpers::set_player_limits_tx(&player, daily_cap, context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[2, 3],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[4],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SpendingLimitExceeded,
+    );
+
+    // Removing the cap only applies after the cooldown
+    let tx =
+        helpers::set_player_limits_tx(&player, PlayerLimits::default(), context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[5],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SpendingLimitExceeded,
+    );
+
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += PLAYER_LIMITS_COOLDOWN;
+    context.set_sysvar(&clock);
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[6, 7, 8],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // Self-exclusion applies immediately and can't be shortened before the cooldown
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let self_exclusion = PlayerLimits {
+        self_excluded_until: clock.unix_timestamp + 30 * 24 * 60 * 60,
+        ..Default::default()
+    };
+    let tx = helpers::set_player_limits_tx(&player, self_exclusion, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::set_player_limits_tx(&player, PlayerLimits::default(), recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx =
+        helpers::purchase_tickets_tx(&pool_authority.pubkey(), &player, 0, &[9], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::PlayerSelfExcluded,
+    );
+
+    // Closing the player account doesn't lift the self-exclusion
+    let tx = helpers::close_account_tx(&player, &pool_authority.pubkey(), recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::PlayerLimitsActive,
+    );
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[10],
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::PlayerSelfExcluded,
+    );
+
+    let round_account = find_round_pda(
+        &solana_lottery_program::ID,
+        &find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey()).0,
+        0,
+    )
+    .0;
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.tickets_sold, 6);
+}
+
+#[tokio::test]
+async fn rounds_are_cancelled_once_their_draw_slot_hash_expired() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The draw slot hash is no longer held by the full SlotHashes sysvar, so no later hash stands in for it
+    context.warp_to_slot(700).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let slot_hashes: Vec<(u64, Hash)> = (700 - MAX_ENTRIES as u64..700)
+        .rev()
+        .map(|slot| (slot, Hash::new_unique()))
+        .collect();
+    context.set_sysvar(&SlotHashes::new(&slot_hashes));
+
+    let tx = helpers::settle_round_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        0,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        RoundData::load(&round.data).unwrap().status,
+        RoundStatus::Cancelled
+    );
+}
+
+#[tokio::test]
+async fn expired_prizes_are_swept_into_the_jackpot() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            house_fee_bps: 1_000,
+            claim_period_slots: 10,
+            sweep_destination: SweepDestination::Jackpot,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx =
+        helpers::sweep_expired_prizes_tx(&player, &pool_authority.pubkey(), &[0], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::PrizeNotSweepable,
+    );
+
+    // The player never claims its prize before the deadline
+    context.warp_to_slot(70).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::ClaimExpired,
+    );
+
+    let tx =
+        helpers::sweep_expired_prizes_tx(&player, &pool_authority.pubkey(), &[0], recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    let first_prize = LAMPORTS_PER_SOL * 9 / 10;
+    assert_eq!(pool_storage.liabilities, 0);
+    assert_eq!(pool_storage.jackpot, first_prize);
+    assert_eq!(pool_storage.house_balance, LAMPORTS_PER_SOL / 10);
+
+    // The jackpot is added to the prize of the next round
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        1,
+        RoundSchedule {
+            sales_open_slot: 70,
+            sales_close_slot: 100,
+            ..Default::default()
+        },
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx =
+        helpers::purchase_tickets_tx(&pool_authority.pubkey(), &player, 1, &[2], recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(106).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 1, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 1);
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.prize, 2 * first_prize);
+
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 1, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, 0);
+    assert_eq!(pool_storage.jackpot, 0);
+}
+
+#[tokio::test]
+async fn pools_are_closed_once_wound_down() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            close_grace_slots: 20,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::close_pool_tx(&pool_authority, 0, &[], context.last_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::RoundStillOpen,
+    );
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The prize isn't claimed yet
+    let tx = helpers::close_pool_tx(&pool_authority, 1, &[], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::OutstandingLiabilities,
+    );
+
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The receipt of the losing ticket is still outstanding during the grace period
+    let tx = helpers::close_pool_tx(
+        &pool_authority,
+        1,
+        &[player_token_pda_address],
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::ReceiptsOutstanding,
+    );
+
+    context.warp_to_slot(80).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::close_pool_tx(&pool_authority, 1, &[], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::ReceiptsOutstanding,
+    );
+
+    let vault_balance = context
+        .banks_client
+        .get_balance(pool_vault_account)
+        .await
+        .unwrap();
+    let mint_balance = context
+        .banks_client
+        .get_balance(pool_mint_account)
+        .await
+        .unwrap();
+    let authority_balance = context
+        .banks_client
+        .get_balance(pool_authority.pubkey())
+        .await
+        .unwrap();
+
+    let tx = helpers::close_pool_tx(
+        &pool_authority,
+        1,
+        &[player_token_pda_address],
+        recent_blockhash,
+    );
+    let tx_cost = context
+        .banks_client
+        .get_fee_for_message(tx.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(pool_authority.pubkey())
+            .await
+            .unwrap(),
+        authority_balance + vault_balance + mint_balance - tx_cost
+    );
+    assert_eq!(
+        context
+            .banks_client
+            .get_account(pool_vault_account)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        context
+            .banks_client
+            .get_account(pool_mint_account)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn receipts_are_non_transferable_and_carry_the_pool_metadata() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+
+    let mut name = [0; 32];
+    name[..12].copy_from_slice(b"Weekly Draw ");
+    let mut symbol = [0; 10];
+    symbol[..4].copy_from_slice(b"LUCK");
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            name,
+            symbol,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let mint_account = context
+        .banks_client
+        .get_account(pool_mint_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+
+    assert!(mint.get_extension::<NonTransferable>().is_ok());
+    assert_eq!(
+        Option::<Pubkey>::from(
+            mint.get_extension::<MintCloseAuthority>()
+                .unwrap()
+                .close_authority
+        ),
+        Some(pool_mint_account)
+    );
+    assert_eq!(
+        Option::<Pubkey>::from(
+            mint.get_extension::<MetadataPointer>()
+                .unwrap()
+                .metadata_address
+        ),
+        Some(pool_mint_account)
+    );
+
+    let metadata = mint.get_variable_len_extension::<TokenMetadata>().unwrap();
+
+    assert_eq!(metadata.name, "Weekly Draw ");
+    assert_eq!(metadata.symbol, "LUCK");
+    assert_eq!(
+        metadata.additional_metadata,
+        vec![("round".to_string(), "0".to_string())]
+    );
+
+    let player_token_account = context
+        .banks_client
+        .get_account(player_token_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account = StateWithExtensions::<Account>::unpack(&player_token_account.data).unwrap();
+
+    assert_eq!(token_account.base.amount, 1);
+    assert!(token_account.get_extension::<ImmutableOwner>().is_ok());
+    assert!(token_account
+        .get_extension::<NonTransferableAccount>()
+        .is_ok());
+}
+
+#[tokio::test]
+async fn transferred_receipts_move_the_ticket_entitlement() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let receiver = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            transferable_tickets: true,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &receiver.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The receiver can't get receipts before holding tickets in the round
+    let tx = helpers::transfer_receipts_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &receiver.pubkey(),
+        0,
+        1,
+        context.last_blockhash,
+    );
+    assert!(context.banks_client.process_transaction(tx).await.is_err());
+
+    let tx = helpers::register_ticket_holder_tx(
+        &pool_authority.pubkey(),
+        &receiver,
+        0,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::transfer_receipts_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &receiver.pubkey(),
+        0,
+        1,
+        blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The last ticket of the sender follows the receipt
+    for (owner, ticket) in [(player.pubkey(), 0), (receiver.pubkey(), 1)] {
+        let (token_address, ..) =
+            find_player_token_pda_account(&solana_lottery_program::ID, &owner);
+        let token_account = context
+            .banks_client
+            .get_account(token_address)
+            .await
+            .unwrap()
+            .unwrap();
+        let token_account = StateWithExtensions::<Account>::unpack(&token_account.data).unwrap();
+
+        assert_eq!(token_account.base.amount, 1);
+        assert_eq!(token_account.base.owner, owner);
+
+        let (round_tickets_address, ..) =
+            find_round_tickets_pda(&solana_lottery_program::ID, &round_account, &owner);
+        let round_tickets = context
+            .banks_client
+            .get_account(round_tickets_address)
+            .await
+            .unwrap()
+            .unwrap();
+        let round_tickets = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+        assert_eq!(round_tickets.tickets, 1);
+        assert!(round_tickets.holds_ticket(ticket));
+    }
+}
+
+#[tokio::test]
+async fn settled_prizes_follow_the_transferred_receipt() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let receiver = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            transferable_tickets: true,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &receiver.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.status, RoundStatus::Settled);
+
+    // The round is over, the receiver registers for it all the same
+    let tx = helpers::register_ticket_holder_tx(
+        &pool_authority.pubkey(),
+        &receiver,
+        0,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::transfer_receipts_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &receiver.pubkey(),
+        0,
+        1,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The winning ticket moves first, whichever of the two it is
+    let (round_tickets_address, ..) = find_round_tickets_pda(
+        &solana_lottery_program::ID,
+        &round_account,
+        &receiver.pubkey(),
+    );
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_tickets = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+    assert_eq!(round_tickets.tickets, 1);
+    assert!(round_tickets.holds_ticket(round_data.winning_ticket));
+
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::NotAWinner,
+    );
+
+    let receiver_balance = context
+        .banks_client
+        .get_balance(receiver.pubkey())
+        .await
+        .unwrap();
+    let tx = helpers::claim_prize_tx(&receiver, &pool_authority.pubkey(), 0, recent_blockhash);
+    let tx_cost = context
+        .banks_client
+        .get_fee_for_message(tx.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(receiver.pubkey())
+            .await
+            .unwrap(),
+        receiver_balance + round_data.prize - tx_cost
+    );
+}
+
+#[tokio::test]
+async fn tickets_can_be_gifted_to_another_wallet() {
+    let (mut context, payer) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    // The beneficiary holds no SOL
+    let beneficiary = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let payer_balance = context
+        .banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .unwrap();
+
+    let tx = helpers::gift_ticket_tx(
+        &pool_authority.pubkey(),
+        &payer,
+        &beneficiary.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert!(
+        context
+            .banks_client
+            .get_balance(payer.pubkey())
+            .await
+            .unwrap()
+            < payer_balance - LAMPORTS_PER_SOL
+    );
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(beneficiary.pubkey())
+            .await
+            .unwrap(),
+        0
+    );
+
+    let (round_tickets_address, ..) = find_round_tickets_pda(
+        &solana_lottery_program::ID,
+        &round_account,
+        &beneficiary.pubkey(),
+    );
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_tickets = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+    assert_eq!(round_tickets.owner, beneficiary.pubkey());
+    assert_eq!(round_tickets.tickets, 1);
+
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &beneficiary.pubkey());
+    let token_account = context
+        .banks_client
+        .get_account(player_token_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account = StateWithExtensions::<Account>::unpack(&token_account.data).unwrap();
+
+    assert_eq!(token_account.base.amount, 1);
+
+    // The payer itself holds no tickets
+    let (payer_tickets_address, ..) =
+        find_round_tickets_pda(&solana_lottery_program::ID, &round_account, &payer.pubkey());
+    assert!(context
+        .banks_client
+        .get_account(payer_tickets_address)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(context
+        .banks_client
+        .get_account(find_player_pda_account(&solana_lottery_program::ID, &beneficiary.pubkey()).0)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn sponsored_purchases_follow_the_signed_intent() {
+    let (mut context, relayer) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    // The player holds no SOL, the relayer pays for everything
+    let player = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (player_pda_address, ..) =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let intent = PurchaseIntent {
+        player: player.pubkey(),
+        pool_vault: pool_vault_account,
+        round_id: 0,
+        tickets: 2,
+        max_ticket_price: LAMPORTS_PER_SOL,
+        expires_at: now + 60,
+        nonce: 0,
+    };
+
+    // The intent must be signed by the player
+    let tx = helpers::sponsored_purchase_tx(
+        &pool_authority.pubkey(),
+        &relayer,
+        &relayer,
+        intent.clone(),
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidIntentSignature,
+    );
+
+    // The player doesn't agree to a higher price
+    let tx = helpers::sponsored_purchase_tx(
+        &pool_authority.pubkey(),
+        &relayer,
+        &player,
+        PurchaseIntent {
+            max_ticket_price: LAMPORTS_PER_SOL - 1,
+            ..intent.clone()
+        },
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::IntentMismatch,
+    );
+
+    let tx = helpers::sponsored_purchase_tx(
+        &pool_authority.pubkey(),
+        &relayer,
+        &player,
+        intent.clone(),
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (round_tickets_address, ..) = find_round_tickets_pda(
+        &solana_lottery_program::ID,
+        &round_account,
+        &player.pubkey(),
+    );
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_tickets = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+    assert_eq!(round_tickets.tickets, 2);
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(player.pubkey())
+            .await
+            .unwrap(),
+        0
+    );
+
+    let player_pda = context
+        .banks_client
+        .get_account(player_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let nonce_offset = PLAYER_ACCOUNT_DATA_SIZE as usize;
+    assert_eq!(
+        u64::from_le_bytes(player_pda.data[nonce_offset..].try_into().unwrap()),
+        1
+    );
+
+    // The intent can't be replayed
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::sponsored_purchase_tx(
+        &pool_authority.pubkey(),
+        &relayer,
+        &player,
+        intent.clone(),
+        blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidIntentNonce,
+    );
+
+    let tx = helpers::sponsored_purchase_tx(
+        &pool_authority.pubkey(),
+        &relayer,
+        &player,
+        PurchaseIntent {
+            expires_at: now - 1,
+            nonce: 1,
+            ..intent.clone()
+        },
+        blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::IntentExpired,
+    );
+
+    // Closing the player account would reset the nonce, replaying the intent against a new account
+    let tx = helpers::fund_account_tx(&relayer, &player.pubkey(), LAMPORTS_PER_SOL, blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::close_account_tx(&player, &pool_authority.pubkey(), blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::IntentNonceInUse,
+    );
+
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::sponsored_purchase_tx(
+        &pool_authority.pubkey(),
+        &relayer,
+        &player,
+        intent,
+        blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidIntentNonce,
+    );
+}
+
+#[tokio::test]
+async fn vouchers_credit_free_tickets_from_the_house_balance() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let voucher_signer = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 1);
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            house_fee_bps: 5_000,
+            voucher_signer: voucher_signer.pubkey(),
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The house earns a ticket price out of the first round
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        1,
+        RoundSchedule {
+            sales_open_slot: 56,
+            sales_close_slot: 150,
+            ..Default::default()
+        },
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let voucher = Voucher {
+        pool_vault: pool_vault_account,
+        beneficiary: Some(player.pubkey()),
+        tickets: 1,
+        expires_at: now + 60,
+        voucher_id: 7,
+    };
+
+    // Only the pool voucher signer issues vouchers
+    let tx = helpers::redeem_voucher_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &player,
+        1,
+        voucher.clone(),
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidVoucher,
+    );
+
+    // The voucher is bound to its beneficiary
+    let tx = helpers::redeem_voucher_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &voucher_signer,
+        1,
+        Voucher {
+            beneficiary: Some(pool_authority.pubkey()),
+            ..voucher.clone()
+        },
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidVoucher,
+    );
+
+    let player_balance = context
+        .banks_client
+        .get_balance(player.pubkey())
+        .await
+        .unwrap();
+
+    // Lamports sent to the nullifier address ahead of time don't block the redemption
+    let (voucher_nullifier_account, _) =
+        find_voucher_nullifier_pda(&solana_lottery_program::ID, &pool_vault_account, 7);
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &voucher_nullifier_account,
+        rent.minimum_balance(0),
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::redeem_voucher_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &voucher_signer,
+        1,
+        voucher.clone(),
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The player only paid the fees and the rent of the nullifier and round tickets
+    assert!(
+        player_balance
+            - context
+                .banks_client
+                .get_balance(player.pubkey())
+                .await
+                .unwrap()
+            < LAMPORTS_PER_SOL / 100
+    );
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    assert_eq!(round_data.tickets_sold, 1);
+    assert_eq!(round_data.lamports_raised, LAMPORTS_PER_SOL);
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.house_balance, 0);
+
+    // Each voucher is redeemed once
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::redeem_voucher_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &voucher_signer,
+        1,
+        voucher,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::VoucherAlreadyRedeemed,
+    );
+
+    // A cancelled round refunds the tickets the player paid, and returns the voucher tickets to the house
+    let tx =
+        helpers::purchase_tickets_tx(&pool_authority.pubkey(), &player, 1, &[3], recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::cancel_round_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        1,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.house_balance, LAMPORTS_PER_SOL);
+    // The unclaimed prize of the first round along with the refund of the paid ticket
+    assert_eq!(pool_storage.liabilities, 2 * LAMPORTS_PER_SOL);
+
+    let player_balance = context
+        .banks_client
+        .get_balance(player.pubkey())
+        .await
+        .unwrap();
+    let tx = helpers::refund_tickets_tx(&player, &pool_authority.pubkey(), 1, recent_blockhash);
+    let tx_cost = context
+        .banks_client
+        .get_fee_for_message(tx.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(player.pubkey())
+            .await
+            .unwrap(),
+        player_balance + LAMPORTS_PER_SOL - tx_cost
+    );
+}
+
+#[tokio::test]
+async fn referrers_earn_a_share_of_the_house_fee() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let referrer = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (referrer_pda_address, ..) = find_referrer_pda(
+        &solana_lottery_program::ID,
+        &pool_vault_account,
+        &referrer.pubkey(),
+    );
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            house_fee_bps: 1_000,
+            referral_share_bps: 5_000,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &referrer.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // Players can't refer themselves
+    let tx = helpers::referred_purchase_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &player.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SelfReferral,
+    );
+
+    for _ in 0..2 {
+        let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let tx = helpers::referred_purchase_tx(
+            &pool_authority.pubkey(),
+            &player,
+            &referrer.pubkey(),
+            0,
+            recent_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let referrer_pda = context
+        .banks_client
+        .get_account(referrer_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let referrer_data = ReferrerData::load(&referrer_pda.data).unwrap();
+
+    // Half of the 10% house fee of each ticket
+    let reward = LAMPORTS_PER_SOL / 20;
+    assert_eq!(referrer_data.referrer, referrer.pubkey());
+    assert_eq!(referrer_data.referrals, 2);
+    assert_eq!(referrer_data.tickets_referred, 2);
+    assert_eq!(referrer_data.lamports_referred, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(referrer_data.earned, 2 * reward);
+    assert_eq!(referrer_data.balance, 0);
+    assert_eq!(
+        referrer_data.pending,
+        vec![PendingReferral {
+            round_id: 0,
+            reward: 2 * reward
+        }]
+    );
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let referrer_balance = context
+        .banks_client
+        .get_balance(referrer.pubkey())
+        .await
+        .unwrap();
+
+    let tx = helpers::withdraw_referral_rewards_tx(
+        &referrer,
+        &pool_authority.pubkey(),
+        &[0],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(referrer.pubkey())
+            .await
+            .unwrap(),
+        referrer_balance + 2 * reward - 5_000
+    );
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    // The house keeps the rest of its fee, the prize is the only liability left
+    assert_eq!(
+        pool_storage.house_balance,
+        LAMPORTS_PER_SOL / 5 - 2 * reward
+    );
+    assert_eq!(pool_storage.liabilities, 2 * LAMPORTS_PER_SOL * 9 / 10);
+}
+
+#[tokio::test]
+async fn referral_rewards_of_cancelled_rounds_are_void() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let referrer = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (referrer_pda_address, ..) = find_referrer_pda(
+        &solana_lottery_program::ID,
+        &pool_vault_account,
+        &referrer.pubkey(),
+    );
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            house_fee_bps: 1_000,
+            referral_share_bps: 5_000,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The round is cancelled as it misses its minimum participation
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            min_tickets_sold: 10,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &referrer.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::referred_purchase_tx(
+        &pool_authority.pubkey(),
+        &player,
+        &referrer.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(50).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::refund_tickets_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let referrer_balance = context
+        .banks_client
+        .get_balance(referrer.pubkey())
+        .await
+        .unwrap();
+
+    let tx = helpers::withdraw_referral_rewards_tx(
+        &referrer,
+        &pool_authority.pubkey(),
+        &[0],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The referrer only pays the transaction fee
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(referrer.pubkey())
+            .await
+            .unwrap(),
+        referrer_balance - 5_000
+    );
+
+    let referrer_pda = context
+        .banks_client
+        .get_account(referrer_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let referrer_data = ReferrerData::load(&referrer_pda.data).unwrap();
+
+    assert_eq!(referrer_data.referrals, 1);
+    assert_eq!(referrer_data.earned, 0);
+    assert_eq!(referrer_data.balance, 0);
+    assert!(referrer_data.pending.is_empty());
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    // The refund was the only liability of the round
+    assert_eq!(pool_storage.liabilities, 0);
+    assert_eq!(pool_storage.house_balance, 0);
+}
+
+#[tokio::test]
+async fn subscriptions_buy_tickets_every_round_out_of_their_escrow() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let cranker = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (subscription_address, ..) = find_subscription_pda(
+        &solana_lottery_program::ID,
+        &pool_vault_account,
+        &player.pubkey(),
+    );
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &cranker.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::subscribe_tx(
+        &player,
+        &pool_authority.pubkey(),
+        SubscriptionTerms {
+            tickets_per_round: 2,
+            deposit: 5 * LAMPORTS_PER_SOL,
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let cranker_balance = context
+        .banks_client
+        .get_balance(cranker.pubkey())
+        .await
+        .unwrap();
+
+    let tx = helpers::crank_subscription_tx(
+        &cranker,
+        &pool_authority.pubkey(),
+        &player.pubkey(),
+        0,
+        2,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The escrow paid back the rent, the cranker only paid the fee
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(cranker.pubkey())
+            .await
+            .unwrap(),
+        cranker_balance - 5_000
+    );
+
+    let (round_tickets_address, ..) = find_round_tickets_pda(
+        &solana_lottery_program::ID,
+        &round_account,
+        &player.pubkey(),
+    );
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_tickets = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+    assert_eq!(round_tickets.tickets, 2);
+
+    let subscription = context
+        .banks_client
+        .get_account(subscription_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let subscription_lamports = subscription.lamports;
+    let subscription = SubscriptionData::load(&subscription.data).unwrap();
+
+    assert_eq!(subscription.last_round_id, Some(0));
+    assert_eq!(subscription.rounds_played, 1);
+    assert!(subscription.escrow < 3 * LAMPORTS_PER_SOL);
+    assert!(subscription.escrow > 2 * LAMPORTS_PER_SOL);
+
+    // The tickets are bought once per round
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::crank_subscription_tx(
+        &cranker,
+        &pool_authority.pubkey(),
+        &player.pubkey(),
+        0,
+        2,
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::SubscriptionAlreadyPlayed,
+    );
+
+    let player_balance = context
+        .banks_client
+        .get_balance(player.pubkey())
+        .await
+        .unwrap();
+
+    let tx = helpers::cancel_subscription_tx(&player, &pool_authority.pubkey(), recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(player.pubkey())
+            .await
+            .unwrap(),
+        player_balance + subscription_lamports - 5_000
+    );
+    assert!(context
+        .banks_client
+        .get_account(subscription_address)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn subscription_cranks_count_against_the_tickets_per_transaction() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let other_player = Keypair::new();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            max_tickets_per_transaction: 3,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &other_player.pubkey(),
+        10 * LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    for subscriber in [&player, &other_player] {
+        let tx = helpers::subscribe_tx(
+            subscriber,
+            &pool_authority.pubkey(),
+            SubscriptionTerms {
+                tickets_per_round: 2,
+                deposit: 5 * LAMPORTS_PER_SOL,
+            },
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    // The crank declares the tickets of the subscription
+    let tx = helpers::crank_subscription_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        &player.pubkey(),
+        0,
+        1,
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidSubscription,
+    );
+
+    // Two cranks of 2 tickets exceed the 3 tickets per transaction
+    let tx = helpers::crank_subscriptions_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        &[(player.pubkey(), 2), (other_player.pubkey(), 2)],
+        0,
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::TransactionTicketCapExceeded,
+    );
+
+    for subscriber in [&player, &other_player] {
+        let tx = helpers::crank_subscription_tx(
+            &pool_authority,
+            &pool_authority.pubkey(),
+            &subscriber.pubkey(),
+            0,
+            2,
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn allowlisted_pools_only_sell_to_the_listed_wallets() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let outsider = Keypair::new();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+
+    let leaves: Vec<[u8; 32]> = [player.pubkey(), Pubkey::new_unique(), Pubkey::new_unique()]
+        .iter()
+        .map(|wallet| Sha256::hash(wallet.as_ref()))
+        .collect();
+    let allowlist = MerkleTree::<Sha256>::from_leaves(&leaves);
+    let allowlist_root = allowlist.root().unwrap();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx =
+        helpers::set_allowlist_root_tx(&pool_authority, 0, allowlist_root, context.last_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+    assert_eq!(pool_storage.allowlist_root, allowlist_root);
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The allowlist can't be rotated while the round is open
+    let tx = helpers::set_allowlist_root_tx(&pool_authority, 0, [0; 32], context.last_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::RoundStillOpen,
+    );
+
+    // The tickets aren't sold without a proof
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::NotAllowlisted,
+    );
+
+    let player_proof = AllowlistProof {
+        proof: allowlist.proof(&[0]).to_bytes(),
+        leaf_index: 0,
+        total_leaves: leaves.len() as u64,
+    };
+
+    // Nor to a wallet presenting the proof of another one
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &outsider.pubkey(),
+        LAMPORTS_PER_SOL * 2,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::allowlisted_purchase_tx(
+        &pool_authority.pubkey(),
+        &outsider,
+        0,
+        player_proof.clone(),
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::NotAllowlisted,
+    );
+
+    let tx = helpers::allowlisted_purchase_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        player_proof,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+    assert_eq!(round_data.tickets_sold, 1);
+}
+
+#[tokio::test]
+async fn attested_pools_require_a_valid_attestation_to_play() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let attestor = Keypair::new();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            attestor: attestor.pubkey(),
+            min_attestation_level: 2,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::AttestationRequired,
+    );
+
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let attestation = Attestation {
+        wallet: player.pubkey(),
+        expires_at: now + 3_600,
+        level: 2,
+    };
+
+    // Only the pool attestor can attest the players
+    let tx = helpers::register_attestation_tx(
+        &player,
+        &pool_authority.pubkey(),
+        &Keypair::new(),
+        attestation.clone(),
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidAttestation,
+    );
+
+    // An attestation below the pool level is cached but doesn't let the player in
+    let tx = helpers::register_attestation_tx(
+        &player,
+        &pool_authority.pubkey(),
+        &attestor,
+        Attestation {
+            level: 1,
+            ..attestation.clone()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx =
+        helpers::purchase_tickets_tx(&pool_authority.pubkey(), &player, 0, &[1], recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::AttestationRequired,
+    );
+
+    let tx = helpers::register_attestation_tx(
+        &player,
+        &pool_authority.pubkey(),
+        &attestor,
+        attestation.clone(),
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let player_pda = context
+        .banks_client
+        .get_account(find_player_pda_account(&solana_lottery_program::ID, &player.pubkey()).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        player_pda.data.len(),
+        ATTESTED_PLAYER_ACCOUNT_DATA_SIZE as usize
+    );
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx =
+        helpers::purchase_tickets_tx(&pool_authority.pubkey(), &player, 0, &[1], recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The prize can't be claimed once the attestation has expired
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = attestation.expires_at + 1;
+    context.set_sysvar(&clock);
+
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::AttestationRequired,
+    );
+
+    let tx = helpers::register_attestation_tx(
+        &player,
+        &pool_authority.pubkey(),
+        &attestor,
+        Attestation {
+            expires_at: clock.unix_timestamp + 3_600,
+            ..attestation
+        },
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+}
+
+#[test]
+fn logged_events_are_decoded() {
+    let event = LotteryEvent::TicketsPurchased {
+        pool_vault: Pubkey::new_unique(),
+        round_id: 3,
+        player: Pubkey::new_unique(),
+        payer: Pubkey::new_unique(),
+        first_ticket: 7,
+        tickets: 2,
+        price: 2 * LAMPORTS_PER_SOL,
+    };
+    let data = borsh::to_vec(&event).unwrap();
+
+    assert_eq!(LotteryEvent::decode(&[EVENT_TAG, &data]), Some(event));
+
+    // The data logged by other programs is skipped
+    assert_eq!(LotteryEvent::decode(&[&data]), None);
+    assert_eq!(LotteryEvent::decode(&[b"other", &data]), None);
+    assert_eq!(LotteryEvent::decode(&[EVENT_TAG, &data[1..]]), None);
+}
+
+#[tokio::test]
+async fn purchases_and_settlements_log_their_events() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            house_fee_bps: 1_000,
+            crank_tip_bps: 1_000,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_ticket_data_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        TicketAccountData {
+            merkle_root: [0; 32],
+            total_tickets: 1,
+        },
+        context.last_blockhash,
+    );
+    assert_eq!(
+        helpers::process_transaction_events(&mut context.banks_client, tx).await,
+        vec![LotteryEvent::TicketsPurchased {
+            pool_vault: pool_vault_account,
+            round_id: 0,
+            player: player.pubkey(),
+            payer: player.pubkey(),
+            first_ticket: 0,
+            tickets: 1,
+            price: LAMPORTS_PER_SOL,
+        }]
+    );
+
+    context.warp_to_slot(51).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        0,
+        recent_blockhash,
+    );
+    let events = helpers::process_transaction_events(&mut context.banks_client, tx).await;
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_data = RoundData::load(&round.data).unwrap();
+
+    // The house keeps 10% of the 1 SOL raised and the cranker gets 10% of that
+    assert_eq!(
+        events,
+        vec![LotteryEvent::RoundSettled {
+            pool_vault: pool_vault_account,
+            round_id: 0,
+            tickets_sold: 1,
+            winning_ticket: round_data.winning_ticket,
+            prize: round_data.prize,
+            house_fee: LAMPORTS_PER_SOL / 10,
+            crank_tip: LAMPORTS_PER_SOL / 100,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn draws_log_their_events() {
+    let (mut context, player, other_player) = helpers::setup_draw().await;
+    let pool_authority = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+
+    // The prize pool is the 2 SOL raised minus the 10% house fee
+    let prize_pool = 2 * LAMPORTS_PER_SOL * 9 / 10;
+
+    let tx = helpers::begin_draw_tx(&pool_authority, 0, recent_blockhash);
+    assert_eq!(
+        helpers::process_transaction_events(&mut context.banks_client, tx).await,
+        vec![LotteryEvent::DrawStarted {
+            pool_vault: pool_vault_account,
+            round_id: 0,
+            prize_pool,
+        }]
+    );
+
+    let tx = helpers::pay_draw_winners_tx(
+        &pool_authority,
+        0,
+        vec![
+            helpers::draw_winner(&player, prize_pool / 2),
+            helpers::draw_winner(&other_player, prize_pool / 2),
+        ],
+        recent_blockhash,
+    );
+    assert_eq!(
+        helpers::process_transaction_events(&mut context.banks_client, tx).await,
+        [&player, &other_player]
+            .iter()
+            .map(|winner| LotteryEvent::WinnerPaid {
+                pool_vault: pool_vault_account,
+                winner: find_player_pda_account(&solana_lottery_program::ID, &winner.pubkey()).0,
+                amount: prize_pool / 2,
+            })
+            .collect::<Vec<_>>()
+    );
+
+    let tx = helpers::finalize_draw_tx(&pool_authority, 0, recent_blockhash);
+    assert_eq!(
+        helpers::process_transaction_events(&mut context.banks_client, tx).await,
+        vec![LotteryEvent::DrawFinalized {
+            pool_vault: pool_vault_account,
+            round_id: 0,
+            winners: 2,
+            prize_pool,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn airdropped_draws_log_their_settlement() {
+    let (mut context, player, _) = helpers::setup_draw().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+
+    // Half of the prize pool, the 2 SOL raised minus the 10% house fee, is paid out
+    let prize = LAMPORTS_PER_SOL * 9 / 10;
+
+    let tx = helpers::process_winners_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(&player, prize)],
+        context.last_blockhash,
+    );
+    assert_eq!(
+        helpers::process_transaction_events(&mut context.banks_client, tx).await,
+        vec![
+            LotteryEvent::RoundSettled {
+                pool_vault: pool_vault_account,
+                round_id: 0,
+                tickets_sold: 2,
+                winning_ticket: 0,
+                prize,
+                house_fee: 2 * LAMPORTS_PER_SOL / 10,
+                crank_tip: 0,
+            },
+            LotteryEvent::WinnerPaid {
+                pool_vault: pool_vault_account,
+                winner: find_player_pda_account(&solana_lottery_program::ID, &player.pubkey()).0,
+                amount: prize,
+            },
+        ]
+    );
+
+    // The unpaid half of the prize pool goes to the jackpot
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        PoolStorageData::load(&pool_vault.data).unwrap().jackpot,
+        prize
+    );
+}
+
+#[tokio::test]
+async fn views_return_the_pool_player_and_round_summaries() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let viewer = context.payer.insecure_clone();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            draw_buffer_slots: 5,
+            house_fee_bps: 1_000,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx =
+        helpers::get_pool_info_tx(&viewer, &pool_authority.pubkey(), 0, context.last_blockhash);
+    let pool_info: PoolInfo = helpers::simulate_view(&mut context.banks_client, tx).await;
+    assert_eq!(pool_info.current_round_id, 0);
+    assert_eq!(pool_info.round_status, None);
+    assert_eq!(pool_info.ticket_price, LAMPORTS_PER_SOL);
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::quote_ticket_price_tx(
+        &viewer,
+        &pool_authority.pubkey(),
+        0,
+        3,
+        context.last_blockhash,
+    );
+    let quote: TicketQuote = helpers::simulate_view(&mut context.banks_client, tx).await;
+    assert_eq!(
+        quote,
+        TicketQuote {
+            round_id: 0,
+            ticket_price: LAMPORTS_PER_SOL,
+            count: 3,
+            total_price: 3 * LAMPORTS_PER_SOL,
+            selling: true,
+        }
+    );
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1, 2],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The house keeps 10% of the sales
+    let prize = 2 * LAMPORTS_PER_SOL * 9 / 10;
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::get_pool_info_tx(&viewer, &pool_authority.pubkey(), 0, recent_blockhash);
+    let pool_info: PoolInfo = helpers::simulate_view(&mut context.banks_client, tx).await;
+    assert_eq!(pool_info.round_status, Some(RoundStatus::Open));
+    assert_eq!(pool_info.tickets_sold, 2);
+    assert_eq!(pool_info.prize_pool, prize);
+
+    let tx = helpers::get_player_info_tx(
+        &viewer,
+        &player.pubkey(),
+        &pool_authority.pubkey(),
+        0,
+        recent_blockhash,
+    );
+    let player_info: PlayerInfo = helpers::simulate_view(&mut context.banks_client, tx).await;
+    assert_eq!(player_info.round_tickets, 2);
+    assert_eq!(player_info.total_tickets, 2);
+    assert_eq!(player_info.claimable, 0);
+
+    context.warp_to_slot(56).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::get_round_result_tx(&viewer, &pool_authority.pubkey(), 0, recent_blockhash);
+    let round_result: RoundResult = helpers::simulate_view(&mut context.banks_client, tx).await;
+    assert_eq!(round_result.status, RoundStatus::Settled);
+    assert_eq!(round_result.tickets_sold, 2);
+    assert!(round_result.winning_ticket.is_some_and(|ticket| ticket < 2));
+    assert_eq!(round_result.prize, prize);
+    assert!(!round_result.prize_claimed);
+
+    // The player holds every ticket, so it can claim the prize
+    let tx = helpers::get_player_info_tx(
+        &viewer,
+        &player.pubkey(),
+        &pool_authority.pubkey(),
+        0,
+        recent_blockhash,
+    );
+    let player_info: PlayerInfo = helpers::simulate_view(&mut context.banks_client, tx).await;
+    assert_eq!(player_info.claimable, prize);
+}
+
+#[tokio::test]
+async fn legacy_accounts_are_migrated_in_place() {
+    let player = Keypair::new();
+    let pool_authority = Keypair::new();
+    let rent = Rent::default();
+
+    // A pool vault and a player account of the first release: the 24-byte pool storage holding the
+    // ticket price, draft count and initial amount, and the 40-byte ticket data of the player
+    let (pool_vault_address, _) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let legacy_pool = [
+        LAMPORTS_PER_SOL.to_le_bytes(),
+        3u64.to_le_bytes(),
+        (2 * LAMPORTS_PER_SOL).to_le_bytes(),
+    ]
+    .concat();
+    let (player_pda_address, ..) =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let legacy_player = [[7; 32].as_slice(), &5u64.to_le_bytes()].concat();
+
+    assert_eq!(legacy_pool.len(), 24);
+    assert_eq!(legacy_player.len(), 40);
+
+    // The receipt mint of the first release, a bare mint without any extension
+    let (pool_mint_address, _) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let mut legacy_mint = vec![0; Mint::LEN];
+    Mint::pack(
+        Mint {
+            mint_authority: COption::Some(pool_mint_address),
+            decimals: 0,
+            is_initialized: true,
+            ..Default::default()
+        },
+        &mut legacy_mint,
+    )
+    .unwrap();
+
+    let mut accounts = vec![(
+        pool_authority.pubkey(),
+        solana_sdk::account::Account::new(100 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+    )];
+
+    for (address, data, owner) in [
+        (pool_vault_address, legacy_pool, solana_lottery_program::ID),
+        (
+            player_pda_address,
+            legacy_player,
+            solana_lottery_program::ID,
+        ),
+        (pool_mint_address, legacy_mint, spl_token_2022::id()),
+    ] {
+        accounts.push((
+            address,
+            solana_sdk::account::Account {
+                lamports: rent.minimum_balance(data.len()),
+                data,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            },
+        ));
+    }
+
+    let mut context = helpers::setup_with_accounts(&player, accounts).await;
+    let payer = context.payer.insecure_clone();
+
+    let tx =
+        helpers::set_player_limits_tx(&player, PlayerLimits::default(), context.last_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidAccountType,
+    );
+
+    for address in [pool_vault_address, player_pda_address] {
+        let tx = helpers::migrate_account_tx(&payer, &address, context.last_blockhash);
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_vault.data.len(), POOL_STORAGE_SIZE as usize);
+    assert!(rent.is_exempt(pool_vault.lamports, pool_vault.data.len()));
+    assert_eq!(pool_storage.ticket_price, LAMPORTS_PER_SOL);
+    assert_eq!(pool_storage.draft_count, 3);
+    assert_eq!(pool_storage.initial_amount, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(pool_storage.mode, PoolMode::Lottery);
+    assert_eq!(pool_storage.house_fee_bps, 0);
+
+    let player_pda = context
+        .banks_client
+        .get_account(player_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let ticket_data = TicketAccountData::load(&player_pda.data).unwrap();
+
+    assert_eq!(player_pda.data.len(), TICKET_ACCOUNT_DATA_SIZE as usize);
+    assert_eq!(ticket_data.merkle_root, [7; 32]);
+    assert_eq!(ticket_data.total_tickets, 5);
+    // The header tells the account types apart
+    assert!(PoolStorageData::load(&player_pda.data).is_err());
+
+    // Migrating an account already up to date does nothing
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::migrate_account_tx(&payer, &player_pda_address, blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_account(player_pda_address)
+            .await
+            .unwrap()
+            .unwrap()
+            .data,
+        player_pda.data
+    );
+
+    let tx = helpers::set_player_limits_tx(&player, PlayerLimits::default(), blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The migrated pool keeps its legacy mint, rounds open and sell tickets all the same
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        3,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(&pool_authority.pubkey(), &player, 3, &[6], blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (player_token_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let player_token_account = context
+        .banks_client
+        .get_account(player_token_address)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        StateWithExtensions::<Account>::unpack(&player_token_account.data)
+            .unwrap()
+            .base
+            .amount,
+        1
+    );
+
+    context.warp_to_slot(51).unwrap();
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::settle_round_tx(&player, &pool_authority.pubkey(), 3, blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::claim_prize_tx(&player, &pool_authority.pubkey(), 3, blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The pool closes, leaving the legacy mint behind as it has no close authority
+    let tx = helpers::close_pool_tx(&pool_authority, 4, &[], blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert!(context
+        .banks_client
+        .get_account(pool_vault_address)
+        .await
+        .unwrap()
+        .is_none());
+
+    let pool_mint = context
+        .banks_client
+        .get_account(pool_mint_address)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(Mint::unpack(&pool_mint.data).unwrap().supply, 0);
+}
+
+#[tokio::test]
+async fn purchases_reject_the_accounts_of_another_pool_or_player() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let other_authority = player.insecure_clone();
+
+    for authority in [&pool_authority, &other_authority] {
+        let tx = helpers::initialize_pool_tx(
+            &solana_lottery_program::ID,
+            authority,
+            PoolStorageData {
+                ticket_price: LAMPORTS_PER_SOL,
+                initial_amount: LAMPORTS_PER_SOL,
+                ..Default::default()
+            },
+            &context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 1_000,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (other_pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &other_authority.pubkey());
+    let (pool_mint_account, ..) =
+        find_stake_pool_mint_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (player_pda_address, ..) =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &player.pubkey());
+    let ticket_data = LotoInstruction::PurchaseTicket(TicketAccountData {
+        merkle_root: [0; 32],
+        total_tickets: 1,
+    });
+
+    // The vault of another pool can't stand in for the vault of the authority
+    let tx = helpers::purchase_ticket_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        &player,
+        player_pda_address,
+        player_token_pda_address,
+        other_pool_vault_account,
+        pool_mint_account,
+        round_account,
+        context.last_blockhash,
+        &ticket_data,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidStakePoolVault,
+    );
+
+    // Nor can the PDA of another player
+    let tx = helpers::purchase_ticket_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        &player,
+        find_player_pda_account(&solana_lottery_program::ID, &pool_authority.pubkey()).0,
+        player_token_pda_address,
+        pool_vault_account,
+        pool_mint_account,
+        round_account,
+        context.last_blockhash,
+        &ticket_data,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidPlayerPdaAccount,
+    );
+
+    let tx = helpers::purchase_ticket_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        &player,
+        player_pda_address,
+        player_token_pda_address,
+        pool_vault_account,
+        pool_mint_account,
+        round_account,
+        context.last_blockhash,
+        &ticket_data,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+}
+
+#[tokio::test]
+async fn deposits_are_added_to_the_pool_vault() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let tx = initialize_stake_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let vault_balance = context
+        .banks_client
+        .get_balance(pool_vault_account)
+        .await
+        .unwrap();
+
+    let tx = helpers::deposit_tx(
+        &player,
+        &pool_authority.pubkey(),
+        LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(pool_vault_account)
+            .await
+            .unwrap(),
+        vault_balance + LAMPORTS_PER_SOL
+    );
+}
+
+#[tokio::test]
+async fn partner_programs_buy_tickets_for_their_pdas_through_cpi() {
+    let (mut context, ticket_buyer_program, buyer) = helpers::setup_ticket_buyer().await;
+    let pool_authority = context.payer.insecure_clone();
+    let cranker = context.payer.insecure_clone();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 1_000,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buyer_balance = context.banks_client.get_balance(buyer).await.unwrap();
+
+    let tx = helpers::ticket_buyer_purchase_tx(
+        &cranker,
+        &ticket_buyer_program,
+        &buyer,
+        &pool_authority.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (round_tickets_address, ..) =
+        find_round_tickets_pda(&solana_lottery_program::ID, &round_account, &buyer);
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let round_tickets = RoundTicketsData::load(&round_tickets.data).unwrap();
+
+    assert_eq!(round_tickets.owner, buyer);
+    assert_eq!(round_tickets.tickets, 1);
+
+    let (player_token_pda_address, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &buyer);
+    let token_account = context
+        .banks_client
+        .get_account(player_token_pda_address)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        StateWithExtensions::<Account>::unpack(&token_account.data)
+            .unwrap()
+            .base
+            .amount,
+        1
+    );
+
+    // The PDA paid for the ticket and the rent of the player accounts
+    assert!(
+        context.banks_client.get_balance(buyer).await.unwrap() < buyer_balance - LAMPORTS_PER_SOL
+    );
+}
+
+#[tokio::test]
+async fn capped_pools_reject_cpi_purchases() {
+    let (mut context, ticket_buyer_program, buyer) = helpers::setup_ticket_buyer().await;
+    let pool_authority = context.payer.insecure_clone();
+    let cranker = context.payer.insecure_clone();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            max_tickets_per_transaction: 2,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 1_000,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The partner program could buy in several instructions of the transaction, each within the cap
+    let tx = helpers::ticket_buyer_purchase_tx(
+        &cranker,
+        &ticket_buyer_program,
+        &buyer,
+        &pool_authority.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::CpiPurchaseNotAllowed,
+    );
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(RoundData::load(&round.data).unwrap().tickets_sold, 0);
+}
+
+#[tokio::test]
+async fn draw_sessions_pay_winners_across_transactions() {
+    let (mut context, player, other_player) = helpers::setup_draw().await;
+    let pool_authority = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (draw_session_account, ..) =
+        find_draw_session_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::begin_draw_tx(&pool_authority, 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        RoundData::load(&round.data).unwrap().status,
+        RoundStatus::Settled
+    );
+
+    // The prize pool is the 2 SOL raised minus the 10% house fee
+    let prize_pool = 2 * LAMPORTS_PER_SOL * 9 / 10;
+    let draw_session = context
+        .banks_client
+        .get_account(draw_session_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        DrawSessionData::load(&draw_session.data)
+            .unwrap()
+            .prize_pool,
+        prize_pool
+    );
+
+    let player_pda_address =
+        find_player_pda_account(&solana_lottery_program::ID, &player.pubkey()).0;
+    let previous_lamports = context
+        .banks_client
+        .get_balance(player_pda_address)
+        .await
+        .unwrap();
+
+    let tx = helpers::pay_draw_winners_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(&player, LAMPORTS_PER_SOL)],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(player_pda_address)
+            .await
+            .unwrap(),
+        previous_lamports + LAMPORTS_PER_SOL
+    );
+
+    // A winner is paid once per draw
+    let tx = helpers::pay_draw_winners_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(&player, LAMPORTS_PER_SOL / 2)],
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::WinnerAlreadyPaid,
+    );
+
+    // The draw can't be finalized before the whole prize pool is paid
+    let tx = helpers::finalize_draw_tx(&pool_authority, 0, recent_blockhash);
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::DrawTotalsMismatch,
+    );
+
+    // Nor can the payouts exceed it
+    let tx = helpers::pay_draw_winners_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(&other_player, LAMPORTS_PER_SOL)],
+        recent_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::DrawBudgetExceeded,
+    );
+
+    let tx = helpers::pay_draw_winners_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(
+            &other_player,
+            prize_pool - LAMPORTS_PER_SOL,
+        )],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = helpers::finalize_draw_tx(&pool_authority, 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert!(context
+        .banks_client
+        .get_account(draw_session_account)
+        .await
+        .unwrap()
+        .is_none());
+
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, 0);
+    assert_eq!(pool_storage.house_balance, 2 * LAMPORTS_PER_SOL / 10);
+}
+
+#[tokio::test]
+async fn draw_sessions_are_aborted_into_the_jackpot() {
+    let (mut context, player, _) = helpers::setup_draw().await;
+    let pool_authority = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (draw_session_account, ..) =
+        find_draw_session_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+
+    let tx = helpers::begin_draw_tx(&pool_authority, 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // The winner accounts must be passed along with the winners
+    let tx = helpers::pay_draw_winners_without_accounts_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(&player, LAMPORTS_PER_SOL)],
+        recent_blockhash,
+    );
+    helpers::assert_instruction_error(
+        context.banks_client.process_transaction(tx).await,
+        InstructionError::NotEnoughAccountKeys,
+    );
+
+    let tx = helpers::pay_draw_winners_tx(
+        &pool_authority,
+        0,
+        vec![helpers::draw_winner(&player, LAMPORTS_PER_SOL / 2)],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let authority_balance = context
+        .banks_client
+        .get_balance(pool_authority.pubkey())
+        .await
+        .unwrap();
+    let session_rent = context
+        .banks_client
+        .get_balance(draw_session_account)
+        .await
+        .unwrap();
+
+    let tx = helpers::abort_draw_tx(&pool_authority, 0, recent_blockhash);
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    assert!(context
+        .banks_client
+        .get_account(draw_session_account)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(pool_authority.pubkey())
+            .await
+            .unwrap(),
+        authority_balance + session_rent - 5_000
+    );
+
+    // The unpaid budget is carried over to the prize of the next round
+    let pool_vault = context
+        .banks_client
+        .get_account(pool_vault_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_storage = PoolStorageData::load(&pool_vault.data).unwrap();
+
+    assert_eq!(pool_storage.liabilities, 0);
+    assert_eq!(
+        pool_storage.jackpot,
+        2 * LAMPORTS_PER_SOL * 9 / 10 - LAMPORTS_PER_SOL / 2
+    );
+}
+
+#[tokio::test]
+async fn refunds_reject_the_token_account_of_another_player() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+    let other_player = Keypair::new();
+
+    let tx = helpers::fund_account_tx(
+        &pool_authority,
+        &other_player.pubkey(),
+        10 * LAMPORTS_PER_SOL,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    for buyer in [&player, &other_player] {
+        let tx = helpers::purchase_tickets_tx(
+            &pool_authority.pubkey(),
+            buyer,
+            0,
+            &[2],
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let tx = helpers::cancel_round_tx(
+        &pool_authority,
+        &pool_authority.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let (other_token_account, ..) =
+        find_player_token_pda_account(&solana_lottery_program::ID, &other_player.pubkey());
+    let tx = helpers::payout_with_token_account_tx(
+        instruction::refund_tickets,
+        &player,
+        &pool_authority.pubkey(),
+        0,
+        other_token_account,
+        context.last_blockhash,
+    );
+    helpers::assert_lottery_error(
+        context.banks_client.process_transaction(tx).await,
+        LotteryError::InvalidPlayerPdaAccount,
+    );
+
+    // The receipts of the other player are left for its own refund
+    let tx = helpers::refund_tickets_tx(
+        &other_player,
+        &pool_authority.pubkey(),
+        0,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let token_account = context
+        .banks_client
+        .get_account(other_token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        StateWithExtensions::<Account>::unpack(&token_account.data)
+            .unwrap()
+            .base
+            .amount,
+        0
+    );
+}
+
+#[tokio::test]
+async fn pre_funded_pdas_are_created_all_the_same() {
+    let (mut context, player) = helpers::setup_with_context().await;
+    let pool_authority = context.payer.insecure_clone();
+
+    let (pool_vault_account, ..) =
+        find_stake_pool_vault_pda(&solana_lottery_program::ID, &pool_authority.pubkey());
+    let (round_account, ..) = find_round_pda(&solana_lottery_program::ID, &pool_vault_account, 0);
+    let (round_tickets_account, ..) = find_round_tickets_pda(
+        &solana_lottery_program::ID,
+        &round_account,
+        &player.pubkey(),
+    );
+
+    let tx = helpers::initialize_pool_tx(
+        &solana_lottery_program::ID,
+        &pool_authority,
+        PoolStorageData {
+            ticket_price: LAMPORTS_PER_SOL,
+            initial_amount: LAMPORTS_PER_SOL,
+            ..Default::default()
+        },
+        &context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+
+    // Anyone can send lamports to the addresses of the next round and of its round tickets
+    for account in [round_account, round_tickets_account] {
+        let tx = helpers::fund_account_tx(
+            &player,
+            &account,
+            rent.minimum_balance(0),
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let tx = helpers::open_round_tx(
+        &pool_authority,
+        0,
+        RoundSchedule {
+            sales_open_slot: 0,
+            sales_close_slot: 50,
+            ..Default::default()
+        },
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = helpers::purchase_tickets_tx(
+        &pool_authority.pubkey(),
+        &player,
+        0,
+        &[1],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let round = context
+        .banks_client
+        .get_account(round_account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(round.owner, solana_lottery_program::ID);
+    assert!(rent.is_exempt(round.lamports, round.data.len()));
+    assert_eq!(
+        RoundData::load(&round.data).unwrap().status,
+        RoundStatus::Open
+    );
+
+    let round_tickets = context
+        .banks_client
+        .get_account(round_tickets_account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(round_tickets.owner, solana_lottery_program::ID);
+    assert!(rent.is_exempt(round_tickets.lamports, round_tickets.data.len()));
+    assert_eq!(
+        RoundTicketsData::load(&round_tickets.data).unwrap().tickets,
+        1
+    );
+}